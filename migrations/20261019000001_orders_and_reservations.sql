@@ -0,0 +1,51 @@
+-- Products, orders and stock reservations.
+-- Money is stored in paise (BIGINT) to avoid floating point rounding.
+
+CREATE TABLE IF NOT EXISTS products (
+    id SERIAL PRIMARY KEY,
+    admin_id INTEGER NOT NULL,
+    sku VARCHAR(64) NOT NULL UNIQUE,
+    name VARCHAR(255) NOT NULL,
+    unit_price_paise BIGINT NOT NULL CHECK (unit_price_paise >= 0),
+    stock_on_hand INTEGER NOT NULL DEFAULT 0 CHECK (stock_on_hand >= 0),
+    stock_reserved INTEGER NOT NULL DEFAULT 0 CHECK (stock_reserved >= 0),
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    CHECK (stock_reserved <= stock_on_hand)
+);
+
+CREATE TABLE IF NOT EXISTS orders (
+    id SERIAL PRIMARY KEY,
+    admin_id INTEGER NOT NULL,
+    customer_email VARCHAR(255) NOT NULL,
+    status VARCHAR(32) NOT NULL DEFAULT 'pending_payment',
+    total_paise BIGINT NOT NULL DEFAULT 0,
+    payment_due_at TIMESTAMP NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS orders_pending_due_idx
+    ON orders (payment_due_at)
+    WHERE status = 'pending_payment';
+
+CREATE TABLE IF NOT EXISTS order_lines (
+    id SERIAL PRIMARY KEY,
+    order_id INTEGER NOT NULL REFERENCES orders (id),
+    product_id INTEGER NOT NULL REFERENCES products (id),
+    quantity INTEGER NOT NULL CHECK (quantity > 0),
+    unit_price_paise BIGINT NOT NULL,
+    line_total_paise BIGINT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS stock_reservations (
+    id SERIAL PRIMARY KEY,
+    order_id INTEGER NOT NULL REFERENCES orders (id),
+    product_id INTEGER NOT NULL REFERENCES products (id),
+    quantity INTEGER NOT NULL CHECK (quantity > 0),
+    status VARCHAR(16) NOT NULL DEFAULT 'active',
+    expires_at TIMESTAMP NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    released_at TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS stock_reservations_order_idx ON stock_reservations (order_id);
@@ -0,0 +1,124 @@
+-- Multi-warehouse inventory backed by an append-only stock ledger.
+-- products.stock_on_hand / stock_reserved stay as product-wide totals and are
+-- updated in the same transaction as every ledger posting.
+
+CREATE TABLE IF NOT EXISTS warehouses (
+    id SERIAL PRIMARY KEY,
+    admin_id INTEGER NOT NULL,
+    code VARCHAR(32) NOT NULL,
+    name VARCHAR(255) NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    UNIQUE (admin_id, code)
+);
+
+CREATE TABLE IF NOT EXISTS stock_movements (
+    id BIGSERIAL PRIMARY KEY,
+    product_id INTEGER NOT NULL REFERENCES products (id),
+    warehouse_id INTEGER NOT NULL REFERENCES warehouses (id),
+    movement_type VARCHAR(16) NOT NULL
+        CHECK (movement_type IN ('receipt', 'issue', 'transfer', 'adjustment', 'reservation')),
+    on_hand_delta INTEGER NOT NULL DEFAULT 0,
+    reserved_delta INTEGER NOT NULL DEFAULT 0,
+    reference VARCHAR(64),
+    note TEXT,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS stock_movements_product_warehouse_idx
+    ON stock_movements (product_id, warehouse_id);
+
+-- The ledger is append-only: corrections are new 'adjustment' rows
+CREATE OR REPLACE FUNCTION stock_movements_append_only() RETURNS trigger AS $$
+BEGIN
+    RAISE EXCEPTION 'stock_movements is append-only';
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS stock_movements_no_update ON stock_movements;
+CREATE TRIGGER stock_movements_no_update
+    BEFORE UPDATE OR DELETE ON stock_movements
+    FOR EACH ROW EXECUTE FUNCTION stock_movements_append_only();
+
+-- Current stock per product per warehouse, derived from the ledger
+CREATE OR REPLACE VIEW warehouse_stock AS
+SELECT
+    m.product_id,
+    m.warehouse_id,
+    SUM(m.on_hand_delta)::BIGINT AS on_hand,
+    SUM(m.reserved_delta)::BIGINT AS reserved,
+    (SUM(m.on_hand_delta) - SUM(m.reserved_delta))::BIGINT AS available
+FROM stock_movements m
+GROUP BY m.product_id, m.warehouse_id;
+
+CREATE TABLE IF NOT EXISTS stock_thresholds (
+    product_id INTEGER NOT NULL REFERENCES products (id),
+    warehouse_id INTEGER NOT NULL REFERENCES warehouses (id),
+    min_quantity INTEGER NOT NULL CHECK (min_quantity >= 0),
+    PRIMARY KEY (product_id, warehouse_id)
+);
+
+-- Orders are fulfilled from one warehouse; older rows predate warehouses
+ALTER TABLE orders ADD COLUMN IF NOT EXISTS warehouse_id INTEGER REFERENCES warehouses (id);
+ALTER TABLE stock_reservations ADD COLUMN IF NOT EXISTS warehouse_id INTEGER REFERENCES warehouses (id);
+
+-- Opening balances: stock recorded on products before the ledger existed
+-- goes into each admin's first warehouse, or a DEFAULT one created for them
+INSERT INTO warehouses (admin_id, code, name)
+SELECT DISTINCT p.admin_id, 'DEFAULT', 'Default warehouse'
+FROM products p
+WHERE (p.stock_on_hand <> 0 OR p.stock_reserved <> 0)
+  AND NOT EXISTS (SELECT 1 FROM warehouses w WHERE w.admin_id = p.admin_id);
+
+CREATE TEMPORARY TABLE opening_warehouses AS
+SELECT DISTINCT ON (w.admin_id) w.admin_id, w.id AS warehouse_id
+FROM warehouses w
+ORDER BY w.admin_id, w.id;
+
+-- Still-active reservations must account for every reserved unit, otherwise
+-- the ledger could not match the product totals
+DO $$
+DECLARE
+    drifted INTEGER;
+BEGIN
+    SELECT p.id INTO drifted
+    FROM products p
+    WHERE p.stock_reserved <> COALESCE((
+        SELECT SUM(r.quantity)
+        FROM stock_reservations r
+        WHERE r.product_id = p.id AND r.status = 'active' AND r.warehouse_id IS NULL
+    ), 0)
+    LIMIT 1;
+
+    IF drifted IS NOT NULL THEN
+        RAISE EXCEPTION 'product % has stock_reserved that does not match its active reservations', drifted;
+    END IF;
+END;
+$$;
+
+-- Unreserved stock as one adjustment per product
+INSERT INTO stock_movements (product_id, warehouse_id, movement_type, on_hand_delta, reserved_delta, note)
+SELECT p.id, o.warehouse_id, 'adjustment', p.stock_on_hand - p.stock_reserved, 0, 'opening balance'
+FROM products p
+JOIN opening_warehouses o ON o.admin_id = p.admin_id
+WHERE p.stock_on_hand - p.stock_reserved <> 0;
+
+-- Reserved stock moves in with its reservation, which then settles through the ledger
+INSERT INTO stock_movements (product_id, warehouse_id, movement_type, on_hand_delta, reserved_delta, reference, note)
+SELECT r.product_id, o.warehouse_id, 'reservation', r.quantity, r.quantity, 'order:' || r.order_id, 'opening balance'
+FROM stock_reservations r
+JOIN products p ON p.id = r.product_id
+JOIN opening_warehouses o ON o.admin_id = p.admin_id
+WHERE r.status = 'active' AND r.warehouse_id IS NULL;
+
+UPDATE stock_reservations r
+SET warehouse_id = o.warehouse_id
+FROM products p, opening_warehouses o
+WHERE p.id = r.product_id AND o.admin_id = p.admin_id
+  AND r.status = 'active' AND r.warehouse_id IS NULL;
+
+UPDATE orders ord
+SET warehouse_id = r.warehouse_id
+FROM stock_reservations r
+WHERE r.order_id = ord.id AND r.status = 'active' AND ord.warehouse_id IS NULL;
+
+DROP TABLE opening_warehouses;
@@ -0,0 +1,60 @@
+-- Price lists with quantity breaks, automatic discounts and coupons.
+-- Percentages are stored in basis points (1000 = 10%), amounts in paise.
+
+CREATE TABLE IF NOT EXISTS price_lists (
+    id SERIAL PRIMARY KEY,
+    admin_id INTEGER NOT NULL,
+    name VARCHAR(255) NOT NULL,
+    -- NULL means the list applies to every customer of the admin
+    customer_email VARCHAR(255),
+    valid_from TIMESTAMP,
+    valid_to TIMESTAMP,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE IF NOT EXISTS price_list_items (
+    id SERIAL PRIMARY KEY,
+    price_list_id INTEGER NOT NULL REFERENCES price_lists (id),
+    product_id INTEGER NOT NULL REFERENCES products (id),
+    min_quantity INTEGER NOT NULL DEFAULT 1 CHECK (min_quantity > 0),
+    unit_price_paise BIGINT NOT NULL CHECK (unit_price_paise >= 0),
+    UNIQUE (price_list_id, product_id, min_quantity)
+);
+
+CREATE TABLE IF NOT EXISTS discounts (
+    id SERIAL PRIMARY KEY,
+    admin_id INTEGER NOT NULL,
+    name VARCHAR(255) NOT NULL,
+    -- NULL means the discount applies to every product of the admin
+    product_id INTEGER REFERENCES products (id),
+    kind VARCHAR(16) NOT NULL CHECK (kind IN ('percent', 'fixed')),
+    value BIGINT NOT NULL CHECK (value > 0),
+    min_quantity INTEGER NOT NULL DEFAULT 1,
+    valid_from TIMESTAMP,
+    valid_to TIMESTAMP,
+    active BOOLEAN NOT NULL DEFAULT TRUE,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE IF NOT EXISTS coupons (
+    id SERIAL PRIMARY KEY,
+    admin_id INTEGER NOT NULL,
+    code VARCHAR(64) NOT NULL,
+    kind VARCHAR(16) NOT NULL CHECK (kind IN ('percent', 'fixed')),
+    value BIGINT NOT NULL CHECK (value > 0),
+    max_uses INTEGER,
+    used_count INTEGER NOT NULL DEFAULT 0,
+    valid_from TIMESTAMP,
+    valid_to TIMESTAMP,
+    active BOOLEAN NOT NULL DEFAULT TRUE,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    UNIQUE (admin_id, code)
+);
+
+ALTER TABLE orders ADD COLUMN IF NOT EXISTS coupon_id INTEGER REFERENCES coupons (id);
+ALTER TABLE orders ADD COLUMN IF NOT EXISTS discount_paise BIGINT NOT NULL DEFAULT 0;
+
+-- Each line keeps the full computation so totals can be audited later
+ALTER TABLE order_lines ADD COLUMN IF NOT EXISTS list_unit_price_paise BIGINT;
+ALTER TABLE order_lines ADD COLUMN IF NOT EXISTS discount_paise BIGINT NOT NULL DEFAULT 0;
+ALTER TABLE order_lines ADD COLUMN IF NOT EXISTS pricing JSONB;
@@ -0,0 +1,60 @@
+-- GST per order line and immutable, sequentially numbered invoices.
+
+ALTER TABLE products ADD COLUMN IF NOT EXISTS hsn_code VARCHAR(8);
+ALTER TABLE products ADD COLUMN IF NOT EXISTS gst_rate_bp INTEGER NOT NULL DEFAULT 1800;
+
+ALTER TABLE orders ADD COLUMN IF NOT EXISTS shipping_pincode VARCHAR(6);
+ALTER TABLE orders ADD COLUMN IF NOT EXISTS origin_state_code VARCHAR(2);
+ALTER TABLE orders ADD COLUMN IF NOT EXISTS place_of_supply VARCHAR(2);
+ALTER TABLE orders ADD COLUMN IF NOT EXISTS tax_paise BIGINT NOT NULL DEFAULT 0;
+ALTER TABLE orders ADD COLUMN IF NOT EXISTS grand_total_paise BIGINT NOT NULL DEFAULT 0;
+
+ALTER TABLE order_lines ADD COLUMN IF NOT EXISTS hsn_code VARCHAR(8);
+ALTER TABLE order_lines ADD COLUMN IF NOT EXISTS gst_rate_bp INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE order_lines ADD COLUMN IF NOT EXISTS cgst_paise BIGINT NOT NULL DEFAULT 0;
+ALTER TABLE order_lines ADD COLUMN IF NOT EXISTS sgst_paise BIGINT NOT NULL DEFAULT 0;
+ALTER TABLE order_lines ADD COLUMN IF NOT EXISTS igst_paise BIGINT NOT NULL DEFAULT 0;
+
+-- One gapless counter per seller per financial year
+CREATE TABLE IF NOT EXISTS invoice_sequences (
+    admin_id INTEGER NOT NULL,
+    financial_year VARCHAR(7) NOT NULL,
+    last_number INTEGER NOT NULL,
+    PRIMARY KEY (admin_id, financial_year)
+);
+
+CREATE TABLE IF NOT EXISTS invoices (
+    id SERIAL PRIMARY KEY,
+    admin_id INTEGER NOT NULL,
+    order_id INTEGER NOT NULL UNIQUE REFERENCES orders (id),
+    invoice_number VARCHAR(32) NOT NULL,
+    financial_year VARCHAR(7) NOT NULL,
+    sequence_number INTEGER NOT NULL,
+    seller_name VARCHAR(255) NOT NULL,
+    seller_pincode VARCHAR(6) NOT NULL,
+    origin_state_code VARCHAR(2) NOT NULL,
+    buyer_email VARCHAR(255) NOT NULL,
+    buyer_pincode VARCHAR(6) NOT NULL,
+    place_of_supply VARCHAR(2) NOT NULL,
+    supply_type VARCHAR(16) NOT NULL,
+    taxable_paise BIGINT NOT NULL,
+    cgst_paise BIGINT NOT NULL,
+    sgst_paise BIGINT NOT NULL,
+    igst_paise BIGINT NOT NULL,
+    total_paise BIGINT NOT NULL,
+    lines JSONB NOT NULL,
+    issued_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    UNIQUE (admin_id, invoice_number)
+);
+
+-- Issued invoices are legal documents; corrections go through credit notes
+CREATE OR REPLACE FUNCTION invoices_immutable() RETURNS trigger AS $$
+BEGIN
+    RAISE EXCEPTION 'invoices cannot be modified once issued';
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS invoices_no_update ON invoices;
+CREATE TRIGGER invoices_no_update
+    BEFORE UPDATE OR DELETE ON invoices
+    FOR EACH ROW EXECUTE FUNCTION invoices_immutable();
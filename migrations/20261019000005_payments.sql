@@ -0,0 +1,24 @@
+-- Payments and refunds against orders, from manual entry or provider webhooks.
+
+CREATE TABLE IF NOT EXISTS payments (
+    id SERIAL PRIMARY KEY,
+    order_id INTEGER NOT NULL REFERENCES orders (id),
+    kind VARCHAR(16) NOT NULL CHECK (kind IN ('payment', 'refund')),
+    method VARCHAR(16) NOT NULL CHECK (method IN ('cash', 'upi', 'card', 'bank_transfer')),
+    amount_paise BIGINT NOT NULL CHECK (amount_paise > 0),
+    -- 'manual' for payments entered by staff, otherwise the webhook provider name
+    provider VARCHAR(32) NOT NULL DEFAULT 'manual',
+    provider_reference VARCHAR(128),
+    note TEXT,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+-- Providers retry webhooks; the same event must only be recorded once
+CREATE UNIQUE INDEX IF NOT EXISTS payments_provider_reference_idx
+    ON payments (provider, provider_reference)
+    WHERE provider_reference IS NOT NULL;
+
+CREATE INDEX IF NOT EXISTS payments_order_idx ON payments (order_id);
+
+ALTER TABLE orders ADD COLUMN IF NOT EXISTS paid_paise BIGINT NOT NULL DEFAULT 0;
+ALTER TABLE orders ADD COLUMN IF NOT EXISTS refunded_paise BIGINT NOT NULL DEFAULT 0;
@@ -0,0 +1,49 @@
+-- Customer returns against delivered orders and the credit notes that offset invoices.
+
+-- Invoices and credit notes are numbered in separate series per financial year
+ALTER TABLE invoice_sequences ADD COLUMN IF NOT EXISTS series VARCHAR(8) NOT NULL DEFAULT 'INV';
+ALTER TABLE invoice_sequences DROP CONSTRAINT IF EXISTS invoice_sequences_pkey;
+ALTER TABLE invoice_sequences ADD PRIMARY KEY (admin_id, series, financial_year);
+
+CREATE TABLE IF NOT EXISTS return_requests (
+    id SERIAL PRIMARY KEY,
+    order_id INTEGER NOT NULL REFERENCES orders (id),
+    admin_id INTEGER NOT NULL,
+    status VARCHAR(16) NOT NULL DEFAULT 'requested'
+        CHECK (status IN ('requested', 'approved', 'rejected')),
+    reason TEXT NOT NULL,
+    decision_note TEXT,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    decided_at TIMESTAMP
+);
+
+CREATE TABLE IF NOT EXISTS return_lines (
+    id SERIAL PRIMARY KEY,
+    return_id INTEGER NOT NULL REFERENCES return_requests (id),
+    order_line_id INTEGER NOT NULL REFERENCES order_lines (id),
+    quantity INTEGER NOT NULL CHECK (quantity > 0)
+);
+
+CREATE TABLE IF NOT EXISTS credit_notes (
+    id SERIAL PRIMARY KEY,
+    admin_id INTEGER NOT NULL,
+    return_id INTEGER NOT NULL UNIQUE REFERENCES return_requests (id),
+    invoice_id INTEGER NOT NULL REFERENCES invoices (id),
+    credit_note_number VARCHAR(32) NOT NULL,
+    financial_year VARCHAR(7) NOT NULL,
+    sequence_number INTEGER NOT NULL,
+    supply_type VARCHAR(16) NOT NULL,
+    taxable_paise BIGINT NOT NULL,
+    cgst_paise BIGINT NOT NULL,
+    sgst_paise BIGINT NOT NULL,
+    igst_paise BIGINT NOT NULL,
+    total_paise BIGINT NOT NULL,
+    lines JSONB NOT NULL,
+    issued_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    UNIQUE (admin_id, credit_note_number)
+);
+
+DROP TRIGGER IF EXISTS credit_notes_no_update ON credit_notes;
+CREATE TRIGGER credit_notes_no_update
+    BEFORE UPDATE OR DELETE ON credit_notes
+    FOR EACH ROW EXECUTE FUNCTION invoices_immutable();
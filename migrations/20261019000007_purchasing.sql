@@ -0,0 +1,70 @@
+-- Buy side: suppliers, purchase orders, goods receipts and supplier invoices.
+-- Goods receipts post 'receipt' rows into the stock ledger; supplier invoices are
+-- matched against the PO and what was actually received.
+
+CREATE TABLE IF NOT EXISTS suppliers (
+    id SERIAL PRIMARY KEY,
+    admin_id INTEGER NOT NULL,
+    name VARCHAR(255) NOT NULL,
+    email VARCHAR(255),
+    gstin VARCHAR(15),
+    pincode VARCHAR(6),
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    UNIQUE (admin_id, name)
+);
+
+CREATE TABLE IF NOT EXISTS purchase_orders (
+    id SERIAL PRIMARY KEY,
+    admin_id INTEGER NOT NULL,
+    supplier_id INTEGER NOT NULL REFERENCES suppliers (id),
+    warehouse_id INTEGER NOT NULL REFERENCES warehouses (id),
+    status VARCHAR(24) NOT NULL DEFAULT 'open'
+        CHECK (status IN ('open', 'partially_received', 'received', 'cancelled')),
+    total_paise BIGINT NOT NULL,
+    note TEXT,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE IF NOT EXISTS purchase_order_lines (
+    id SERIAL PRIMARY KEY,
+    purchase_order_id INTEGER NOT NULL REFERENCES purchase_orders (id),
+    product_id INTEGER NOT NULL REFERENCES products (id),
+    quantity INTEGER NOT NULL CHECK (quantity > 0),
+    unit_cost_paise BIGINT NOT NULL CHECK (unit_cost_paise >= 0),
+    received_quantity INTEGER NOT NULL DEFAULT 0,
+    CHECK (received_quantity BETWEEN 0 AND quantity)
+);
+
+CREATE TABLE IF NOT EXISTS goods_receipts (
+    id SERIAL PRIMARY KEY,
+    purchase_order_id INTEGER NOT NULL REFERENCES purchase_orders (id),
+    warehouse_id INTEGER NOT NULL REFERENCES warehouses (id),
+    note TEXT,
+    received_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE IF NOT EXISTS goods_receipt_lines (
+    id SERIAL PRIMARY KEY,
+    goods_receipt_id INTEGER NOT NULL REFERENCES goods_receipts (id),
+    purchase_order_line_id INTEGER NOT NULL REFERENCES purchase_order_lines (id),
+    quantity INTEGER NOT NULL CHECK (quantity > 0)
+);
+
+CREATE TABLE IF NOT EXISTS supplier_invoices (
+    id SERIAL PRIMARY KEY,
+    purchase_order_id INTEGER NOT NULL REFERENCES purchase_orders (id),
+    supplier_id INTEGER NOT NULL REFERENCES suppliers (id),
+    invoice_number VARCHAR(64) NOT NULL,
+    total_paise BIGINT NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    UNIQUE (supplier_id, invoice_number)
+);
+
+CREATE TABLE IF NOT EXISTS supplier_invoice_lines (
+    id SERIAL PRIMARY KEY,
+    supplier_invoice_id INTEGER NOT NULL REFERENCES supplier_invoices (id),
+    purchase_order_line_id INTEGER NOT NULL REFERENCES purchase_order_lines (id),
+    quantity INTEGER NOT NULL CHECK (quantity > 0),
+    unit_cost_paise BIGINT NOT NULL CHECK (unit_cost_paise >= 0)
+);
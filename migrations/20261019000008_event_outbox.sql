@@ -0,0 +1,19 @@
+-- Transactional outbox for domain events. Rows are written in the same transaction
+-- as the change they describe and relayed to RabbitMQ afterwards, so an event is
+-- published only if its transaction committed and is retried until the broker confirms.
+
+CREATE TABLE IF NOT EXISTS outbox_events (
+    id BIGSERIAL PRIMARY KEY,
+    event_type VARCHAR(64) NOT NULL,
+    event_version INTEGER NOT NULL,
+    aggregate_type VARCHAR(32) NOT NULL,
+    aggregate_id BIGINT NOT NULL,
+    payload JSONB NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    published_at TIMESTAMP,
+    attempts INTEGER NOT NULL DEFAULT 0,
+    last_error TEXT
+);
+
+CREATE INDEX IF NOT EXISTS outbox_events_unpublished_idx
+    ON outbox_events (id) WHERE published_at IS NULL;
@@ -0,0 +1,4 @@
+-- W3C trace context (`traceparent`, `tracestate`) of the request that recorded the event,
+-- carried into the AMQP message headers so consumers continue the same trace.
+
+ALTER TABLE outbox_events ADD COLUMN IF NOT EXISTS trace_context JSONB NOT NULL DEFAULT '{}'::jsonb;
@@ -0,0 +1,6 @@
+-- Part-paid orders expire too, so the sweep index has to cover them
+DROP INDEX IF EXISTS orders_pending_due_idx;
+
+CREATE INDEX IF NOT EXISTS orders_unpaid_due_idx
+    ON orders (payment_due_at)
+    WHERE status IN ('pending_payment', 'partially_paid');
@@ -0,0 +1,5 @@
+-- Sellers print their GSTIN on tax invoices; admins registered before this have none yet
+
+ALTER TABLE admins ADD COLUMN IF NOT EXISTS gstin VARCHAR(15);
+
+ALTER TABLE invoices ADD COLUMN IF NOT EXISTS seller_gstin VARCHAR(15);
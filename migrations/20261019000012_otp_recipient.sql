@@ -0,0 +1,6 @@
+-- An OTP is only accepted together with the email or mobile it was sent to, which also lets
+-- /verify be rate limited per recipient. Codes issued before this have no recipient and lapse.
+
+ALTER TABLE otp ADD COLUMN IF NOT EXISTS email TEXT;
+
+ALTER TABLE otp ADD COLUMN IF NOT EXISTS mobile TEXT;
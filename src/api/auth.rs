@@ -1,40 +1,44 @@
 use axum::{
     extract::{Json, State},
-    http::StatusCode,
     response::IntoResponse,
 };
-use lettre::transport::smtp::authentication::Credentials;
-use lettre::{Message, SmtpTransport, Transport};
 use rand::Rng;
 use serde::Deserialize;
+use utoipa::ToSchema;
 use serde_json::json;
-use std::env;
 use std::borrow::Cow;
 use crate::AppState;
+use crate::events::OtpIssued;
+use crate::metrics;
+use crate::notifications::deliver_otp;
 // Update this import path if needed
 use crate::db::users::Otp;
 use crate::db::users::User;
 use chrono::{Duration, Utc};
+use tracing::{error, info};
 
 
-#[derive(Deserialize)]
+#[derive(Deserialize, ToSchema)]
 pub struct OTPRequest {
     pub email: String,
     pub mobile: String,
     pub username: String,
 }
 
-#[derive(Deserialize)]
+#[allow(dead_code)]
+#[derive(Deserialize, ToSchema)]
 pub struct Admin {
     pub id: Option<i32>,  // Or whatever type you're using
     pub email: String,
     pub mobile: String,
     pub username: String,
     pub pincode: String,
+    pub gstin: Option<String>,
     pub regocde: Option<String>,  // Assuming this is optional based on your code
 }
 
-#[derive(Deserialize)]
+#[allow(dead_code, non_camel_case_types)]
+#[derive(Deserialize, ToSchema)]
 pub struct Admin_Users {
     pub id : Option<i32>,
     pub admin_id : i32,
@@ -46,12 +50,25 @@ pub struct Admin_Users {
 }
 
 
-#[derive(Deserialize)]
+#[derive(Deserialize, ToSchema)]
 pub struct OtpVerify {
     pub otp: u32,
+    pub email: Option<String>,
+    pub mobile: Option<String>,
 }
 
 // Using concrete types instead of impl Trait
+#[utoipa::path(
+    post,
+    path = "/api/v1/login",
+    tag = "auth",
+    security(()),
+    request_body = OTPRequest,
+    responses(
+        (status = 200, description = "OTP stored and queued for delivery to the email and mobile", body = ApiResponse),
+        (status = 429, description = "Rate limited per client address, email or mobile; see Retry-After"),
+    )
+)]
 pub async fn login(
     State(state): State<AppState>,
     Json(payload): Json<OTPRequest>,
@@ -60,7 +77,7 @@ pub async fn login(
     let OTPRequest {
         email,
         mobile,
-        username,
+        username: _,
     } = payload;
 
     if email.is_empty() || mobile.is_empty() {
@@ -71,63 +88,34 @@ pub async fn login(
     }
 
     let otp = generate_otp();
-    println!("Generated OTP: {}", otp);
-
-    let smtp_user = env::var("EMAIL_USER").unwrap_or_else(|_| "default@example.com".to_string());
-    let smtp_pass = env::var("EMAIL_PASS").unwrap_or_else(|_| "default_password".to_string());
-
-    let email_message = match Message::builder()
-        .from(format!("OTP Service <{}>", smtp_user).parse().unwrap())
-        .to(email.parse().unwrap())
-        .subject("Your OTP Code")
-        .body(format!("Your OTP is: {}", otp))
-    {
-        Ok(message) => message,
-        Err(_) => {
-            return Json(json!({
-                "status": "error",
-                "message": "Failed to create email message"
-            }));
-        }
-    };
-
-    let creds = Credentials::new(smtp_user.clone(), smtp_pass);
-
-    let mailer = match SmtpTransport::relay("smtp.gmail.com") {
-        Ok(transport) => transport.credentials(creds).build(),
-        Err(_) => {
-            return Json(json!({
-                "status": "error",
-                "message": "Failed to create email transport"
-            }));
-        }
-    };
 
-    match mailer.send(&email_message) {
-        Ok(_) => {
-            let otp_value = otp as i32;
-            match Otp::add_otp(pool, otp_value).await {
-                Ok(updated_otp) => {
-                    println!("OTP array updated: {:?}", updated_otp.otp);
-                    Json(json!({
-                        "status": "success",
-                        "message": format!("OTP sent to {} and {} and added to DB", mobile, email)
-                    }))
-                }
-                Err(e) => {
-                    println!("Error adding OTP to DB: {:?}", e);
-                    Json(json!({
-                        "status": "error",
-                        "message": "DB Error"
-                    }))
-                }
+    let otp_value = otp as i32;
+    match Otp::add_otp(pool, otp_value, &email, &mobile).await {
+        Ok(updated_otp) => {
+            info!(otp_id = updated_otp.id, "OTP stored");
+
+            // The worker picks up the otp.issued event; without a broker, deliver from here
+            if state.config.amqp_url.is_none() {
+                let pool = pool.clone();
+                let config = state.config.clone();
+                let issued = OtpIssued { otp_id: updated_otp.id, email: email.clone(), mobile: mobile.clone() };
+                state.shutdown.spawn(async move {
+                    if let Err(e) = deliver_otp(&pool, &config, &issued).await {
+                        error!("Error delivering OTP: {}", e);
+                    }
+                });
             }
+
+            Json(json!({
+                "status": "success",
+                "message": format!("OTP will be sent to {} and {}", mobile, email)
+            }))
         }
         Err(e) => {
-            println!("Error sending email: {:?}", e);
+            error!("Error adding OTP to DB: {:?}", e);
             Json(json!({
                 "status": "error",
-                "message": "Email send error"
+                "message": "DB Error"
             }))
         }
     }
@@ -137,17 +125,37 @@ fn generate_otp() -> u32 {
     rand::thread_rng().gen_range(100000..999999)
 }
 
+#[utoipa::path(
+    post,
+    path = "/api/v1/verify",
+    tag = "auth",
+    security(()),
+    request_body = OtpVerify,
+    responses(
+        (status = 200, description = "Whether the OTP sent to the email or mobile matched and is still valid", body = ApiResponse),
+        (status = 429, description = "Rate limited per client address, email or mobile; see Retry-After"),
+    )
+)]
 pub async fn verify_otp(
     State(state): State<AppState>,
     Json(payload): Json<OtpVerify>,
 ) -> impl IntoResponse {
     let pool = &state.pool;
-    let OtpVerify { otp } = payload;
+    let OtpVerify { otp, email, mobile } = payload;
+
+    let email = email.filter(|email| !email.trim().is_empty());
+    let mobile = mobile.filter(|mobile| !mobile.trim().is_empty());
+    if email.is_none() && mobile.is_none() {
+        return Json(json!({
+            "status": "error",
+            "message": "Missing email or mobile"
+        }));
+    }
 
     // Convert u32 -> i32 safely
     let otp_value = otp as i32;
 
-    match Otp::fetch_all_otps(pool).await {
+    match Otp::fetch_for_recipient(pool, email.as_deref(), mobile.as_deref()).await {
         Ok(updated_otps) => {
             // find the matching otp record with ID
             if let Some(otp_record) = updated_otps
@@ -157,32 +165,36 @@ pub async fn verify_otp(
                 let now = Utc::now().naive_utc();
                 let elapsed = now - otp_record.created_at;
 
-                if elapsed > Duration::minutes(1) {
+                if elapsed > Duration::minutes(state.config.otp_validity_minutes) {
                     // OTP expired, delete using otp_record.id
+                    metrics::record_otp_verification(metrics::VERIFY_EXPIRED);
                     let _ = Otp::delete_all_otps(pool, otp_record.id).await;
-                    return Json(json!({
+                    Json(json!({
                         "status": "error",
                         "message": "OTP expired"
-                    }));
+                    }))
                 } else {
                     // OTP is still valid
-                    println!("OTP {:?} verified successfully", otp_record);
+                    info!(otp_id = otp_record.id, "OTP verified");
+                    metrics::record_otp_verification(metrics::VERIFY_VERIFIED);
                     let _ = Otp::delete_all_otps(pool, otp_record.id).await;
-                    return Json(json!({
+                    Json(json!({
                         "status": "success",
                         "message": "OTP verified successfully"
-                    }));
+                    }))
                 }
             } else {
                 // OTP not found
-                return Json(json!({
+                metrics::record_otp_verification(metrics::VERIFY_NOT_FOUND);
+                Json(json!({
                     "status": "error",
                     "message": "OTP not found"
-                }));
+                }))
             }
         }
         Err(e) => {
-            println!("Error fetching OTPs from DB: {:?}", e);
+            error!("Error fetching OTPs from DB: {:?}", e);
+            metrics::record_otp_verification(metrics::VERIFY_ERROR);
             Json(json!({
                 "status": "error",
                 "message": "DB Error"
@@ -192,6 +204,16 @@ pub async fn verify_otp(
 }
 
 
+#[utoipa::path(
+    post,
+    path = "/api/v1/users",
+    tag = "auth",
+    security(()),
+    request_body = OTPRequest,
+    responses(
+        (status = 200, description = "User registered, or the email or mobile is taken", body = ApiResponse),
+    )
+)]
 pub async fn register(
     State(state): State<AppState>,
     Json(payload): Json<OTPRequest>,
@@ -213,7 +235,7 @@ pub async fn register(
     }
 
     // Example logic: Insert or trigger OTP generation here
-    match User::create_user(pool, &email, &mobile, &username).await {
+    match User::create_user(pool, &username, &email, &mobile).await {
         Ok(_) => {
             // Successfully generated OTP
             Json(json!({
@@ -229,7 +251,7 @@ pub async fn register(
         }
         Err(e) => {
             // Handle DB or logic error
-            println!("Failed to Regrister user: {:?}", e);
+            error!("Failed to Regrister user: {:?}", e);
             Json(json!({
                 "status": "error",
                 "message": "Failed to Regrister user"
@@ -239,6 +261,16 @@ pub async fn register(
 }
 
 
+#[utoipa::path(
+    post,
+    path = "/api/v1/admins",
+    tag = "auth",
+    security(()),
+    request_body = Admin,
+    responses(
+        (status = 200, description = "Admin created with a new registration code", body = ApiResponse),
+    )
+)]
 pub async fn create_admin(
     State(state): State<AppState>,
     Json(payload): Json<Admin>,
@@ -251,6 +283,7 @@ pub async fn create_admin(
         mobile,
         username,
         pincode,
+        gstin,
         regocde: _,
     } = payload;
 
@@ -261,6 +294,14 @@ pub async fn create_admin(
             "message": "Missing required fields"
         }));
     }
+    if let Some(gstin) = &gstin {
+        if gstin.len() != 15 || !gstin.chars().all(|c| c.is_ascii_alphanumeric()) {
+            return Json(json!({
+                "status": "error",
+                "message": "GSTIN must be 15 letters or digits"
+            }));
+        }
+    }
 
     // Generate OTP code
     let code = match Otp::generate_code(pool).await {
@@ -275,7 +316,7 @@ pub async fn create_admin(
 
 
     // Insert admin with OTP
-    match Otp::insert_admin(pool, &code, &username, &mobile, &email, &pincode).await {
+    match Otp::insert_admin(pool, &code, &username, &mobile, &email, &pincode, gstin.as_deref()).await {
         Ok(_) => Json(json!({
             "status": "success",
             "message": "Registration successful!"
@@ -288,6 +329,16 @@ pub async fn create_admin(
 }
 
 
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin_users",
+    tag = "auth",
+    security(()),
+    request_body = Admin_Users,
+    responses(
+        (status = 200, description = "User created under the admin's registration code", body = ApiResponse),
+    )
+)]
 pub async fn create_admin_users(
     State(state): State<AppState>,
     Json(payload): Json<Admin_Users>,
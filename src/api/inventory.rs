@@ -0,0 +1,364 @@
+use axum::{
+    extract::{Json, Query, State},
+    response::IntoResponse,
+};
+use serde::Deserialize;
+use utoipa::{IntoParams, ToSchema};
+use serde_json::json;
+use std::borrow::Cow;
+use crate::AppState;
+use crate::db::inventory::{
+    InventoryError, LowStock, MovementType, Posting, StockMovement, StockThreshold, Warehouse, WarehouseStock,
+};
+use crate::middleware::auth::AuthAdmin;
+use tracing::error;
+
+#[derive(Deserialize, ToSchema)]
+pub struct NewWarehouse {
+    pub code: String,
+    pub name: String,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct StockChange {
+    pub product_id: i32,
+    pub warehouse_id: i32,
+    pub quantity: i32,
+    pub reference: Option<String>,
+    pub note: Option<String>,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct Transfer {
+    pub product_id: i32,
+    pub from_warehouse_id: i32,
+    pub to_warehouse_id: i32,
+    pub quantity: i32,
+    pub note: Option<String>,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct LedgerQuery {
+    pub product_id: i32,
+    pub warehouse_id: Option<i32>,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct Threshold {
+    pub product_id: i32,
+    pub warehouse_id: i32,
+    pub min_quantity: i32,
+}
+
+fn inventory_error(e: InventoryError) -> Json<serde_json::Value> {
+    if let InventoryError::Db(db_err) = &e {
+        error!("Inventory DB error: {:?}", db_err);
+    }
+    Json(json!({
+        "status": "error",
+        "message": e.to_string()
+    }))
+}
+
+// Length of `stock_movements.reference`
+const MAX_REFERENCE_LEN: usize = 64;
+
+fn db_error(e: sqlx::Error) -> Json<serde_json::Value> {
+    inventory_error(InventoryError::Db(e))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/warehouses",
+    tag = "inventory",
+    request_body = NewWarehouse,
+    responses(
+        (status = 200, description = "Warehouse created; carries `warehouse`", body = ApiResponse),
+    )
+)]
+pub async fn create_warehouse(
+    State(state): State<AppState>,
+    admin: AuthAdmin,
+    Json(payload): Json<NewWarehouse>,
+) -> impl IntoResponse {
+    if payload.code.is_empty() || payload.name.is_empty() {
+        return Json(json!({
+            "status": "error",
+            "message": "Missing code or name"
+        }));
+    }
+
+    match Warehouse::create_warehouse(&state.pool, admin.admin_id, &payload.code, &payload.name).await {
+        Ok(warehouse) => Json(json!({
+            "status": "success",
+            "message": "Warehouse created",
+            "warehouse": warehouse
+        })),
+        Err(sqlx::Error::Database(db_err)) if db_err.code() == Some(Cow::Borrowed("23505")) => Json(json!({
+            "status": "error",
+            "message": "Warehouse with this code already exists"
+        })),
+        Err(e) => db_error(e),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/warehouses",
+    tag = "inventory",
+    responses(
+        (status = 200, description = "The admin's warehouses under `warehouses`", body = ApiResponse),
+    )
+)]
+pub async fn list_warehouses(
+    State(state): State<AppState>,
+    admin: AuthAdmin,
+) -> impl IntoResponse {
+    match Warehouse::fetch_for_admin(&state.pool, admin.admin_id).await {
+        Ok(warehouses) => Json(json!({
+            "status": "success",
+            "warehouses": warehouses
+        })),
+        Err(e) => db_error(e),
+    }
+}
+
+async fn record_movement(
+    state: &AppState,
+    admin: AuthAdmin,
+    payload: StockChange,
+    movement_type: MovementType,
+    on_hand_delta: i32,
+) -> Json<serde_json::Value> {
+    if payload.reference.as_ref().is_some_and(|r| r.chars().count() > MAX_REFERENCE_LEN) {
+        return Json(json!({
+            "status": "error",
+            "message": format!("reference cannot be longer than {} characters", MAX_REFERENCE_LEN)
+        }));
+    }
+
+    let posting = Posting {
+        product_id: payload.product_id,
+        warehouse_id: payload.warehouse_id,
+        movement_type,
+        on_hand_delta,
+        reserved_delta: 0,
+        reference: payload.reference.as_deref(),
+        note: payload.note.as_deref(),
+    };
+
+    match StockMovement::record(&state.pool, admin.admin_id, &posting).await {
+        Ok(movement) => Json(json!({
+            "status": "success",
+            "message": format!("Stock {} recorded", movement_type.as_str()),
+            "movement": movement
+        })),
+        Err(e) => inventory_error(e),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/inventory/receipts",
+    tag = "inventory",
+    request_body = StockChange,
+    responses(
+        (status = 200, description = "Receipt posted; carries `movement`", body = ApiResponse),
+    )
+)]
+pub async fn receive_stock(
+    State(state): State<AppState>,
+    admin: AuthAdmin,
+    Json(payload): Json<StockChange>,
+) -> impl IntoResponse {
+    if payload.quantity <= 0 {
+        return inventory_error(InventoryError::InvalidQuantity);
+    }
+    let quantity = payload.quantity;
+    record_movement(&state, admin, payload, MovementType::Receipt, quantity).await
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/inventory/issues",
+    tag = "inventory",
+    request_body = StockChange,
+    responses(
+        (status = 200, description = "Issue posted; carries `movement`", body = ApiResponse),
+    )
+)]
+pub async fn issue_stock(
+    State(state): State<AppState>,
+    admin: AuthAdmin,
+    Json(payload): Json<StockChange>,
+) -> impl IntoResponse {
+    if payload.quantity <= 0 {
+        return inventory_error(InventoryError::InvalidQuantity);
+    }
+    let quantity = payload.quantity;
+    record_movement(&state, admin, payload, MovementType::Issue, -quantity).await
+}
+
+// Adjustments carry a signed quantity, e.g. -2 after a stock count found two missing
+#[utoipa::path(
+    post,
+    path = "/api/v1/inventory/adjustments",
+    tag = "inventory",
+    request_body = StockChange,
+    responses(
+        (status = 200, description = "Adjustment posted; carries `movement`", body = ApiResponse),
+    )
+)]
+pub async fn adjust_stock(
+    State(state): State<AppState>,
+    admin: AuthAdmin,
+    Json(payload): Json<StockChange>,
+) -> impl IntoResponse {
+    if payload.quantity == 0 {
+        return Json(json!({
+            "status": "error",
+            "message": "Adjustment quantity cannot be zero"
+        }));
+    }
+    let quantity = payload.quantity;
+    record_movement(&state, admin, payload, MovementType::Adjustment, quantity).await
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/inventory/transfers",
+    tag = "inventory",
+    request_body = Transfer,
+    responses(
+        (status = 200, description = "Transfer posted; carries both `movements`", body = ApiResponse),
+    )
+)]
+pub async fn transfer_stock(
+    State(state): State<AppState>,
+    admin: AuthAdmin,
+    Json(payload): Json<Transfer>,
+) -> impl IntoResponse {
+    match StockMovement::transfer(
+        &state.pool,
+        admin.admin_id,
+        payload.product_id,
+        payload.from_warehouse_id,
+        payload.to_warehouse_id,
+        payload.quantity,
+        payload.note.as_deref(),
+    )
+    .await
+    {
+        Ok(movements) => Json(json!({
+            "status": "success",
+            "message": "Stock transferred",
+            "movements": movements
+        })),
+        Err(e) => inventory_error(e),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/inventory/stock",
+    tag = "inventory",
+    responses(
+        (status = 200, description = "Stock per product and warehouse under `stock`", body = ApiResponse),
+    )
+)]
+pub async fn current_stock(
+    State(state): State<AppState>,
+    admin: AuthAdmin,
+) -> impl IntoResponse {
+    match WarehouseStock::fetch_for_admin(&state.pool, admin.admin_id).await {
+        Ok(stock) => Json(json!({
+            "status": "success",
+            "stock": stock
+        })),
+        Err(e) => db_error(e),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/inventory/ledger",
+    tag = "inventory",
+    params(LedgerQuery),
+    responses(
+        (status = 200, description = "Stock movements for the product under `movements`", body = ApiResponse),
+    )
+)]
+pub async fn stock_ledger(
+    State(state): State<AppState>,
+    admin: AuthAdmin,
+    Query(query): Query<LedgerQuery>,
+) -> impl IntoResponse {
+    match StockMovement::fetch_ledger(&state.pool, admin.admin_id, query.product_id, query.warehouse_id).await {
+        Ok(movements) => Json(json!({
+            "status": "success",
+            "movements": movements
+        })),
+        Err(e) => db_error(e),
+    }
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/v1/inventory/thresholds",
+    tag = "inventory",
+    request_body = Threshold,
+    responses(
+        (status = 200, description = "Threshold saved; carries `threshold`", body = ApiResponse),
+    )
+)]
+pub async fn set_threshold(
+    State(state): State<AppState>,
+    admin: AuthAdmin,
+    Json(payload): Json<Threshold>,
+) -> impl IntoResponse {
+    if payload.min_quantity < 0 {
+        return Json(json!({
+            "status": "error",
+            "message": "Threshold cannot be negative"
+        }));
+    }
+
+    match StockThreshold::upsert(
+        &state.pool,
+        admin.admin_id,
+        payload.product_id,
+        payload.warehouse_id,
+        payload.min_quantity,
+    )
+    .await
+    {
+        Ok(threshold) => Json(json!({
+            "status": "success",
+            "message": "Low-stock threshold saved",
+            "threshold": threshold
+        })),
+        Err(e) => inventory_error(e),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/inventory/low_stock",
+    tag = "inventory",
+    responses(
+        (status = 200, description = "Products below their threshold under `low_stock`", body = ApiResponse),
+    )
+)]
+pub async fn low_stock(
+    State(state): State<AppState>,
+    admin: AuthAdmin,
+) -> impl IntoResponse {
+    match LowStock::fetch_for_admin(&state.pool, admin.admin_id).await {
+        Ok(items) => Json(json!({
+            "status": "success",
+            "low_stock": items
+        })),
+        Err(e) => db_error(e),
+    }
+}
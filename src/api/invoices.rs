@@ -0,0 +1,209 @@
+use axum::{
+    extract::{Json, Path, State},
+    http::header,
+    response::{Html, IntoResponse, Response},
+};
+use serde_json::json;
+use crate::AppState;
+use crate::db::invoices::{Invoice, InvoiceError};
+use crate::mailer::{send_email, EmailAttachment};
+use crate::metrics;
+use crate::middleware::auth::AuthAdmin;
+use crate::rendering::{render_invoice_html, render_invoice_pdf};
+use tracing::{error, Span};
+
+fn invoice_error(e: InvoiceError) -> Json<serde_json::Value> {
+    if let InvoiceError::Db(db_err) = &e {
+        error!("Invoice DB error: {:?}", db_err);
+    }
+    Json(json!({
+        "status": "error",
+        "message": e.to_string()
+    }))
+}
+
+fn render_error(e: impl std::fmt::Debug) -> Json<serde_json::Value> {
+    error!("Error rendering invoice: {:?}", e);
+    Json(json!({
+        "status": "error",
+        "message": "Failed to render invoice"
+    }))
+}
+
+// "INV/2627/000001" -> "INV-2627-000001.pdf"
+fn pdf_filename(invoice: &Invoice) -> String {
+    format!("{}.pdf", invoice.invoice_number.replace('/', "-"))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/orders/{id}/invoice",
+    tag = "invoices",
+    params(
+        ("id" = i32, Path, description = "Order id"),
+    ),
+    responses(
+        (status = 200, description = "Tax invoice issued; carries `invoice`", body = ApiResponse),
+    )
+)]
+pub async fn issue_invoice(
+    State(state): State<AppState>,
+    admin: AuthAdmin,
+    Path(order_id): Path<i32>,
+) -> impl IntoResponse {
+    match Invoice::issue_for_order(&state.pool, order_id, admin.admin_id).await {
+        Ok(invoice) => Json(json!({
+            "status": "success",
+            "message": format!("Invoice {} issued", invoice.invoice_number),
+            "invoice": invoice
+        })),
+        Err(e) => invoice_error(e),
+    }
+}
+
+// Serves `/invoices/12` as JSON, `/invoices/12.html` as a page and `/invoices/12.pdf` as a download
+#[utoipa::path(
+    get,
+    path = "/api/v1/invoices/{id}",
+    tag = "invoices",
+    params(
+        ("id" = String, Path, description = "Invoice id, optionally with a .html or .pdf extension"),
+    ),
+    responses(
+        (status = 200, description = "The invoice as JSON, an HTML page or a PDF download", body = ApiResponse),
+    )
+)]
+pub async fn get_invoice(
+    State(state): State<AppState>,
+    admin: AuthAdmin,
+    Path(file): Path<String>,
+) -> Response {
+    let (id, format) = match file.split_once('.') {
+        Some((id, format)) => (id, format),
+        None => (file.as_str(), "json"),
+    };
+    let Ok(invoice_id) = id.parse::<i32>() else {
+        return Json(json!({
+            "status": "error",
+            "message": "Invalid invoice id"
+        }))
+        .into_response();
+    };
+
+    let invoice = match Invoice::fetch_invoice(&state.pool, admin.admin_id, invoice_id).await {
+        Ok(Some(invoice)) => invoice,
+        Ok(None) => {
+            return Json(json!({
+                "status": "error",
+                "message": format!("Invoice {} not found", invoice_id)
+            }))
+            .into_response();
+        }
+        Err(e) => return invoice_error(e.into()).into_response(),
+    };
+
+    match format {
+        "json" => Json(json!({
+            "status": "success",
+            "invoice": invoice
+        }))
+        .into_response(),
+        "html" => match render_invoice_html(&invoice, &state.config.app_name) {
+            Ok(html) => Html(html).into_response(),
+            Err(e) => render_error(e).into_response(),
+        },
+        "pdf" => match render_invoice_pdf(&invoice, &state.config.app_name) {
+            Ok(bytes) => (
+                [
+                    (header::CONTENT_TYPE, "application/pdf".to_string()),
+                    (header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}\"", pdf_filename(&invoice))),
+                ],
+                bytes,
+            )
+                .into_response(),
+            Err(e) => render_error(e).into_response(),
+        },
+        _ => Json(json!({
+            "status": "error",
+            "message": "Unsupported invoice format"
+        }))
+        .into_response(),
+    }
+}
+
+// Emails the PDF to the buyer using the same sender as the OTP mails
+#[utoipa::path(
+    post,
+    path = "/api/v1/invoices/{id}/email",
+    tag = "invoices",
+    params(
+        ("id" = i32, Path, description = "Invoice id"),
+    ),
+    responses(
+        (status = 200, description = "Invoice PDF emailed to the buyer", body = ApiResponse),
+    )
+)]
+pub async fn email_invoice(
+    State(state): State<AppState>,
+    admin: AuthAdmin,
+    Path(invoice_id): Path<i32>,
+) -> impl IntoResponse {
+    let invoice = match Invoice::fetch_invoice(&state.pool, admin.admin_id, invoice_id).await {
+        Ok(Some(invoice)) => invoice,
+        Ok(None) => {
+            return Json(json!({
+                "status": "error",
+                "message": format!("Invoice {} not found", invoice_id)
+            }));
+        }
+        Err(e) => return invoice_error(e.into()),
+    };
+
+    let bytes = match render_invoice_pdf(&invoice, &state.config.app_name) {
+        Ok(bytes) => bytes,
+        Err(e) => return render_error(e),
+    };
+
+    let app_name = state.config.app_name.clone();
+    let to = invoice.buyer_email.clone();
+    let subject = format!("Tax invoice {}", invoice.invoice_number);
+    let body = format!(
+        "Please find attached tax invoice {} for your order #{}.",
+        invoice.invoice_number, invoice.order_id
+    );
+    let attachment = EmailAttachment {
+        filename: pdf_filename(&invoice),
+        content_type: "application/pdf".to_string(),
+        bytes,
+    };
+
+    // The SMTP span belongs under this request even though it runs on the blocking pool
+    let span = Span::current();
+    let config = state.config.clone();
+    let sent = tokio::task::spawn_blocking(move || {
+        span.in_scope(|| send_email(&config, &app_name, &to, &subject, body, vec![attachment]))
+    })
+    .await;
+    metrics::record_email("invoice", matches!(sent, Ok(Ok(()))));
+
+    match sent {
+        Ok(Ok(())) => Json(json!({
+            "status": "success",
+            "message": format!("Invoice {} sent to {}", invoice.invoice_number, invoice.buyer_email)
+        })),
+        Ok(Err(e)) => {
+            error!("Error sending invoice email: {}", e.detail());
+            Json(json!({
+                "status": "error",
+                "message": e.to_string()
+            }))
+        }
+        Err(e) => {
+            error!("Invoice email task failed: {:?}", e);
+            Json(json!({
+                "status": "error",
+                "message": "Email send error"
+            }))
+        }
+    }
+}
@@ -0,0 +1,102 @@
+use axum::{
+    extract::Request,
+    http::{header, HeaderValue},
+    middleware::{self, Next},
+    response::Response,
+    routing::post,
+    Router,
+};
+use crate::AppState;
+use crate::api::V1_PREFIX;
+use crate::api::auth::{create_admin, create_admin_users, login, register, verify_otp};
+use tracing::warn;
+
+// RFC 9745 date the flat paths were deprecated (2026-10-19) and the RFC 8594 date they go away
+const DEPRECATED_AT: &str = "@1792368000";
+const SUNSET: &str = "Sun, 31 Jan 2027 00:00:00 GMT";
+
+// Flat paths whose v1 name is different; all others are the same path under the prefix
+const RENAMED: [(&str, &str); 3] = [
+    ("/add_user", "/users"),
+    ("/ceate_user", "/admins"),
+    ("/ceate_admin_user", "/admin_users"),
+];
+
+pub fn successor(path: &str) -> String {
+    let renamed = RENAMED.iter().find(|(old, _)| *old == path).map(|(_, new)| *new);
+    format!("{}{}", V1_PREFIX, renamed.unwrap_or(path))
+}
+
+// Marks every response from a flat path as deprecated and points at its v1 replacement
+async fn deprecated(req: Request, next: Next) -> Response {
+    let successor = successor(req.uri().path());
+    warn!(method = %req.method(), path = req.uri().path(), successor = %successor, "Deprecated route called");
+
+    let mut response = next.run(req).await;
+    let headers = response.headers_mut();
+    headers.insert("deprecation", HeaderValue::from_static(DEPRECATED_AT));
+    headers.insert("sunset", HeaderValue::from_static(SUNSET));
+    if let Ok(link) = HeaderValue::from_str(&format!("<{}>; rel=\"successor-version\"", successor)) {
+        headers.insert(header::LINK, link);
+    }
+    response
+}
+
+// The unversioned paths clients used before `/api/v1`, typos included. Frozen: only the
+// routes that existed then are aliased, new endpoints only go into a versioned router.
+pub fn routes() -> Router<AppState> {
+    Router::new()
+        .route("/login", post(login))
+        .route("/verify", post(verify_otp))
+        .route("/add_user", post(register))
+        .route("/ceate_user", post(create_admin))
+        .route("/ceate_admin_user", post(create_admin_users))
+        .route_layer(middleware::from_fn(deprecated))
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::body::Body;
+    use axum::http::{header, Request, StatusCode};
+    use tower::ServiceExt;
+
+    use super::{DEPRECATED_AT, SUNSET};
+    use crate::{api, test_state};
+
+    // Both requests are turned away by their extractors before a handler runs
+    #[tokio::test]
+    async fn flat_paths_point_at_their_successor() {
+        let state = test_state(None);
+        let app = api::router(&state).with_state(state);
+
+        let renamed = Request::post("/ceate_user")
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from("{}"))
+            .unwrap();
+        let response = app.clone().oneshot(renamed).await.unwrap();
+        assert_eq!(response.headers()["deprecation"], DEPRECATED_AT);
+        assert_eq!(response.headers()["sunset"], SUNSET);
+        assert_eq!(response.headers()[header::LINK], r#"</api/v1/admins>; rel="successor-version""#);
+
+        let unchanged = Request::post("/login")
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from("{}"))
+            .unwrap();
+        let response = app.clone().oneshot(unchanged).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(response.headers()["deprecation"], DEPRECATED_AT);
+        assert_eq!(response.headers()[header::LINK], r#"</api/v1/login>; rel="successor-version""#);
+
+        // Endpoints added since have no flat alias, and the versioned path itself isn't deprecated
+        let added = Request::get("/inventory/stock").body(Body::empty()).unwrap();
+        let response = app.clone().oneshot(added).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let current = Request::post("/api/v1/login")
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from("{}"))
+            .unwrap();
+        let response = app.oneshot(current).await.unwrap();
+        assert!(response.headers().get("deprecation").is_none());
+    }
+}
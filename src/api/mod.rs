@@ -2,3 +2,24 @@ pub mod users;
 pub mod auth;
 pub mod products;
 pub mod orders;
+pub mod inventory;
+pub mod pricing;
+pub mod invoices;
+pub mod payments;
+pub mod purchasing;
+pub mod returns;
+pub mod realtime;
+pub mod v1;
+pub mod legacy;
+
+use axum::Router;
+use crate::AppState;
+
+pub const V1_PREFIX: &str = "/api/v1";
+
+// Each API version keeps its own router so a v2 can change handlers without touching v1
+pub fn router(state: &AppState) -> Router<AppState> {
+    Router::new()
+        .nest(V1_PREFIX, v1::routes(state))
+        .merge(legacy::routes())
+}
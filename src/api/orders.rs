@@ -0,0 +1,179 @@
+use axum::{
+    extract::{Json, Path, State},
+    response::IntoResponse,
+};
+use chrono::Duration;
+use serde::Deserialize;
+use utoipa::ToSchema;
+use serde_json::json;
+use crate::AppState;
+use crate::db::orders::{NewOrder, NewOrderLine, Order, OrderError};
+use crate::middleware::auth::AuthAdmin;
+use tracing::error;
+
+#[derive(Deserialize, ToSchema)]
+pub struct QuoteRequest {
+    pub customer_email: String,
+    pub lines: Vec<NewOrderLine>,
+    pub coupon_code: Option<String>,
+}
+
+fn order_error(e: OrderError) -> Json<serde_json::Value> {
+    if let OrderError::Db(db_err) = &e {
+        error!("Order DB error: {:?}", db_err);
+    }
+    Json(json!({
+        "status": "error",
+        "message": e.to_string()
+    }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/orders",
+    tag = "orders",
+    request_body = NewOrder,
+    responses(
+        (status = 200, description = "Order placed with stock reserved; carries `order` and `lines`", body = ApiResponse),
+    )
+)]
+pub async fn place_order(
+    State(state): State<AppState>,
+    admin: AuthAdmin,
+    Json(payload): Json<NewOrder>,
+) -> impl IntoResponse {
+    if payload.customer_email.is_empty() {
+        return Json(json!({
+            "status": "error",
+            "message": "Missing customer email"
+        }));
+    }
+
+    let payment_timeout = Duration::minutes(state.config.payment_timeout_minutes);
+
+    match Order::place_order(&state.pool, admin.admin_id, &payload, payment_timeout).await {
+        Ok((order, lines)) => Json(json!({
+            "status": "success",
+            "message": "Order placed and stock reserved",
+            "order": order,
+            "lines": lines
+        })),
+        Err(e) => order_error(e),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/orders/quote",
+    tag = "orders",
+    request_body = QuoteRequest,
+    responses(
+        (status = 200, description = "Priced lines and totals without reserving stock", body = ApiResponse),
+    )
+)]
+pub async fn quote_order(
+    State(state): State<AppState>,
+    admin: AuthAdmin,
+    Json(payload): Json<QuoteRequest>,
+) -> impl IntoResponse {
+    match Order::quote(
+        &state.pool,
+        admin.admin_id,
+        &payload.customer_email,
+        &payload.lines,
+        payload.coupon_code.as_deref(),
+    )
+    .await
+    {
+        Ok(lines) => {
+            let total_paise: i64 = lines.iter().map(|l| l.net_paise).sum();
+            Json(json!({
+                "status": "success",
+                "total_paise": total_paise,
+                "lines": lines
+            }))
+        }
+        Err(e) => order_error(e),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/orders/{id}",
+    tag = "orders",
+    params(
+        ("id" = i32, Path, description = "Order id"),
+    ),
+    responses(
+        (status = 200, description = "The order under `order` with its `lines`", body = ApiResponse),
+    )
+)]
+pub async fn get_order(
+    State(state): State<AppState>,
+    admin: AuthAdmin,
+    Path(order_id): Path<i32>,
+) -> impl IntoResponse {
+    match Order::fetch_order(&state.pool, order_id).await {
+        Ok(Some((order, _))) if order.admin_id != admin.admin_id => order_error(OrderError::NotOwner(admin.admin_id)),
+        Ok(Some((order, lines))) => Json(json!({
+            "status": "success",
+            "order": order,
+            "lines": lines
+        })),
+        Ok(None) => order_error(OrderError::NotFound(order_id)),
+        Err(e) => order_error(e.into()),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/orders/{id}/cancel",
+    tag = "orders",
+    params(
+        ("id" = i32, Path, description = "Order id"),
+    ),
+    responses(
+        (status = 200, description = "Unpaid or part-paid order cancelled, its reservation and coupon use released; `refund_due_paise` is what was already paid", body = ApiResponse),
+    )
+)]
+pub async fn cancel_order(
+    State(state): State<AppState>,
+    admin: AuthAdmin,
+    Path(order_id): Path<i32>,
+) -> impl IntoResponse {
+    match Order::cancel_order(&state.pool, order_id, admin.admin_id).await {
+        Ok(order) => Json(json!({
+            "status": "success",
+            "message": "Order cancelled and stock released",
+            "refund_due_paise": order.refund_due_paise(),
+            "order": order
+        })),
+        Err(e) => order_error(e),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/orders/{id}/deliver",
+    tag = "orders",
+    params(
+        ("id" = i32, Path, description = "Order id"),
+    ),
+    responses(
+        (status = 200, description = "Paid order marked delivered", body = ApiResponse),
+    )
+)]
+pub async fn deliver_order(
+    State(state): State<AppState>,
+    admin: AuthAdmin,
+    Path(order_id): Path<i32>,
+) -> impl IntoResponse {
+    match Order::mark_delivered(&state.pool, order_id, admin.admin_id).await {
+        Ok(order) => Json(json!({
+            "status": "success",
+            "message": "Order marked as delivered",
+            "order": order
+        })),
+        Err(e) => order_error(e),
+    }
+}
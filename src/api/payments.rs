@@ -0,0 +1,228 @@
+use axum::{
+    body::Bytes,
+    extract::{Json, Path, State},
+    http::{HeaderMap, StatusCode},
+    response::IntoResponse,
+};
+use serde::Deserialize;
+use utoipa::ToSchema;
+use serde_json::json;
+use crate::AppState;
+use crate::db::payments::{NewPayment, Payment, PaymentError, Reconciliation, KIND_PAYMENT, KIND_REFUND, PROVIDER_MANUAL};
+use crate::middleware::auth::AuthAdmin;
+use tracing::{error, warn};
+
+#[derive(Deserialize, ToSchema)]
+pub struct ManualPayment {
+    pub method: String,
+    pub amount_paise: i64,
+    pub reference: Option<String>,
+    pub note: Option<String>,
+}
+
+fn payment_error(e: PaymentError) -> Json<serde_json::Value> {
+    if let PaymentError::Db(db_err) = &e {
+        error!("Payment DB error: {:?}", db_err);
+    }
+    Json(json!({
+        "status": "error",
+        "message": e.to_string()
+    }))
+}
+
+async fn record(
+    state: &AppState,
+    admin: AuthAdmin,
+    order_id: i32,
+    kind: &str,
+    payload: ManualPayment,
+) -> Json<serde_json::Value> {
+    let payment = NewPayment {
+        kind: kind.to_string(),
+        method: payload.method,
+        amount_paise: payload.amount_paise,
+        provider: PROVIDER_MANUAL.to_string(),
+        provider_reference: payload.reference,
+        note: payload.note,
+    };
+
+    match Payment::record(&state.pool, order_id, Some(admin.admin_id), &payment).await {
+        Ok((payment, order)) => Json(json!({
+            "status": "success",
+            "message": format!("{} recorded, order is {}", payment.kind, order.status),
+            "payment": payment,
+            "order": order
+        })),
+        Err(e) => payment_error(e),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/orders/{id}/payments",
+    tag = "payments",
+    request_body = ManualPayment,
+    params(
+        ("id" = i32, Path, description = "Order id"),
+    ),
+    responses(
+        (status = 200, description = "Payment recorded against the order", body = ApiResponse),
+    )
+)]
+pub async fn record_payment(
+    State(state): State<AppState>,
+    admin: AuthAdmin,
+    Path(order_id): Path<i32>,
+    Json(payload): Json<ManualPayment>,
+) -> impl IntoResponse {
+    record(&state, admin, order_id, KIND_PAYMENT, payload).await
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/orders/{id}/refunds",
+    tag = "payments",
+    request_body = ManualPayment,
+    params(
+        ("id" = i32, Path, description = "Order id"),
+    ),
+    responses(
+        (status = 200, description = "Refund recorded against the order", body = ApiResponse),
+    )
+)]
+pub async fn record_refund(
+    State(state): State<AppState>,
+    admin: AuthAdmin,
+    Path(order_id): Path<i32>,
+    Json(payload): Json<ManualPayment>,
+) -> impl IntoResponse {
+    record(&state, admin, order_id, KIND_REFUND, payload).await
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/orders/{id}/payments",
+    tag = "payments",
+    params(
+        ("id" = i32, Path, description = "Order id"),
+    ),
+    responses(
+        (status = 200, description = "Payments and refunds for the order under `payments`", body = ApiResponse),
+    )
+)]
+pub async fn list_payments(
+    State(state): State<AppState>,
+    admin: AuthAdmin,
+    Path(order_id): Path<i32>,
+) -> impl IntoResponse {
+    match Payment::fetch_for_order(&state.pool, admin.admin_id, order_id).await {
+        Ok(payments) => Json(json!({
+            "status": "success",
+            "payments": payments
+        })),
+        Err(e) => payment_error(e.into()),
+    }
+}
+
+// Gateways retry on non-2xx, so only a bad signature or unknown provider is rejected
+// with an HTTP error; business failures are acknowledged and reported in the body.
+#[utoipa::path(
+    post,
+    path = "/api/v1/payments/webhooks/{provider}",
+    tag = "payments",
+    security(()),
+    request_body(content = String, content_type = "application/json", description = "Provider event, signed over the raw body"),
+    params(
+        ("provider" = String, Path, description = "Registered payment provider, e.g. mock"),
+        ("x-signature" = String, Header, description = "Hex HMAC-SHA256 of the body"),
+    ),
+    responses(
+        (status = 200, description = "Event applied, or a business failure reported in the body", body = ApiResponse),
+        (status = 401, description = "Invalid signature", body = ApiResponse),
+        (status = 404, description = "Unknown payment provider", body = ApiResponse),
+    )
+)]
+pub async fn payment_webhook(
+    State(state): State<AppState>,
+    Path(provider_name): Path<String>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> impl IntoResponse {
+    let Some(provider) = state.payment_providers.get(&provider_name) else {
+        return (
+            StatusCode::NOT_FOUND,
+            Json(json!({
+                "status": "error",
+                "message": "Unknown payment provider"
+            })),
+        );
+    };
+
+    if !provider.verify_signature(&headers, &body) {
+        warn!(provider = %provider_name, "Rejected webhook with invalid signature");
+        return (
+            StatusCode::UNAUTHORIZED,
+            Json(json!({
+                "status": "error",
+                "message": "Invalid signature"
+            })),
+        );
+    }
+
+    let event = match provider.parse_event(&body) {
+        Ok(event) => event,
+        Err(e) => {
+            error!(provider = %provider_name, error = %e, "Malformed webhook");
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(json!({
+                    "status": "error",
+                    "message": "Malformed event"
+                })),
+            );
+        }
+    };
+
+    match Payment::record(&state.pool, event.order_id, None, &event.payment).await {
+        Ok((payment, order)) => (
+            StatusCode::OK,
+            Json(json!({
+                "status": "success",
+                "payment_id": payment.id,
+                "order_status": order.status
+            })),
+        ),
+        Err(PaymentError::Db(e)) => {
+            error!("Payment DB error: {:?}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({
+                    "status": "error",
+                    "message": "DB Error"
+                })),
+            )
+        }
+        Err(e) => (StatusCode::OK, payment_error(e)),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/payments/reconciliation",
+    tag = "payments",
+    responses(
+        (status = 200, description = "Orders whose payments do not add up under `mismatches`", body = ApiResponse),
+    )
+)]
+pub async fn reconciliation(
+    State(state): State<AppState>,
+    admin: AuthAdmin,
+) -> impl IntoResponse {
+    match Reconciliation::fetch_mismatches(&state.pool, admin.admin_id).await {
+        Ok(mismatches) => Json(json!({
+            "status": "success",
+            "mismatches": mismatches
+        })),
+        Err(e) => payment_error(e.into()),
+    }
+}
@@ -0,0 +1,217 @@
+use axum::{
+    extract::{Json, Path, State},
+    response::IntoResponse,
+};
+use chrono::NaiveDateTime;
+use serde::Deserialize;
+use utoipa::ToSchema;
+use serde_json::json;
+use std::borrow::Cow;
+use crate::AppState;
+use crate::db::pricing::{Coupon, Discount, NewCoupon, NewDiscount, PriceList};
+use crate::middleware::auth::AuthAdmin;
+use crate::pricing::{is_valid_kind, KIND_PERCENT};
+use tracing::error;
+
+#[derive(Deserialize, ToSchema)]
+pub struct NewPriceList {
+    pub name: String,
+    pub customer_email: Option<String>,
+    pub valid_from: Option<NaiveDateTime>,
+    pub valid_to: Option<NaiveDateTime>,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct NewPriceListItem {
+    pub product_id: i32,
+    pub min_quantity: Option<i32>,
+    pub unit_price_paise: i64,
+}
+
+fn db_error(e: sqlx::Error) -> Json<serde_json::Value> {
+    error!("Pricing DB error: {:?}", e);
+    Json(json!({
+        "status": "error",
+        "message": "DB Error"
+    }))
+}
+
+fn invalid(message: &str) -> Json<serde_json::Value> {
+    Json(json!({
+        "status": "error",
+        "message": message
+    }))
+}
+
+// Percent values are basis points, so anything above 10000 would be more than 100% off
+fn check_amount(kind: &str, value: i64) -> Result<(), Json<serde_json::Value>> {
+    if !is_valid_kind(kind) {
+        return Err(invalid("kind must be 'percent' or 'fixed'"));
+    }
+    if value <= 0 || (kind == KIND_PERCENT && value > 10_000) {
+        return Err(invalid("value out of range"));
+    }
+    Ok(())
+}
+
+// An open end is fine, but a window that closes before it opens would never apply
+fn check_validity(valid_from: Option<NaiveDateTime>, valid_to: Option<NaiveDateTime>) -> Result<(), Json<serde_json::Value>> {
+    match (valid_from, valid_to) {
+        (Some(from), Some(to)) if from >= to => Err(invalid("valid_from must be before valid_to")),
+        _ => Ok(()),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/price_lists",
+    tag = "pricing",
+    request_body = NewPriceList,
+    responses(
+        (status = 200, description = "Price list created; carries `price_list`", body = ApiResponse),
+    )
+)]
+pub async fn create_price_list(
+    State(state): State<AppState>,
+    admin: AuthAdmin,
+    Json(payload): Json<NewPriceList>,
+) -> impl IntoResponse {
+    if payload.name.is_empty() {
+        return invalid("Missing name");
+    }
+    if let Err(response) = check_validity(payload.valid_from, payload.valid_to) {
+        return response;
+    }
+
+    match PriceList::create_price_list(
+        &state.pool,
+        admin.admin_id,
+        &payload.name,
+        payload.customer_email.as_deref(),
+        payload.valid_from,
+        payload.valid_to,
+    )
+    .await
+    {
+        Ok(price_list) => Json(json!({
+            "status": "success",
+            "message": "Price list created",
+            "price_list": price_list
+        })),
+        Err(e) => db_error(e),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/price_lists/{id}/items",
+    tag = "pricing",
+    request_body = NewPriceListItem,
+    params(
+        ("id" = i32, Path, description = "Price list id"),
+    ),
+    responses(
+        (status = 200, description = "Item added to the price list; carries `item`", body = ApiResponse),
+    )
+)]
+pub async fn add_price_list_item(
+    State(state): State<AppState>,
+    admin: AuthAdmin,
+    Path(price_list_id): Path<i32>,
+    Json(payload): Json<NewPriceListItem>,
+) -> impl IntoResponse {
+    let min_quantity = payload.min_quantity.unwrap_or(1);
+    if min_quantity <= 0 || payload.unit_price_paise < 0 {
+        return invalid("min_quantity must be positive and price cannot be negative");
+    }
+
+    match PriceList::ownership(&state.pool, admin.admin_id, price_list_id, payload.product_id).await {
+        Ok((true, true)) => {}
+        Ok((false, _)) => return invalid(&format!("Price list {} not found", price_list_id)),
+        Ok((true, false)) => return invalid(&format!("Product {} not found", payload.product_id)),
+        Err(e) => return db_error(e),
+    }
+
+    match PriceList::upsert_item(&state.pool, price_list_id, payload.product_id, min_quantity, payload.unit_price_paise).await {
+        Ok(item) => Json(json!({
+            "status": "success",
+            "message": "Price saved",
+            "item": item
+        })),
+        Err(e) => db_error(e),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/discounts",
+    tag = "pricing",
+    request_body = NewDiscount,
+    responses(
+        (status = 200, description = "Discount created; carries `discount`", body = ApiResponse),
+    )
+)]
+pub async fn create_discount(
+    State(state): State<AppState>,
+    admin: AuthAdmin,
+    Json(payload): Json<NewDiscount>,
+) -> impl IntoResponse {
+    if payload.name.is_empty() {
+        return invalid("Missing name");
+    }
+    if let Err(response) = check_amount(&payload.kind, payload.value) {
+        return response;
+    }
+    if let Err(response) = check_validity(payload.valid_from, payload.valid_to) {
+        return response;
+    }
+
+    match Discount::create_discount(&state.pool, admin.admin_id, &payload).await {
+        Ok(discount) => Json(json!({
+            "status": "success",
+            "message": "Discount created",
+            "discount": discount
+        })),
+        Err(e) => db_error(e),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/coupons",
+    tag = "pricing",
+    request_body = NewCoupon,
+    responses(
+        (status = 200, description = "Coupon created; carries `coupon`", body = ApiResponse),
+    )
+)]
+pub async fn create_coupon(
+    State(state): State<AppState>,
+    admin: AuthAdmin,
+    Json(payload): Json<NewCoupon>,
+) -> impl IntoResponse {
+    if payload.code.is_empty() {
+        return invalid("Missing coupon code");
+    }
+    if let Err(response) = check_amount(&payload.kind, payload.value) {
+        return response;
+    }
+    if payload.max_uses.is_some_and(|max_uses| max_uses <= 0) {
+        return invalid("max_uses must be greater than zero");
+    }
+    if let Err(response) = check_validity(payload.valid_from, payload.valid_to) {
+        return response;
+    }
+
+    match Coupon::create_coupon(&state.pool, admin.admin_id, &payload).await {
+        Ok(coupon) => Json(json!({
+            "status": "success",
+            "message": "Coupon created",
+            "coupon": coupon
+        })),
+        Err(sqlx::Error::Database(db_err)) if db_err.code() == Some(Cow::Borrowed("23505")) => {
+            invalid("Coupon with this code already exists")
+        }
+        Err(e) => db_error(e),
+    }
+}
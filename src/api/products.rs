@@ -0,0 +1,124 @@
+use axum::{
+    extract::{Json, State},
+    response::IntoResponse,
+};
+use serde_json::json;
+use std::borrow::Cow;
+use crate::AppState;
+use crate::db::inventory::InventoryError;
+use crate::db::products::{NewProduct, Product};
+use crate::gst::VALID_RATES_BP;
+use crate::middleware::auth::AuthAdmin;
+use tracing::error;
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/products",
+    tag = "products",
+    request_body = NewProduct,
+    responses(
+        (status = 200, description = "Product created with its opening stock; carries `product`", body = ApiResponse),
+    )
+)]
+pub async fn create_product(
+    State(state): State<AppState>,
+    admin: AuthAdmin,
+    Json(payload): Json<NewProduct>,
+) -> impl IntoResponse {
+    let pool = &state.pool;
+
+    if payload.sku.is_empty() || payload.name.is_empty() {
+        return Json(json!({
+            "status": "error",
+            "message": "Missing sku or name"
+        }));
+    }
+
+    if payload.unit_price_paise < 0 || payload.stock_on_hand < 0 {
+        return Json(json!({
+            "status": "error",
+            "message": "Price and stock cannot be negative"
+        }));
+    }
+
+    if !VALID_RATES_BP.contains(&payload.gst_rate_bp) {
+        return Json(json!({
+            "status": "error",
+            "message": "gst_rate_bp must be one of the notified GST rates"
+        }));
+    }
+
+    // HSN codes are 4, 6 or 8 digits depending on turnover
+    if let Some(hsn_code) = &payload.hsn_code {
+        if ![4, 6, 8].contains(&hsn_code.len()) || !hsn_code.bytes().all(|b| b.is_ascii_digit()) {
+            return Json(json!({
+                "status": "error",
+                "message": "hsn_code must be 4, 6 or 8 digits"
+            }));
+        }
+    }
+
+    // Opening stock has to land in a warehouse so the ledger stays complete
+    let opening_stock = match (payload.stock_on_hand, payload.warehouse_id) {
+        (0, _) => None,
+        (quantity, Some(warehouse_id)) => Some((warehouse_id, quantity)),
+        (_, None) => {
+            return Json(json!({
+                "status": "error",
+                "message": "warehouse_id is required for opening stock"
+            }));
+        }
+    };
+
+    match Product::create_product(pool, admin.admin_id, &payload, opening_stock).await {
+        Ok(product) => Json(json!({
+            "status": "success",
+            "message": "Product created",
+            "product": product
+        })),
+        Err(InventoryError::Db(sqlx::Error::Database(db_err))) if db_err.code() == Some(Cow::Borrowed("23505")) => {
+            Json(json!({
+                "status": "error",
+                "message": "Product with this sku already exists"
+            }))
+        }
+        Err(InventoryError::Db(e)) => {
+            error!("Failed to create product: {:?}", e);
+            Json(json!({
+                "status": "error",
+                "message": "DB Error"
+            }))
+        }
+        Err(e) => Json(json!({
+            "status": "error",
+            "message": e.to_string()
+        })),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/products",
+    tag = "products",
+    responses(
+        (status = 200, description = "The admin's products under `products`", body = ApiResponse),
+    )
+)]
+pub async fn list_products(
+    State(state): State<AppState>,
+    admin: AuthAdmin,
+) -> impl IntoResponse {
+    match Product::fetch_for_admin(&state.pool, admin.admin_id).await {
+        Ok(products) => Json(json!({
+            "status": "success",
+            "products": products
+        })),
+        Err(e) => {
+            error!("Error fetching products from DB: {:?}", e);
+            Json(json!({
+                "status": "error",
+                "message": "DB Error"
+            }))
+        }
+    }
+}
@@ -0,0 +1,279 @@
+use axum::{
+    extract::{Json, Path, State},
+    response::IntoResponse,
+};
+use serde_json::json;
+use std::borrow::Cow;
+use crate::AppState;
+use crate::db::purchasing::{
+    GoodsReceipt, MatchReport, NewGoodsReceipt, NewPurchaseOrder, NewSupplier, NewSupplierInvoice, PurchaseOrder,
+    PurchasingError, Supplier, SupplierInvoice,
+};
+use crate::gst;
+use crate::middleware::auth::AuthAdmin;
+use tracing::error;
+
+fn purchasing_error(e: PurchasingError) -> Json<serde_json::Value> {
+    if let PurchasingError::Db(db_err) = &e {
+        error!("Purchasing DB error: {:?}", db_err);
+    }
+    Json(json!({
+        "status": "error",
+        "message": e.to_string()
+    }))
+}
+
+fn is_unique_violation(e: &PurchasingError) -> bool {
+    matches!(e, PurchasingError::Db(sqlx::Error::Database(db_err)) if db_err.code() == Some(Cow::Borrowed("23505")))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/suppliers",
+    tag = "purchasing",
+    request_body = NewSupplier,
+    responses(
+        (status = 200, description = "Supplier created; carries `supplier`", body = ApiResponse),
+    )
+)]
+pub async fn create_supplier(
+    State(state): State<AppState>,
+    admin: AuthAdmin,
+    Json(payload): Json<NewSupplier>,
+) -> impl IntoResponse {
+    if payload.name.trim().is_empty() {
+        return Json(json!({
+            "status": "error",
+            "message": "Missing supplier name"
+        }));
+    }
+    if let Some(gstin) = &payload.gstin {
+        if gstin.len() != 15 || !gstin.chars().all(|c| c.is_ascii_alphanumeric()) {
+            return Json(json!({
+                "status": "error",
+                "message": "GSTIN must be 15 letters or digits"
+            }));
+        }
+    }
+    if let Some(pincode) = &payload.pincode {
+        if gst::state_for_pincode(pincode).is_none() {
+            return Json(json!({
+                "status": "error",
+                "message": format!("Cannot determine GST state for pincode {}", pincode)
+            }));
+        }
+    }
+
+    match Supplier::create_supplier(&state.pool, admin.admin_id, &payload).await {
+        Ok(supplier) => Json(json!({
+            "status": "success",
+            "message": "Supplier created",
+            "supplier": supplier
+        })),
+        Err(e) => {
+            let e = PurchasingError::Db(e);
+            if is_unique_violation(&e) {
+                return Json(json!({
+                    "status": "error",
+                    "message": "Supplier with this name already exists"
+                }));
+            }
+            purchasing_error(e)
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/suppliers",
+    tag = "purchasing",
+    responses(
+        (status = 200, description = "The admin's suppliers under `suppliers`", body = ApiResponse),
+    )
+)]
+pub async fn list_suppliers(
+    State(state): State<AppState>,
+    admin: AuthAdmin,
+) -> impl IntoResponse {
+    match Supplier::fetch_for_admin(&state.pool, admin.admin_id).await {
+        Ok(suppliers) => Json(json!({
+            "status": "success",
+            "suppliers": suppliers
+        })),
+        Err(e) => purchasing_error(e.into()),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/purchase_orders",
+    tag = "purchasing",
+    request_body = NewPurchaseOrder,
+    responses(
+        (status = 200, description = "Purchase order created; carries `purchase_order` and `lines`", body = ApiResponse),
+    )
+)]
+pub async fn create_purchase_order(
+    State(state): State<AppState>,
+    admin: AuthAdmin,
+    Json(payload): Json<NewPurchaseOrder>,
+) -> impl IntoResponse {
+    match PurchaseOrder::create_purchase_order(&state.pool, admin.admin_id, &payload).await {
+        Ok((purchase_order, lines)) => Json(json!({
+            "status": "success",
+            "message": "Purchase order created",
+            "purchase_order": purchase_order,
+            "lines": lines
+        })),
+        Err(e) => purchasing_error(e),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/purchase_orders/{id}",
+    tag = "purchasing",
+    params(
+        ("id" = i32, Path, description = "Purchase order id"),
+    ),
+    responses(
+        (status = 200, description = "The purchase order under `purchase_order` with its `lines`", body = ApiResponse),
+    )
+)]
+pub async fn get_purchase_order(
+    State(state): State<AppState>,
+    admin: AuthAdmin,
+    Path(purchase_order_id): Path<i32>,
+) -> impl IntoResponse {
+    match PurchaseOrder::fetch_purchase_order(&state.pool, admin.admin_id, purchase_order_id).await {
+        Ok(Some((purchase_order, lines))) => Json(json!({
+            "status": "success",
+            "purchase_order": purchase_order,
+            "lines": lines
+        })),
+        Ok(None) => purchasing_error(PurchasingError::NotFound(purchase_order_id)),
+        Err(e) => purchasing_error(e.into()),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/purchase_orders/{id}/cancel",
+    tag = "purchasing",
+    params(
+        ("id" = i32, Path, description = "Purchase order id"),
+    ),
+    responses(
+        (status = 200, description = "Purchase order cancelled", body = ApiResponse),
+    )
+)]
+pub async fn cancel_purchase_order(
+    State(state): State<AppState>,
+    admin: AuthAdmin,
+    Path(purchase_order_id): Path<i32>,
+) -> impl IntoResponse {
+    match PurchaseOrder::cancel_purchase_order(&state.pool, admin.admin_id, purchase_order_id).await {
+        Ok(purchase_order) => Json(json!({
+            "status": "success",
+            "message": "Purchase order cancelled",
+            "purchase_order": purchase_order
+        })),
+        Err(e) => purchasing_error(e),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/purchase_orders/{id}/receipts",
+    tag = "purchasing",
+    request_body = NewGoodsReceipt,
+    params(
+        ("id" = i32, Path, description = "Purchase order id"),
+    ),
+    responses(
+        (status = 200, description = "Goods received into stock; carries `goods_receipt`", body = ApiResponse),
+    )
+)]
+pub async fn receive_goods(
+    State(state): State<AppState>,
+    admin: AuthAdmin,
+    Path(purchase_order_id): Path<i32>,
+    Json(payload): Json<NewGoodsReceipt>,
+) -> impl IntoResponse {
+    match GoodsReceipt::receive(&state.pool, admin.admin_id, purchase_order_id, &payload).await {
+        Ok((goods_receipt, lines, purchase_order)) => Json(json!({
+            "status": "success",
+            "message": "Goods received into stock",
+            "goods_receipt": goods_receipt,
+            "lines": lines,
+            "purchase_order": purchase_order
+        })),
+        Err(e) => purchasing_error(e),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/purchase_orders/{id}/supplier_invoices",
+    tag = "purchasing",
+    request_body = NewSupplierInvoice,
+    params(
+        ("id" = i32, Path, description = "Purchase order id"),
+    ),
+    responses(
+        (status = 200, description = "Supplier invoice recorded; carries `supplier_invoice`", body = ApiResponse),
+    )
+)]
+pub async fn record_supplier_invoice(
+    State(state): State<AppState>,
+    admin: AuthAdmin,
+    Path(purchase_order_id): Path<i32>,
+    Json(payload): Json<NewSupplierInvoice>,
+) -> impl IntoResponse {
+    if payload.invoice_number.trim().is_empty() {
+        return Json(json!({
+            "status": "error",
+            "message": "Missing invoice number"
+        }));
+    }
+
+    match SupplierInvoice::record(&state.pool, admin.admin_id, purchase_order_id, &payload).await {
+        Ok((invoice, lines)) => Json(json!({
+            "status": "success",
+            "message": "Supplier invoice recorded",
+            "supplier_invoice": invoice,
+            "lines": lines
+        })),
+        Err(e) if is_unique_violation(&e) => Json(json!({
+            "status": "error",
+            "message": "This supplier invoice number has already been recorded"
+        })),
+        Err(e) => purchasing_error(e),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/purchase_orders/{id}/match",
+    tag = "purchasing",
+    params(
+        ("id" = i32, Path, description = "Purchase order id"),
+    ),
+    responses(
+        (status = 200, description = "Ordered, received and invoiced quantities per line under `match`", body = ApiResponse),
+    )
+)]
+pub async fn match_purchase_order(
+    State(state): State<AppState>,
+    admin: AuthAdmin,
+    Path(purchase_order_id): Path<i32>,
+) -> impl IntoResponse {
+    match MatchReport::fetch(&state.pool, admin.admin_id, purchase_order_id).await {
+        Ok(Some(report)) => Json(json!({
+            "status": "success",
+            "match": report
+        })),
+        Ok(None) => purchasing_error(PurchasingError::NotFound(purchase_order_id)),
+        Err(e) => purchasing_error(e.into()),
+    }
+}
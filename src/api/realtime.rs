@@ -0,0 +1,114 @@
+use std::future::Future;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use axum::{
+    extract::{
+        ws::{close_code, CloseFrame, Message, WebSocket, WebSocketUpgrade},
+        Query, State,
+    },
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+};
+use serde::Deserialize;
+use utoipa::IntoParams;
+use tokio::sync::broadcast::error::RecvError;
+use crate::AppState;
+use crate::middleware::auth::{bearer_token, Principal};
+use crate::realtime::NotificationHub;
+use tracing::warn;
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct TokenQuery {
+    // Browsers cannot set headers on a WebSocket handshake, so the token may come as ?token=
+    pub token: Option<String>,
+}
+
+// Streams order status changes and low-stock alerts for the admin named in the token
+#[utoipa::path(
+    get,
+    path = "/api/v1/ws/notifications",
+    tag = "realtime",
+    params(TokenQuery),
+    responses(
+        (status = 101, description = "Switches to a WebSocket streaming order status and low-stock notifications"),
+        (status = 401, description = "Missing or invalid token"),
+        (status = 503, description = "Redis or JWT keys are not configured"),
+    )
+)]
+pub async fn notifications_socket(
+    State(state): State<AppState>,
+    Query(query): Query<TokenQuery>,
+    headers: HeaderMap,
+    ws: WebSocketUpgrade,
+) -> Response {
+    let (Some(keys), Some(hub)) = (&state.jwt_keys, &state.notification_hub) else {
+        return (StatusCode::SERVICE_UNAVAILABLE, "Realtime notifications are not configured").into_response();
+    };
+    let Some(token) = query.token.as_deref().or_else(|| bearer_token(&headers)) else {
+        return (StatusCode::UNAUTHORIZED, "Missing token").into_response();
+    };
+    let claims = match keys.decode(token) {
+        Ok(claims) => claims,
+        Err(e) => {
+            warn!(error = %e, "Rejected WebSocket token");
+            return (StatusCode::UNAUTHORIZED, "Invalid token").into_response();
+        }
+    };
+
+    let hub = hub.clone();
+    let principal = Principal(claims.sub.clone());
+    let stopping = state.shutdown.requested();
+    let mut response = ws.on_upgrade(move |socket| {
+        stream_notifications(socket, hub, claims.admin_id, claims.exp, stopping)
+    });
+    response.extensions_mut().insert(principal);
+    response
+}
+
+async fn stream_notifications(
+    mut socket: WebSocket,
+    hub: NotificationHub,
+    admin_id: i32,
+    expires_at: u64,
+    stopping: impl Future<Output = ()>,
+) {
+    let mut updates = hub.subscribe();
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+    let expiry = tokio::time::sleep(Duration::from_secs(expires_at.saturating_sub(now)));
+    tokio::pin!(expiry);
+    tokio::pin!(stopping);
+
+    loop {
+        tokio::select! {
+            update = updates.recv() => match update {
+                Ok(message) if message.admin_id == admin_id => {
+                    if socket.send(Message::Text(message.payload.to_string())).await.is_err() {
+                        break;
+                    }
+                }
+                Ok(_) => {}
+                Err(RecvError::Lagged(skipped)) => {
+                    warn!(admin_id, skipped, "WebSocket fell behind, notifications dropped");
+                }
+                Err(RecvError::Closed) => break,
+            },
+            incoming = socket.recv() => match incoming {
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                // Pings are answered by the socket itself; clients have nothing else to say
+                Some(Ok(_)) => {}
+            },
+            // The connection lives no longer than the token that opened it
+            _ = &mut expiry => {
+                let _ = socket.send(Message::Close(None)).await;
+                break;
+            }
+            // Upgraded connections aren't drained by the server, so tell the client to reconnect elsewhere
+            _ = &mut stopping => {
+                let frame = CloseFrame { code: close_code::AWAY, reason: "Server shutting down".into() };
+                let _ = socket.send(Message::Close(Some(frame))).await;
+                break;
+            }
+        }
+    }
+}
@@ -0,0 +1,183 @@
+use axum::{
+    extract::{Json, Path, State},
+    response::IntoResponse,
+};
+use serde::Deserialize;
+use utoipa::ToSchema;
+use serde_json::json;
+use crate::AppState;
+use crate::db::returns::{CreditNote, NewReturn, ReturnError, ReturnRequest};
+use crate::middleware::auth::AuthAdmin;
+use tracing::error;
+
+#[derive(Deserialize, ToSchema)]
+pub struct ReturnDecision {
+    pub note: Option<String>,
+}
+
+fn return_error(e: ReturnError) -> Json<serde_json::Value> {
+    if let ReturnError::Db(db_err) = &e {
+        error!("Return DB error: {:?}", db_err);
+    }
+    Json(json!({
+        "status": "error",
+        "message": e.to_string()
+    }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/orders/{id}/returns",
+    tag = "returns",
+    request_body = NewReturn,
+    params(
+        ("id" = i32, Path, description = "Order id"),
+    ),
+    responses(
+        (status = 200, description = "Return requested; carries `return` and `lines`", body = ApiResponse),
+    )
+)]
+pub async fn request_return(
+    State(state): State<AppState>,
+    admin: AuthAdmin,
+    Path(order_id): Path<i32>,
+    Json(payload): Json<NewReturn>,
+) -> impl IntoResponse {
+    if payload.reason.trim().is_empty() {
+        return Json(json!({
+            "status": "error",
+            "message": "Missing return reason"
+        }));
+    }
+
+    match ReturnRequest::create(&state.pool, order_id, admin.admin_id, &payload).await {
+        Ok(request) => Json(json!({
+            "status": "success",
+            "message": "Return requested",
+            "return": request
+        })),
+        Err(e) => return_error(e),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/returns/{id}",
+    tag = "returns",
+    params(
+        ("id" = i32, Path, description = "Return id"),
+    ),
+    responses(
+        (status = 200, description = "The return under `return` with its `lines`", body = ApiResponse),
+    )
+)]
+pub async fn get_return(
+    State(state): State<AppState>,
+    admin: AuthAdmin,
+    Path(return_id): Path<i32>,
+) -> impl IntoResponse {
+    let (request, lines) = match ReturnRequest::fetch_return(&state.pool, return_id).await {
+        Ok(Some((request, _))) if request.admin_id != admin.admin_id => {
+            return return_error(ReturnError::NotOwner(admin.admin_id))
+        }
+        Ok(Some(found)) => found,
+        Ok(None) => return return_error(ReturnError::NotFound(return_id)),
+        Err(e) => return return_error(e.into()),
+    };
+
+    match CreditNote::fetch_for_return(&state.pool, return_id).await {
+        Ok(credit_note) => Json(json!({
+            "status": "success",
+            "return": request,
+            "lines": lines,
+            "credit_note": credit_note
+        })),
+        Err(e) => return_error(e.into()),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/returns/{id}/approve",
+    tag = "returns",
+    request_body = ReturnDecision,
+    params(
+        ("id" = i32, Path, description = "Return id"),
+    ),
+    responses(
+        (status = 200, description = "Return approved, goods restocked and a credit note issued", body = ApiResponse),
+    )
+)]
+pub async fn approve_return(
+    State(state): State<AppState>,
+    admin: AuthAdmin,
+    Path(return_id): Path<i32>,
+    Json(payload): Json<ReturnDecision>,
+) -> impl IntoResponse {
+    match ReturnRequest::approve(&state.pool, return_id, admin.admin_id, payload.note.as_deref()).await {
+        Ok((request, credit_note)) => Json(json!({
+            "status": "success",
+            "message": format!("Return approved, credit note {} issued", credit_note.credit_note_number),
+            "return": request,
+            "credit_note": credit_note
+        })),
+        Err(e) => return_error(e),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/returns/{id}/reject",
+    tag = "returns",
+    request_body = ReturnDecision,
+    params(
+        ("id" = i32, Path, description = "Return id"),
+    ),
+    responses(
+        (status = 200, description = "Return rejected", body = ApiResponse),
+    )
+)]
+pub async fn reject_return(
+    State(state): State<AppState>,
+    admin: AuthAdmin,
+    Path(return_id): Path<i32>,
+    Json(payload): Json<ReturnDecision>,
+) -> impl IntoResponse {
+    match ReturnRequest::reject(&state.pool, return_id, admin.admin_id, payload.note.as_deref()).await {
+        Ok(request) => Json(json!({
+            "status": "success",
+            "message": "Return rejected",
+            "return": request
+        })),
+        Err(e) => return_error(e),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/credit_notes/{id}",
+    tag = "returns",
+    params(
+        ("id" = i32, Path, description = "Credit note id"),
+    ),
+    responses(
+        (status = 200, description = "The credit note under `credit_note`", body = ApiResponse),
+    )
+)]
+pub async fn get_credit_note(
+    State(state): State<AppState>,
+    admin: AuthAdmin,
+    Path(credit_note_id): Path<i32>,
+) -> impl IntoResponse {
+    match CreditNote::fetch_credit_note(&state.pool, credit_note_id).await {
+        Ok(Some(credit_note)) if credit_note.admin_id == admin.admin_id => Json(json!({
+            "status": "success",
+            "credit_note": credit_note
+        })),
+        Ok(_) => Json(json!({
+            "status": "error",
+            "message": format!("Credit note {} not found", credit_note_id)
+        })),
+        Err(e) => return_error(e.into()),
+    }
+}
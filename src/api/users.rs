@@ -1,8 +1,7 @@
 use axum::{Json, extract::Path};
 use serde_json::json;
-use sqlx::postgres::PgPoolOptions;
-use sqlx::PgPool;
 
+#[allow(dead_code)]
 pub async fn get_user(Path(id): Path<i32>) -> Json<serde_json::Value> {
     Json(json!({
         "id": id,
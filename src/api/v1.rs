@@ -0,0 +1,79 @@
+use axum::{
+    middleware,
+    routing::{get, post, put},
+    Router,
+};
+use crate::AppState;
+use crate::api::auth::{create_admin, create_admin_users, login, register, verify_otp};
+use crate::api::inventory::{
+    adjust_stock, create_warehouse, current_stock, issue_stock, list_warehouses, low_stock, receive_stock,
+    set_threshold, stock_ledger, transfer_stock,
+};
+use crate::api::invoices::{email_invoice, get_invoice, issue_invoice};
+use crate::api::orders::{cancel_order, deliver_order, get_order, place_order, quote_order};
+use crate::api::payments::{list_payments, payment_webhook, reconciliation, record_payment, record_refund};
+use crate::api::pricing::{add_price_list_item, create_coupon, create_discount, create_price_list};
+use crate::api::products::{create_product, list_products};
+use crate::api::purchasing::{
+    cancel_purchase_order, create_purchase_order, create_supplier, get_purchase_order, list_suppliers,
+    match_purchase_order, receive_goods, record_supplier_invoice,
+};
+use crate::api::realtime::notifications_socket;
+use crate::api::returns::{approve_return, get_credit_note, get_return, reject_return, request_return};
+use crate::middleware::auth::jwt_auth;
+
+// Version 1 of the API, mounted under `/api/v1`. Paths are relative to that prefix.
+// Everything but sign-up, login, provider webhooks and the WebSocket (which checks its
+// own token) needs an admin's access token.
+pub fn routes(state: &AppState) -> Router<AppState> {
+    let public = Router::new()
+        .route("/login", post(login))
+        .route("/verify", post(verify_otp))
+        .route("/users", post(register))
+        .route("/admins", post(create_admin))
+        .route("/admin_users", post(create_admin_users))
+        .route("/payments/webhooks/:provider", post(payment_webhook))
+        .route("/ws/notifications", get(notifications_socket));
+
+    let admin = Router::new()
+        .route("/products", post(create_product).get(list_products))
+        .route("/warehouses", post(create_warehouse).get(list_warehouses))
+        .route("/inventory/stock", get(current_stock))
+        .route("/inventory/ledger", get(stock_ledger))
+        .route("/inventory/receipts", post(receive_stock))
+        .route("/inventory/issues", post(issue_stock))
+        .route("/inventory/adjustments", post(adjust_stock))
+        .route("/inventory/transfers", post(transfer_stock))
+        .route("/inventory/thresholds", put(set_threshold))
+        .route("/inventory/low_stock", get(low_stock))
+        .route("/price_lists", post(create_price_list))
+        .route("/price_lists/:id/items", post(add_price_list_item))
+        .route("/discounts", post(create_discount))
+        .route("/coupons", post(create_coupon))
+        .route("/orders/quote", post(quote_order))
+        .route("/orders", post(place_order))
+        .route("/orders/:id", get(get_order))
+        .route("/orders/:id/cancel", post(cancel_order))
+        .route("/orders/:id/deliver", post(deliver_order))
+        .route("/orders/:id/invoice", post(issue_invoice))
+        .route("/orders/:id/payments", post(record_payment).get(list_payments))
+        .route("/orders/:id/refunds", post(record_refund))
+        .route("/orders/:id/returns", post(request_return))
+        .route("/returns/:id", get(get_return))
+        .route("/returns/:id/approve", post(approve_return))
+        .route("/returns/:id/reject", post(reject_return))
+        .route("/credit_notes/:id", get(get_credit_note))
+        .route("/suppliers", post(create_supplier).get(list_suppliers))
+        .route("/purchase_orders", post(create_purchase_order))
+        .route("/purchase_orders/:id", get(get_purchase_order))
+        .route("/purchase_orders/:id/cancel", post(cancel_purchase_order))
+        .route("/purchase_orders/:id/receipts", post(receive_goods))
+        .route("/purchase_orders/:id/supplier_invoices", post(record_supplier_invoice))
+        .route("/purchase_orders/:id/match", get(match_purchase_order))
+        .route("/payments/reconciliation", get(reconciliation))
+        .route("/invoices/:id", get(get_invoice))
+        .route("/invoices/:id/email", post(email_invoice))
+        .route_layer(middleware::from_fn_with_state(state.clone(), jwt_auth));
+
+    public.merge(admin)
+}
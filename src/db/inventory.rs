@@ -0,0 +1,443 @@
+use std::fmt;
+
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgConnection, PgPool};
+
+use crate::db::outbox::OutboxEvent;
+use crate::db::products::Product;
+use crate::events::StockMoved;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MovementType {
+    Receipt,
+    Issue,
+    Transfer,
+    Adjustment,
+    Reservation,
+}
+
+impl MovementType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MovementType::Receipt => "receipt",
+            MovementType::Issue => "issue",
+            MovementType::Transfer => "transfer",
+            MovementType::Adjustment => "adjustment",
+            MovementType::Reservation => "reservation",
+        }
+    }
+}
+
+#[derive(FromRow, Serialize, Deserialize, Debug, Clone)]
+pub struct Warehouse {
+    pub id: i32,
+    pub admin_id: i32,
+    pub code: String,
+    pub name: String,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(FromRow, Serialize, Deserialize, Debug, Clone)]
+pub struct StockMovement {
+    pub id: i64,
+    pub product_id: i32,
+    pub warehouse_id: i32,
+    pub movement_type: String,
+    pub on_hand_delta: i32,
+    pub reserved_delta: i32,
+    pub reference: Option<String>,
+    pub note: Option<String>,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(FromRow, Serialize, Deserialize, Debug, Clone)]
+pub struct WarehouseStock {
+    pub product_id: i32,
+    pub warehouse_id: i32,
+    pub on_hand: i64,
+    pub reserved: i64,
+    pub available: i64,
+}
+
+#[derive(FromRow, Serialize, Deserialize, Debug, Clone)]
+pub struct StockThreshold {
+    pub product_id: i32,
+    pub warehouse_id: i32,
+    pub min_quantity: i32,
+}
+
+#[derive(FromRow, Serialize, Deserialize, Debug, Clone)]
+pub struct LowStock {
+    pub product_id: i32,
+    pub sku: String,
+    pub warehouse_id: i32,
+    pub warehouse_code: String,
+    pub available: i64,
+    pub min_quantity: i32,
+}
+
+// One ledger posting: how on-hand and reserved stock change in a warehouse
+#[derive(Debug, Clone)]
+pub struct Posting<'a> {
+    pub product_id: i32,
+    pub warehouse_id: i32,
+    pub movement_type: MovementType,
+    pub on_hand_delta: i32,
+    pub reserved_delta: i32,
+    pub reference: Option<&'a str>,
+    pub note: Option<&'a str>,
+}
+
+#[derive(Debug)]
+pub enum InventoryError {
+    Db(sqlx::Error),
+    InvalidQuantity,
+    SameWarehouse,
+    ProductNotFound(i32),
+    WarehouseNotFound(i32),
+    InsufficientStock { product_id: i32, warehouse_id: i32, requested: i64, available: i64 },
+}
+
+impl From<sqlx::Error> for InventoryError {
+    fn from(e: sqlx::Error) -> Self {
+        InventoryError::Db(e)
+    }
+}
+
+impl fmt::Display for InventoryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InventoryError::Db(_) => write!(f, "DB Error"),
+            InventoryError::InvalidQuantity => write!(f, "Quantity must be greater than zero"),
+            InventoryError::SameWarehouse => write!(f, "Source and destination warehouse must differ"),
+            InventoryError::ProductNotFound(id) => write!(f, "Product {} not found", id),
+            InventoryError::WarehouseNotFound(id) => write!(f, "Warehouse {} not found", id),
+            InventoryError::InsufficientStock { product_id, warehouse_id, requested, available } => write!(
+                f,
+                "Insufficient stock for product {} in warehouse {}: requested {}, available {}",
+                product_id, warehouse_id, requested, available
+            ),
+        }
+    }
+}
+
+impl Warehouse {
+    pub async fn create_warehouse(pool: &PgPool, admin_id: i32, code: &str, name: &str) -> Result<Warehouse, sqlx::Error> {
+        sqlx::query_as::<_, Warehouse>(
+            "INSERT INTO warehouses (admin_id, code, name) VALUES ($1, $2, $3) RETURNING *"
+        )
+        .bind(admin_id)
+        .bind(code)
+        .bind(name)
+        .fetch_one(pool)
+        .await
+    }
+
+    pub async fn fetch_for_admin(pool: &PgPool, admin_id: i32) -> Result<Vec<Warehouse>, sqlx::Error> {
+        sqlx::query_as::<_, Warehouse>("SELECT * FROM warehouses WHERE admin_id = $1 ORDER BY id ASC")
+            .bind(admin_id)
+            .fetch_all(pool)
+            .await
+    }
+
+    pub async fn fetch_warehouse(pool: &PgPool, warehouse_id: i32) -> Result<Option<Warehouse>, sqlx::Error> {
+        sqlx::query_as::<_, Warehouse>("SELECT * FROM warehouses WHERE id = $1")
+            .bind(warehouse_id)
+            .fetch_optional(pool)
+            .await
+    }
+}
+
+// Locks the product row and checks that both the product and the warehouse belong to the admin.
+// Every posting for a product goes through this lock, which serialises stock changes.
+pub async fn lock_product_in_warehouse(
+    conn: &mut PgConnection,
+    admin_id: i32,
+    product_id: i32,
+    warehouse_id: i32,
+) -> Result<Product, InventoryError> {
+    let product = sqlx::query_as::<_, Product>("SELECT * FROM products WHERE id = $1 AND admin_id = $2 FOR UPDATE")
+        .bind(product_id)
+        .bind(admin_id)
+        .fetch_optional(&mut *conn)
+        .await?
+        .ok_or(InventoryError::ProductNotFound(product_id))?;
+
+    let warehouse: Option<(i32,)> = sqlx::query_as("SELECT id FROM warehouses WHERE id = $1 AND admin_id = $2")
+        .bind(warehouse_id)
+        .bind(admin_id)
+        .fetch_optional(&mut *conn)
+        .await?;
+
+    match warehouse {
+        Some(_) => Ok(product),
+        None => Err(InventoryError::WarehouseNotFound(warehouse_id)),
+    }
+}
+
+impl WarehouseStock {
+    pub async fn fetch(conn: &mut PgConnection, product_id: i32, warehouse_id: i32) -> Result<WarehouseStock, sqlx::Error> {
+        let stock = sqlx::query_as::<_, WarehouseStock>(
+            "SELECT * FROM warehouse_stock WHERE product_id = $1 AND warehouse_id = $2"
+        )
+        .bind(product_id)
+        .bind(warehouse_id)
+        .fetch_optional(conn)
+        .await?;
+
+        Ok(stock.unwrap_or(WarehouseStock {
+            product_id,
+            warehouse_id,
+            on_hand: 0,
+            reserved: 0,
+            available: 0,
+        }))
+    }
+
+    pub async fn fetch_for_admin(pool: &PgPool, admin_id: i32) -> Result<Vec<WarehouseStock>, sqlx::Error> {
+        sqlx::query_as::<_, WarehouseStock>(
+            "SELECT s.* FROM warehouse_stock s
+             JOIN warehouses w ON w.id = s.warehouse_id
+             WHERE w.admin_id = $1
+             ORDER BY s.product_id, s.warehouse_id"
+        )
+        .bind(admin_id)
+        .fetch_all(pool)
+        .await
+    }
+
+    // Fails unless the warehouse can give up `quantity` units that aren't reserved
+    pub async fn ensure_available(
+        conn: &mut PgConnection,
+        product_id: i32,
+        warehouse_id: i32,
+        quantity: i64,
+    ) -> Result<(), InventoryError> {
+        let stock = WarehouseStock::fetch(conn, product_id, warehouse_id).await?;
+        if stock.available < quantity {
+            return Err(InventoryError::InsufficientStock {
+                product_id,
+                warehouse_id,
+                requested: quantity,
+                available: stock.available,
+            });
+        }
+        Ok(())
+    }
+}
+
+impl StockMovement {
+    // Appends to the ledger and keeps the product-wide totals in step.
+    // Callers must hold the product lock (see lock_product_in_warehouse).
+    pub async fn post(conn: &mut PgConnection, posting: &Posting<'_>) -> Result<StockMovement, sqlx::Error> {
+        let movement = sqlx::query_as::<_, StockMovement>(
+            "INSERT INTO stock_movements
+                (product_id, warehouse_id, movement_type, on_hand_delta, reserved_delta, reference, note)
+             VALUES ($1, $2, $3, $4, $5, $6, $7)
+             RETURNING *"
+        )
+        .bind(posting.product_id)
+        .bind(posting.warehouse_id)
+        .bind(posting.movement_type.as_str())
+        .bind(posting.on_hand_delta)
+        .bind(posting.reserved_delta)
+        .bind(posting.reference)
+        .bind(posting.note)
+        .fetch_one(&mut *conn)
+        .await?;
+
+        sqlx::query(
+            "UPDATE products
+             SET stock_on_hand = stock_on_hand + $1, stock_reserved = stock_reserved + $2
+             WHERE id = $3"
+        )
+        .bind(posting.on_hand_delta)
+        .bind(posting.reserved_delta)
+        .bind(posting.product_id)
+        .execute(&mut *conn)
+        .await?;
+
+        OutboxEvent::record(conn, &StockMoved {
+            movement_id: movement.id,
+            product_id: movement.product_id,
+            warehouse_id: movement.warehouse_id,
+            movement_type: movement.movement_type.clone(),
+            on_hand_delta: movement.on_hand_delta,
+            reserved_delta: movement.reserved_delta,
+            reference: movement.reference.clone(),
+        })
+        .await?;
+
+        Ok(movement)
+    }
+
+    // Receipts, issues and adjustments against a single warehouse
+    pub async fn record(pool: &PgPool, admin_id: i32, posting: &Posting<'_>) -> Result<StockMovement, InventoryError> {
+        let Posting { product_id, warehouse_id, on_hand_delta, .. } = *posting;
+        let mut tx = pool.begin().await?;
+
+        lock_product_in_warehouse(&mut tx, admin_id, product_id, warehouse_id).await?;
+        if on_hand_delta < 0 {
+            WarehouseStock::ensure_available(&mut tx, product_id, warehouse_id, -i64::from(on_hand_delta)).await?;
+        }
+
+        let movement = StockMovement::post(&mut tx, posting).await?;
+
+        tx.commit().await?;
+
+        Ok(movement)
+    }
+
+    // Moves stock between two warehouses of the same admin as a pair of postings
+    pub async fn transfer(
+        pool: &PgPool,
+        admin_id: i32,
+        product_id: i32,
+        from_warehouse_id: i32,
+        to_warehouse_id: i32,
+        quantity: i32,
+        note: Option<&str>,
+    ) -> Result<Vec<StockMovement>, InventoryError> {
+        if quantity <= 0 {
+            return Err(InventoryError::InvalidQuantity);
+        }
+        if from_warehouse_id == to_warehouse_id {
+            return Err(InventoryError::SameWarehouse);
+        }
+
+        let mut tx = pool.begin().await?;
+
+        lock_product_in_warehouse(&mut tx, admin_id, product_id, from_warehouse_id).await?;
+        lock_product_in_warehouse(&mut tx, admin_id, product_id, to_warehouse_id).await?;
+        WarehouseStock::ensure_available(&mut tx, product_id, from_warehouse_id, i64::from(quantity)).await?;
+
+        let reference = format!("transfer:{}->{}", from_warehouse_id, to_warehouse_id);
+        let outgoing = StockMovement::post(
+            &mut tx,
+            &Posting {
+                product_id,
+                warehouse_id: from_warehouse_id,
+                movement_type: MovementType::Transfer,
+                on_hand_delta: -quantity,
+                reserved_delta: 0,
+                reference: Some(&reference),
+                note,
+            },
+        )
+        .await?;
+        let incoming = StockMovement::post(
+            &mut tx,
+            &Posting {
+                product_id,
+                warehouse_id: to_warehouse_id,
+                movement_type: MovementType::Transfer,
+                on_hand_delta: quantity,
+                reserved_delta: 0,
+                reference: Some(&reference),
+                note,
+            },
+        )
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(vec![outgoing, incoming])
+    }
+
+    pub async fn fetch_ledger(
+        pool: &PgPool,
+        admin_id: i32,
+        product_id: i32,
+        warehouse_id: Option<i32>,
+    ) -> Result<Vec<StockMovement>, sqlx::Error> {
+        sqlx::query_as::<_, StockMovement>(
+            "SELECT m.* FROM stock_movements m
+             JOIN products p ON p.id = m.product_id
+             WHERE m.product_id = $1 AND p.admin_id = $2 AND ($3::INTEGER IS NULL OR m.warehouse_id = $3)
+             ORDER BY m.id ASC"
+        )
+        .bind(product_id)
+        .bind(admin_id)
+        .bind(warehouse_id)
+        .fetch_all(pool)
+        .await
+    }
+}
+
+impl StockThreshold {
+    // Both the product and the warehouse have to belong to the admin setting the threshold
+    pub async fn upsert(
+        pool: &PgPool,
+        admin_id: i32,
+        product_id: i32,
+        warehouse_id: i32,
+        min_quantity: i32,
+    ) -> Result<StockThreshold, InventoryError> {
+        let (owns_product, owns_warehouse): (bool, bool) = sqlx::query_as(
+            "SELECT EXISTS (SELECT 1 FROM products WHERE id = $1 AND admin_id = $3),
+                    EXISTS (SELECT 1 FROM warehouses WHERE id = $2 AND admin_id = $3)"
+        )
+        .bind(product_id)
+        .bind(warehouse_id)
+        .bind(admin_id)
+        .fetch_one(pool)
+        .await?;
+        if !owns_product {
+            return Err(InventoryError::ProductNotFound(product_id));
+        }
+        if !owns_warehouse {
+            return Err(InventoryError::WarehouseNotFound(warehouse_id));
+        }
+
+        let threshold = sqlx::query_as::<_, StockThreshold>(
+            "INSERT INTO stock_thresholds (product_id, warehouse_id, min_quantity)
+             VALUES ($1, $2, $3)
+             ON CONFLICT (product_id, warehouse_id) DO UPDATE SET min_quantity = EXCLUDED.min_quantity
+             RETURNING *"
+        )
+        .bind(product_id)
+        .bind(warehouse_id)
+        .bind(min_quantity)
+        .fetch_one(pool)
+        .await?;
+
+        Ok(threshold)
+    }
+}
+
+impl LowStock {
+    // Products whose available stock in a warehouse has dropped below its threshold
+    pub async fn fetch_for_admin(pool: &PgPool, admin_id: i32) -> Result<Vec<LowStock>, sqlx::Error> {
+        sqlx::query_as::<_, LowStock>(
+            "SELECT t.product_id, p.sku, t.warehouse_id, w.code AS warehouse_code,
+                    COALESCE(s.available, 0)::BIGINT AS available, t.min_quantity
+             FROM stock_thresholds t
+             JOIN products p ON p.id = t.product_id
+             JOIN warehouses w ON w.id = t.warehouse_id
+             LEFT JOIN warehouse_stock s ON s.product_id = t.product_id AND s.warehouse_id = t.warehouse_id
+             WHERE w.admin_id = $1 AND COALESCE(s.available, 0) < t.min_quantity
+             ORDER BY t.product_id, t.warehouse_id"
+        )
+        .bind(admin_id)
+        .fetch_all(pool)
+        .await
+    }
+
+    // Current stock against the threshold for one product in one warehouse, if a threshold is set
+    pub async fn fetch_threshold(pool: &PgPool, product_id: i32, warehouse_id: i32) -> Result<Option<LowStock>, sqlx::Error> {
+        sqlx::query_as::<_, LowStock>(
+            "SELECT t.product_id, p.sku, t.warehouse_id, w.code AS warehouse_code,
+                    COALESCE(s.available, 0)::BIGINT AS available, t.min_quantity
+             FROM stock_thresholds t
+             JOIN products p ON p.id = t.product_id
+             JOIN warehouses w ON w.id = t.warehouse_id
+             LEFT JOIN warehouse_stock s ON s.product_id = t.product_id AND s.warehouse_id = t.warehouse_id
+             WHERE t.product_id = $1 AND t.warehouse_id = $2"
+        )
+        .bind(product_id)
+        .bind(warehouse_id)
+        .fetch_optional(pool)
+        .await
+    }
+}
@@ -0,0 +1,234 @@
+use std::fmt;
+
+use chrono::{NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::types::Json;
+use sqlx::{FromRow, PgConnection, PgPool};
+
+use crate::db::orders::{Order, STATUS_CANCELLED, STATUS_EXPIRED};
+use crate::gst;
+
+pub const SERIES_INVOICE: &str = "INV";
+pub const SERIES_CREDIT_NOTE: &str = "CN";
+
+#[derive(FromRow, Serialize, Deserialize, Debug, Clone)]
+pub struct Invoice {
+    pub id: i32,
+    pub admin_id: i32,
+    pub order_id: i32,
+    pub invoice_number: String,
+    pub financial_year: String,
+    pub sequence_number: i32,
+    pub seller_name: String,
+    pub seller_pincode: String,
+    pub seller_gstin: Option<String>,
+    pub origin_state_code: String,
+    pub buyer_email: String,
+    pub buyer_pincode: String,
+    pub place_of_supply: String,
+    pub supply_type: String,
+    pub taxable_paise: i64,
+    pub cgst_paise: i64,
+    pub sgst_paise: i64,
+    pub igst_paise: i64,
+    pub total_paise: i64,
+    pub lines: Json<Vec<InvoiceLine>>,
+    pub issued_at: NaiveDateTime,
+}
+
+// Snapshot of an order line as printed on the invoice
+#[derive(FromRow, Serialize, Deserialize, Debug, Clone)]
+pub struct InvoiceLine {
+    pub product_id: i32,
+    pub sku: String,
+    pub name: String,
+    pub hsn_code: Option<String>,
+    pub quantity: i32,
+    pub unit_price_paise: i64,
+    pub discount_paise: i64,
+    pub taxable_paise: i64,
+    pub gst_rate_bp: i32,
+    pub cgst_paise: i64,
+    pub sgst_paise: i64,
+    pub igst_paise: i64,
+}
+
+#[derive(Debug)]
+pub enum InvoiceError {
+    Db(sqlx::Error),
+    OrderNotFound(i32),
+    NotOwner(i32),
+    NotInvoiceable(String),
+}
+
+impl From<sqlx::Error> for InvoiceError {
+    fn from(e: sqlx::Error) -> Self {
+        InvoiceError::Db(e)
+    }
+}
+
+impl fmt::Display for InvoiceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InvoiceError::Db(_) => write!(f, "DB Error"),
+            InvoiceError::OrderNotFound(order_id) => write!(f, "Order {} not found", order_id),
+            InvoiceError::NotOwner(admin_id) => write!(f, "Admin {} does not own this order", admin_id),
+            InvoiceError::NotInvoiceable(reason) => write!(f, "Order cannot be invoiced: {}", reason),
+        }
+    }
+}
+
+// Bumps the seller's counter for a document series inside the caller's transaction.
+// The row lock held until commit keeps numbers gapless and unique.
+pub async fn next_sequence(
+    conn: &mut PgConnection,
+    admin_id: i32,
+    series: &str,
+    financial_year: &str,
+) -> Result<i32, sqlx::Error> {
+    let (sequence_number,): (i32,) = sqlx::query_as(
+        "INSERT INTO invoice_sequences (admin_id, series, financial_year, last_number)
+         VALUES ($1, $2, $3, 1)
+         ON CONFLICT (admin_id, series, financial_year)
+         DO UPDATE SET last_number = invoice_sequences.last_number + 1
+         RETURNING last_number"
+    )
+    .bind(admin_id)
+    .bind(series)
+    .bind(financial_year)
+    .fetch_one(conn)
+    .await?;
+
+    Ok(sequence_number)
+}
+
+// GST rules cap document numbers at 16 characters, so "2026-27" is printed as "2627"
+pub fn document_number(series: &str, financial_year: &str, sequence_number: i32) -> String {
+    let year: String = financial_year.chars().filter(char::is_ascii_digit).skip(2).collect();
+    format!("{}/{}/{:06}", series, year, sequence_number)
+}
+
+impl Invoice {
+    // Issues the tax invoice for an order. Numbers come from a per-seller, per-financial-year
+    // counter bumped inside the same transaction, so they stay gapless and never repeat.
+    // Issuing twice for the same order returns the original invoice.
+    pub async fn issue_for_order(pool: &PgPool, order_id: i32, admin_id: i32) -> Result<Invoice, InvoiceError> {
+        let mut tx = pool.begin().await?;
+
+        let order = sqlx::query_as::<_, Order>("SELECT * FROM orders WHERE id = $1 FOR UPDATE")
+            .bind(order_id)
+            .fetch_optional(&mut *tx)
+            .await?
+            .ok_or(InvoiceError::OrderNotFound(order_id))?;
+        if order.admin_id != admin_id {
+            return Err(InvoiceError::NotOwner(admin_id));
+        }
+
+        if let Some(invoice) = Invoice::fetch_for_order(&mut tx, order_id).await? {
+            return Ok(invoice);
+        }
+
+        if order.status == STATUS_CANCELLED || order.status == STATUS_EXPIRED {
+            return Err(InvoiceError::NotInvoiceable(format!("order is {}", order.status)));
+        }
+        let (Some(buyer_pincode), Some(origin_state_code), Some(place_of_supply)) =
+            (order.shipping_pincode.clone(), order.origin_state_code.clone(), order.place_of_supply.clone())
+        else {
+            return Err(InvoiceError::NotInvoiceable("order was placed without GST details".to_string()));
+        };
+
+        let (seller_name, seller_pincode, seller_gstin): (String, String, Option<String>) =
+            sqlx::query_as("SELECT user_name, pincode, gstin FROM admins WHERE id = $1")
+                .bind(order.admin_id)
+                .fetch_one(&mut *tx)
+                .await?;
+
+        let lines = sqlx::query_as::<_, InvoiceLine>(
+            "SELECT l.product_id, p.sku, p.name, l.hsn_code, l.quantity, l.unit_price_paise,
+                    l.discount_paise, l.line_total_paise AS taxable_paise, l.gst_rate_bp,
+                    l.cgst_paise, l.sgst_paise, l.igst_paise
+             FROM order_lines l
+             JOIN products p ON p.id = l.product_id
+             WHERE l.order_id = $1
+             ORDER BY l.id ASC"
+        )
+        .bind(order_id)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        let financial_year = gst::financial_year(Utc::now().naive_utc());
+        let sequence_number = next_sequence(&mut tx, order.admin_id, SERIES_INVOICE, &financial_year).await?;
+        let invoice_number = document_number(SERIES_INVOICE, &financial_year, sequence_number);
+
+        let supply_type = if origin_state_code == place_of_supply {
+            gst::SUPPLY_INTRA_STATE
+        } else {
+            gst::SUPPLY_INTER_STATE
+        };
+        let cgst_paise: i64 = lines.iter().map(|l| l.cgst_paise).sum();
+        let sgst_paise: i64 = lines.iter().map(|l| l.sgst_paise).sum();
+        let igst_paise: i64 = lines.iter().map(|l| l.igst_paise).sum();
+
+        let invoice = sqlx::query_as::<_, Invoice>(
+            "INSERT INTO invoices
+                (admin_id, order_id, invoice_number, financial_year, sequence_number, seller_name,
+                 seller_pincode, seller_gstin, origin_state_code, buyer_email, buyer_pincode, place_of_supply,
+                 supply_type, taxable_paise, cgst_paise, sgst_paise, igst_paise, total_paise, lines)
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19)
+             RETURNING *"
+        )
+        .bind(order.admin_id)
+        .bind(order.id)
+        .bind(&invoice_number)
+        .bind(&financial_year)
+        .bind(sequence_number)
+        .bind(seller_name)
+        .bind(seller_pincode)
+        .bind(seller_gstin)
+        .bind(origin_state_code)
+        .bind(&order.customer_email)
+        .bind(buyer_pincode)
+        .bind(place_of_supply)
+        .bind(supply_type)
+        .bind(order.total_paise)
+        .bind(cgst_paise)
+        .bind(sgst_paise)
+        .bind(igst_paise)
+        .bind(order.total_paise + cgst_paise + sgst_paise + igst_paise)
+        .bind(Json(&lines))
+        .fetch_one(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(invoice)
+    }
+
+    pub async fn fetch_for_order(conn: &mut PgConnection, order_id: i32) -> Result<Option<Invoice>, sqlx::Error> {
+        sqlx::query_as::<_, Invoice>("SELECT * FROM invoices WHERE order_id = $1")
+            .bind(order_id)
+            .fetch_optional(conn)
+            .await
+    }
+
+    // Only the seller's own invoices; another admin's are reported as not found
+    pub async fn fetch_invoice(pool: &PgPool, admin_id: i32, invoice_id: i32) -> Result<Option<Invoice>, sqlx::Error> {
+        sqlx::query_as::<_, Invoice>("SELECT * FROM invoices WHERE id = $1 AND admin_id = $2")
+            .bind(invoice_id)
+            .bind(admin_id)
+            .fetch_optional(pool)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{document_number, SERIES_CREDIT_NOTE, SERIES_INVOICE};
+
+    #[test]
+    fn document_numbers_fit_gst_limit() {
+        assert_eq!(document_number(SERIES_INVOICE, "2026-27", 1), "INV/2627/000001");
+        assert_eq!(document_number(SERIES_CREDIT_NOTE, "2099-00", 999_999), "CN/9900/999999");
+        assert!(document_number(SERIES_INVOICE, "2026-27", 999_999).len() <= 16);
+    }
+}
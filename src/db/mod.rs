@@ -1 +1,11 @@
 pub mod users;
+pub mod products;
+pub mod orders;
+pub mod inventory;
+pub mod pricing;
+pub mod invoices;
+pub mod payments;
+pub mod purchasing;
+pub mod outbox;
+pub mod pool;
+pub mod returns;
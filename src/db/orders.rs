@@ -0,0 +1,668 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use chrono::{Duration, NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use sqlx::types::Json;
+use sqlx::{FromRow, PgConnection, PgPool, Postgres, Transaction};
+
+use crate::db::inventory::{MovementType, Posting, StockMovement, WarehouseStock};
+use crate::db::outbox::OutboxEvent;
+use crate::db::pricing::{ApplicablePrice, Coupon, Discount};
+use crate::db::products::Product;
+use crate::events::{OrderPlaced, OrderStatusChanged};
+use crate::gst::{self, GstState};
+use crate::pricing::{self, LinePricing, PricingError};
+
+pub const STATUS_PENDING_PAYMENT: &str = "pending_payment";
+pub const STATUS_PARTIALLY_PAID: &str = "partially_paid";
+pub const STATUS_PAID: &str = "paid";
+pub const STATUS_DELIVERED: &str = "delivered";
+pub const STATUS_REFUNDED: &str = "refunded";
+pub const STATUS_CANCELLED: &str = "cancelled";
+pub const STATUS_EXPIRED: &str = "expired";
+
+pub const RESERVATION_ACTIVE: &str = "active";
+pub const RESERVATION_RELEASED: &str = "released";
+pub const RESERVATION_CONSUMED: &str = "consumed";
+
+#[derive(FromRow, Serialize, Deserialize, Debug, Clone)]
+pub struct Order {
+    pub id: i32,
+    pub admin_id: i32,
+    pub customer_email: String,
+    pub status: String,
+    pub total_paise: i64,
+    pub payment_due_at: NaiveDateTime,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+    pub warehouse_id: Option<i32>,
+    pub coupon_id: Option<i32>,
+    pub discount_paise: i64,
+    pub shipping_pincode: Option<String>,
+    pub origin_state_code: Option<String>,
+    pub place_of_supply: Option<String>,
+    pub tax_paise: i64,
+    pub grand_total_paise: i64,
+    pub paid_paise: i64,
+    pub refunded_paise: i64,
+}
+
+#[derive(FromRow, Serialize, Deserialize, Debug, Clone)]
+pub struct OrderLine {
+    pub id: i32,
+    pub order_id: i32,
+    pub product_id: i32,
+    pub quantity: i32,
+    pub unit_price_paise: i64,
+    pub line_total_paise: i64,
+    pub list_unit_price_paise: Option<i64>,
+    pub discount_paise: i64,
+    pub pricing: Option<Json<LinePricing>>,
+    pub hsn_code: Option<String>,
+    pub gst_rate_bp: i32,
+    pub cgst_paise: i64,
+    pub sgst_paise: i64,
+    pub igst_paise: i64,
+}
+
+#[derive(FromRow, Serialize, Deserialize, Debug, Clone)]
+pub struct StockReservation {
+    pub id: i32,
+    pub order_id: i32,
+    pub product_id: i32,
+    pub quantity: i32,
+    pub status: String,
+    pub expires_at: NaiveDateTime,
+    pub created_at: NaiveDateTime,
+    pub released_at: Option<NaiveDateTime>,
+    pub warehouse_id: Option<i32>,
+}
+
+#[derive(Deserialize, Debug, Clone, ToSchema)]
+pub struct NewOrderLine {
+    pub product_id: i32,
+    pub quantity: i32,
+}
+
+#[derive(Deserialize, Debug, Clone, ToSchema)]
+pub struct NewOrder {
+    pub warehouse_id: i32,
+    pub customer_email: String,
+    pub shipping_pincode: String,
+    pub lines: Vec<NewOrderLine>,
+    pub coupon_code: Option<String>,
+}
+
+#[derive(Debug)]
+pub enum OrderError {
+    Db(sqlx::Error),
+    EmptyOrder,
+    InvalidQuantity(i32),
+    ProductNotFound(i32),
+    WarehouseNotFound(i32),
+    AdminNotFound(i32),
+    UnknownPincode(String),
+    InsufficientStock { product_id: i32, requested: i64, available: i64 },
+    InvalidCoupon(String),
+    NotFound(i32),
+    NotOwner(i32),
+    InvalidStatus(String),
+    Pricing(PricingError),
+}
+
+impl From<sqlx::Error> for OrderError {
+    fn from(e: sqlx::Error) -> Self {
+        OrderError::Db(e)
+    }
+}
+
+impl From<PricingError> for OrderError {
+    fn from(e: PricingError) -> Self {
+        OrderError::Pricing(e)
+    }
+}
+
+impl fmt::Display for OrderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OrderError::Db(_) => write!(f, "DB Error"),
+            OrderError::EmptyOrder => write!(f, "Order must contain at least one line"),
+            OrderError::InvalidQuantity(product_id) => {
+                write!(f, "Quantity for product {} must be greater than zero", product_id)
+            }
+            OrderError::ProductNotFound(product_id) => write!(f, "Product {} not found", product_id),
+            OrderError::WarehouseNotFound(warehouse_id) => write!(f, "Warehouse {} not found", warehouse_id),
+            OrderError::AdminNotFound(admin_id) => write!(f, "Admin {} not found", admin_id),
+            OrderError::UnknownPincode(pincode) => write!(f, "Cannot determine GST state for pincode {}", pincode),
+            OrderError::InsufficientStock { product_id, requested, available } => write!(
+                f,
+                "Insufficient stock for product {}: requested {}, available {}",
+                product_id, requested, available
+            ),
+            OrderError::InvalidCoupon(code) => write!(f, "Coupon '{}' is invalid or no longer available", code),
+            OrderError::NotFound(order_id) => write!(f, "Order {} not found", order_id),
+            OrderError::NotOwner(admin_id) => write!(f, "Admin {} does not own this order", admin_id),
+            OrderError::InvalidStatus(status) => write!(f, "Order cannot be changed in status '{}'", status),
+            OrderError::Pricing(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl Order {
+    // Part payments taken before a cancel or expiry, which have to be refunded
+    pub fn refund_due_paise(&self) -> i64 {
+        self.paid_paise - self.refunded_paise
+    }
+
+    // Inserts the order and reserves stock in the fulfilment warehouse in a single transaction.
+    // Product rows are locked in id order so concurrent orders can't oversell or deadlock.
+    pub async fn place_order(
+        pool: &PgPool,
+        admin_id: i32,
+        new_order: &NewOrder,
+        payment_timeout: Duration,
+    ) -> Result<(Order, Vec<OrderLine>), OrderError> {
+        let warehouse_id = new_order.warehouse_id;
+        let customer_email = new_order.customer_email.as_str();
+        let quantities = merge_lines(&new_order.lines)?;
+
+        let destination = gst::state_for_pincode(&new_order.shipping_pincode)
+            .ok_or_else(|| OrderError::UnknownPincode(new_order.shipping_pincode.clone()))?;
+
+        let mut tx = pool.begin().await?;
+
+        let origin = seller_state(&mut tx, admin_id).await?;
+        let intra_state = origin.code == destination.code;
+
+        let warehouse: Option<(i32,)> = sqlx::query_as("SELECT id FROM warehouses WHERE id = $1 AND admin_id = $2")
+            .bind(warehouse_id)
+            .bind(admin_id)
+            .fetch_optional(&mut *tx)
+            .await?;
+        if warehouse.is_none() {
+            return Err(OrderError::WarehouseNotFound(warehouse_id));
+        }
+
+        let mut priced: Vec<(Product, i32)> = Vec::with_capacity(quantities.len());
+        for (&product_id, &quantity) in &quantities {
+            let product = sqlx::query_as::<_, Product>(
+                "SELECT * FROM products WHERE id = $1 AND admin_id = $2 FOR UPDATE"
+            )
+            .bind(product_id)
+            .bind(admin_id)
+            .fetch_optional(&mut *tx)
+            .await?
+            .ok_or(OrderError::ProductNotFound(product_id))?;
+
+            let stock = WarehouseStock::fetch(&mut tx, product_id, warehouse_id).await?;
+            if stock.available < i64::from(quantity) {
+                return Err(OrderError::InsufficientStock {
+                    product_id,
+                    requested: i64::from(quantity),
+                    available: stock.available,
+                });
+            }
+            priced.push((product, quantity));
+        }
+
+        let now = Utc::now().naive_utc();
+        let coupon = match new_order.coupon_code.as_deref() {
+            Some(code) => Some(
+                Coupon::lock_by_code(&mut tx, admin_id, code)
+                    .await?
+                    .filter(|coupon| coupon.is_usable(now))
+                    .ok_or_else(|| OrderError::InvalidCoupon(code.to_string()))?,
+            ),
+            None => None,
+        };
+        let line_pricing = price_order(&mut tx, admin_id, customer_email, &priced, coupon.as_ref()).await?;
+        if let Some(coupon) = &coupon {
+            Coupon::redeem(&mut tx, coupon.id).await?;
+        }
+
+        // GST is charged on the discounted value of each line
+        let line_taxes: Vec<gst::LineTax> = priced
+            .iter()
+            .zip(&line_pricing)
+            .map(|((product, _), pricing)| gst::compute_line_tax(pricing.net_paise, product.gst_rate_bp, intra_state))
+            .collect();
+
+        let total_paise: i64 = line_pricing.iter().map(|l| l.net_paise).sum();
+        let discount_paise: i64 = line_pricing.iter().map(|l| l.discount_paise()).sum();
+        let tax_paise: i64 = line_taxes.iter().map(|t| t.total()).sum();
+        let payment_due_at = now + payment_timeout;
+
+        let order = sqlx::query_as::<_, Order>(
+            "INSERT INTO orders
+                (admin_id, warehouse_id, customer_email, status, total_paise, discount_paise, coupon_id,
+                 payment_due_at, shipping_pincode, origin_state_code, place_of_supply, tax_paise, grand_total_paise)
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)
+             RETURNING *"
+        )
+        .bind(admin_id)
+        .bind(warehouse_id)
+        .bind(customer_email)
+        .bind(STATUS_PENDING_PAYMENT)
+        .bind(total_paise)
+        .bind(discount_paise)
+        .bind(coupon.as_ref().map(|c| c.id))
+        .bind(payment_due_at)
+        .bind(new_order.shipping_pincode.trim())
+        .bind(origin.code)
+        .bind(destination.code)
+        .bind(tax_paise)
+        .bind(total_paise + tax_paise)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let reference = format!("order:{}", order.id);
+        let mut order_lines = Vec::with_capacity(priced.len());
+        for (((product, quantity), pricing), tax) in priced.iter().zip(line_pricing).zip(line_taxes) {
+            let line = sqlx::query_as::<_, OrderLine>(
+                "INSERT INTO order_lines
+                    (order_id, product_id, quantity, unit_price_paise, list_unit_price_paise,
+                     discount_paise, line_total_paise, pricing, hsn_code, gst_rate_bp,
+                     cgst_paise, sgst_paise, igst_paise)
+                 VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)
+                 RETURNING *"
+            )
+            .bind(order.id)
+            .bind(product.id)
+            .bind(quantity)
+            .bind(pricing.unit_price_paise)
+            .bind(pricing.list_unit_price_paise)
+            .bind(pricing.discount_paise())
+            .bind(pricing.net_paise)
+            .bind(Json(&pricing))
+            .bind(&product.hsn_code)
+            .bind(product.gst_rate_bp)
+            .bind(tax.cgst_paise)
+            .bind(tax.sgst_paise)
+            .bind(tax.igst_paise)
+            .fetch_one(&mut *tx)
+            .await?;
+            order_lines.push(line);
+
+            sqlx::query(
+                "INSERT INTO stock_reservations (order_id, product_id, warehouse_id, quantity, status, expires_at)
+                 VALUES ($1, $2, $3, $4, $5, $6)"
+            )
+            .bind(order.id)
+            .bind(product.id)
+            .bind(warehouse_id)
+            .bind(quantity)
+            .bind(RESERVATION_ACTIVE)
+            .bind(payment_due_at)
+            .execute(&mut *tx)
+            .await?;
+
+            StockMovement::post(
+                &mut tx,
+                &Posting {
+                    product_id: product.id,
+                    warehouse_id,
+                    movement_type: MovementType::Reservation,
+                    on_hand_delta: 0,
+                    reserved_delta: *quantity,
+                    reference: Some(&reference),
+                    note: None,
+                },
+            )
+            .await?;
+        }
+
+        OutboxEvent::record(&mut tx, &OrderPlaced {
+            order_id: order.id,
+            admin_id: order.admin_id,
+            customer_email: order.customer_email.clone(),
+            grand_total_paise: order.grand_total_paise,
+            payment_due_at: order.payment_due_at,
+        })
+        .await?;
+
+        tx.commit().await?;
+
+        Ok((order, order_lines))
+    }
+
+    // Prices a prospective order without reserving stock or redeeming the coupon
+    pub async fn quote(
+        pool: &PgPool,
+        admin_id: i32,
+        customer_email: &str,
+        lines: &[NewOrderLine],
+        coupon_code: Option<&str>,
+    ) -> Result<Vec<LinePricing>, OrderError> {
+        let quantities = merge_lines(lines)?;
+        let mut conn = pool.acquire().await?;
+
+        let mut priced: Vec<(Product, i32)> = Vec::with_capacity(quantities.len());
+        for (&product_id, &quantity) in &quantities {
+            let product = sqlx::query_as::<_, Product>("SELECT * FROM products WHERE id = $1 AND admin_id = $2")
+                .bind(product_id)
+                .bind(admin_id)
+                .fetch_optional(&mut *conn)
+                .await?
+                .ok_or(OrderError::ProductNotFound(product_id))?;
+            priced.push((product, quantity));
+        }
+
+        let now = Utc::now().naive_utc();
+        let coupon = match coupon_code {
+            Some(code) => Some(
+                Coupon::fetch_by_code(&mut conn, admin_id, code)
+                    .await?
+                    .filter(|coupon| coupon.is_usable(now))
+                    .ok_or_else(|| OrderError::InvalidCoupon(code.to_string()))?,
+            ),
+            None => None,
+        };
+
+        price_order(&mut conn, admin_id, customer_email, &priced, coupon.as_ref()).await
+    }
+
+    pub async fn fetch_order(pool: &PgPool, order_id: i32) -> Result<Option<(Order, Vec<OrderLine>)>, sqlx::Error> {
+        let order = sqlx::query_as::<_, Order>("SELECT * FROM orders WHERE id = $1")
+            .bind(order_id)
+            .fetch_optional(pool)
+            .await?;
+
+        let Some(order) = order else {
+            return Ok(None);
+        };
+
+        let lines = sqlx::query_as::<_, OrderLine>("SELECT * FROM order_lines WHERE order_id = $1 ORDER BY id ASC")
+            .bind(order_id)
+            .fetch_all(pool)
+            .await?;
+
+        Ok(Some((order, lines)))
+    }
+
+    pub async fn cancel_order(pool: &PgPool, order_id: i32, admin_id: i32) -> Result<Order, OrderError> {
+        let mut tx = pool.begin().await?;
+
+        let order = lock_order(&mut tx, order_id, admin_id).await?;
+
+        if !is_unpaid(&order) {
+            return Err(OrderError::InvalidStatus(order.status));
+        }
+
+        // Money already taken stays on the order; reconciliation lists it as due for refund
+        release_holds(&mut tx, &order).await?;
+        let order = set_status(&mut tx, order_id, STATUS_CANCELLED).await?;
+
+        tx.commit().await?;
+
+        Ok(order)
+    }
+
+    pub async fn mark_delivered(pool: &PgPool, order_id: i32, admin_id: i32) -> Result<Order, OrderError> {
+        let mut tx = pool.begin().await?;
+
+        let order = lock_order(&mut tx, order_id, admin_id).await?;
+
+        if order.status != STATUS_PAID {
+            return Err(OrderError::InvalidStatus(order.status));
+        }
+
+        let order = set_status(&mut tx, order_id, STATUS_DELIVERED).await?;
+        tx.commit().await?;
+
+        Ok(order)
+    }
+
+    // Releases stock held by orders not fully paid when their payment window passed,
+    // including part-paid ones. SKIP LOCKED lets several instances sweep at once without
+    // blocking each other.
+    pub async fn expire_overdue(pool: &PgPool, batch_size: i64) -> Result<Vec<Order>, sqlx::Error> {
+        let mut tx = pool.begin().await?;
+
+        let overdue = sqlx::query_as::<_, Order>(
+            "SELECT * FROM orders
+             WHERE status IN ($1, $2) AND payment_due_at < $3
+             ORDER BY id ASC
+             LIMIT $4
+             FOR UPDATE SKIP LOCKED"
+        )
+        .bind(STATUS_PENDING_PAYMENT)
+        .bind(STATUS_PARTIALLY_PAID)
+        .bind(Utc::now().naive_utc())
+        .bind(batch_size)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        let mut expired = Vec::with_capacity(overdue.len());
+        for order in overdue {
+            release_holds(&mut tx, &order).await?;
+            expired.push(set_status(&mut tx, order.id, STATUS_EXPIRED).await?);
+        }
+
+        tx.commit().await?;
+
+        Ok(expired)
+    }
+}
+
+// The seller's state comes from the pincode the admin registered with
+async fn seller_state(conn: &mut PgConnection, admin_id: i32) -> Result<GstState, OrderError> {
+    let admin: Option<(String,)> = sqlx::query_as("SELECT pincode FROM admins WHERE id = $1")
+        .bind(admin_id)
+        .fetch_optional(conn)
+        .await?;
+    let (pincode,) = admin.ok_or(OrderError::AdminNotFound(admin_id))?;
+    gst::state_for_pincode(&pincode).ok_or(OrderError::UnknownPincode(pincode))
+}
+
+// Merges duplicate products; BTreeMap keeps them sorted for lock ordering
+fn merge_lines(lines: &[NewOrderLine]) -> Result<BTreeMap<i32, i32>, OrderError> {
+    if lines.is_empty() {
+        return Err(OrderError::EmptyOrder);
+    }
+
+    let mut quantities: BTreeMap<i32, i32> = BTreeMap::new();
+    for line in lines {
+        if line.quantity <= 0 {
+            return Err(OrderError::InvalidQuantity(line.product_id));
+        }
+        let quantity = quantities.entry(line.product_id).or_insert(0);
+        *quantity = quantity
+            .checked_add(line.quantity)
+            .ok_or(OrderError::InvalidQuantity(line.product_id))?;
+    }
+    Ok(quantities)
+}
+
+// Loads the admin's price lists and discounts valid right now and prices each line
+async fn price_order(
+    conn: &mut PgConnection,
+    admin_id: i32,
+    customer_email: &str,
+    priced: &[(Product, i32)],
+    coupon: Option<&Coupon>,
+) -> Result<Vec<LinePricing>, OrderError> {
+    let now = Utc::now().naive_utc();
+    let product_ids: Vec<i32> = priced.iter().map(|(product, _)| product.id).collect();
+    let prices = ApplicablePrice::fetch(&mut *conn, admin_id, now, customer_email, &product_ids).await?;
+    let discounts = Discount::fetch_active(&mut *conn, admin_id, now).await?;
+
+    Ok(pricing::price_lines(priced, &prices, &discounts, coupon)?)
+}
+
+// Locks an order for a state change by the admin who owns it
+async fn lock_order(conn: &mut PgConnection, order_id: i32, admin_id: i32) -> Result<Order, OrderError> {
+    let order = sqlx::query_as::<_, Order>("SELECT * FROM orders WHERE id = $1 FOR UPDATE")
+        .bind(order_id)
+        .fetch_optional(conn)
+        .await?
+        .ok_or(OrderError::NotFound(order_id))?;
+
+    if order.admin_id != admin_id {
+        return Err(OrderError::NotOwner(admin_id));
+    }
+
+    Ok(order)
+}
+
+// Not (fully) paid yet, so it can still be cancelled or expire
+fn is_unpaid(order: &Order) -> bool {
+    order.status == STATUS_PENDING_PAYMENT || order.status == STATUS_PARTIALLY_PAID
+}
+
+// Everything an unpaid order holds on to: reserved stock and a coupon use
+async fn release_holds(tx: &mut Transaction<'_, Postgres>, order: &Order) -> Result<(), sqlx::Error> {
+    release_reservations(tx, order.id).await?;
+    if let Some(coupon_id) = order.coupon_id {
+        Coupon::release(tx, coupon_id).await?;
+    }
+    Ok(())
+}
+
+pub async fn set_status(
+    tx: &mut Transaction<'_, Postgres>,
+    order_id: i32,
+    status: &str,
+) -> Result<Order, sqlx::Error> {
+    let order = sqlx::query_as::<_, Order>("UPDATE orders SET status = $1, updated_at = NOW() WHERE id = $2 RETURNING *")
+        .bind(status)
+        .bind(order_id)
+        .fetch_one(&mut **tx)
+        .await?;
+
+    OutboxEvent::record(tx, &OrderStatusChanged {
+        order_id: order.id,
+        admin_id: order.admin_id,
+        customer_email: order.customer_email.clone(),
+        status: order.status.clone(),
+    })
+    .await?;
+
+    Ok(order)
+}
+
+// Once an order is paid its reserved stock leaves the warehouse for good
+pub async fn consume_reservations(tx: &mut Transaction<'_, Postgres>, order_id: i32) -> Result<(), sqlx::Error> {
+    let reservations = sqlx::query_as::<_, StockReservation>(
+        "SELECT * FROM stock_reservations WHERE order_id = $1 AND status = $2 ORDER BY product_id ASC"
+    )
+    .bind(order_id)
+    .bind(RESERVATION_ACTIVE)
+    .fetch_all(&mut **tx)
+    .await?;
+
+    let reference = format!("order:{}", order_id);
+    for reservation in &reservations {
+        match reservation.warehouse_id {
+            Some(warehouse_id) => {
+                StockMovement::post(
+                    tx,
+                    &Posting {
+                        product_id: reservation.product_id,
+                        warehouse_id,
+                        movement_type: MovementType::Issue,
+                        on_hand_delta: -reservation.quantity,
+                        reserved_delta: -reservation.quantity,
+                        reference: Some(&reference),
+                        note: Some("order paid"),
+                    },
+                )
+                .await?;
+            }
+            None => {
+                sqlx::query(
+                    "UPDATE products
+                     SET stock_on_hand = stock_on_hand - $1, stock_reserved = stock_reserved - $1
+                     WHERE id = $2"
+                )
+                .bind(reservation.quantity)
+                .bind(reservation.product_id)
+                .execute(&mut **tx)
+                .await?;
+            }
+        }
+    }
+
+    sqlx::query("UPDATE stock_reservations SET status = $1 WHERE order_id = $2 AND status = $3")
+        .bind(RESERVATION_CONSUMED)
+        .bind(order_id)
+        .bind(RESERVATION_ACTIVE)
+        .execute(&mut **tx)
+        .await?;
+
+    Ok(())
+}
+
+// Gives reserved stock back to the products and marks the reservations released
+async fn release_reservations(tx: &mut Transaction<'_, Postgres>, order_id: i32) -> Result<(), sqlx::Error> {
+    let reservations = sqlx::query_as::<_, StockReservation>(
+        "SELECT * FROM stock_reservations WHERE order_id = $1 AND status = $2 ORDER BY product_id ASC"
+    )
+    .bind(order_id)
+    .bind(RESERVATION_ACTIVE)
+    .fetch_all(&mut **tx)
+    .await?;
+
+    // Updated in product id order, same lock ordering as place_order
+    let reference = format!("order:{}", order_id);
+    for reservation in &reservations {
+        match reservation.warehouse_id {
+            Some(warehouse_id) => {
+                StockMovement::post(
+                    tx,
+                    &Posting {
+                        product_id: reservation.product_id,
+                        warehouse_id,
+                        movement_type: MovementType::Reservation,
+                        on_hand_delta: 0,
+                        reserved_delta: -reservation.quantity,
+                        reference: Some(&reference),
+                        note: Some("reservation released"),
+                    },
+                )
+                .await?;
+            }
+            // Reservations made before warehouses existed only touched the product totals
+            None => {
+                sqlx::query("UPDATE products SET stock_reserved = stock_reserved - $1 WHERE id = $2")
+                    .bind(reservation.quantity)
+                    .bind(reservation.product_id)
+                    .execute(&mut **tx)
+                    .await?;
+            }
+        }
+    }
+
+    sqlx::query(
+        "UPDATE stock_reservations SET status = $1, released_at = NOW() WHERE order_id = $2 AND status = $3"
+    )
+    .bind(RESERVATION_RELEASED)
+    .bind(order_id)
+    .bind(RESERVATION_ACTIVE)
+    .execute(&mut **tx)
+    .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{merge_lines, NewOrderLine, OrderError};
+
+    fn line(product_id: i32, quantity: i32) -> NewOrderLine {
+        NewOrderLine { product_id, quantity }
+    }
+
+    #[test]
+    fn duplicate_lines_are_merged() {
+        let merged = merge_lines(&[line(2, 1), line(1, 3), line(2, 4)]).unwrap();
+        assert_eq!(merged.into_iter().collect::<Vec<_>>(), vec![(1, 3), (2, 5)]);
+    }
+
+    #[test]
+    fn merged_quantity_overflow_is_rejected() {
+        let result = merge_lines(&[line(1, i32::MAX), line(1, 2)]);
+        assert!(matches!(result, Err(OrderError::InvalidQuantity(1))));
+    }
+}
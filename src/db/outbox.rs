@@ -0,0 +1,77 @@
+use std::collections::HashMap;
+
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use sqlx::types::Json;
+use sqlx::{FromRow, PgConnection};
+
+use crate::events::DomainEvent;
+use crate::telemetry;
+
+#[derive(FromRow, Serialize, Deserialize, Debug, Clone)]
+pub struct OutboxEvent {
+    pub id: i64,
+    pub event_type: String,
+    pub event_version: i32,
+    pub aggregate_type: String,
+    pub aggregate_id: i64,
+    pub payload: Json<serde_json::Value>,
+    pub created_at: NaiveDateTime,
+    pub published_at: Option<NaiveDateTime>,
+    pub attempts: i32,
+    pub last_error: Option<String>,
+    pub trace_context: Json<HashMap<String, String>>,
+}
+
+impl OutboxEvent {
+    // Must run on the same connection/transaction as the change the event describes
+    pub async fn record<E: DomainEvent>(conn: &mut PgConnection, event: &E) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            "INSERT INTO outbox_events (event_type, event_version, aggregate_type, aggregate_id, payload, trace_context)
+             VALUES ($1, $2, $3, $4, $5, $6)"
+        )
+        .bind(E::TYPE)
+        .bind(E::VERSION)
+        .bind(E::AGGREGATE)
+        .bind(event.aggregate_id())
+        .bind(Json(event))
+        .bind(Json(telemetry::current_context()))
+        .execute(conn)
+        .await?;
+
+        Ok(())
+    }
+
+    // Oldest unpublished events; SKIP LOCKED lets several relays share the outbox
+    pub async fn lock_unpublished(conn: &mut PgConnection, batch_size: i64) -> Result<Vec<OutboxEvent>, sqlx::Error> {
+        sqlx::query_as::<_, OutboxEvent>(
+            "SELECT * FROM outbox_events
+             WHERE published_at IS NULL
+             ORDER BY id ASC
+             LIMIT $1
+             FOR UPDATE SKIP LOCKED"
+        )
+        .bind(batch_size)
+        .fetch_all(conn)
+        .await
+    }
+
+    pub async fn mark_published(conn: &mut PgConnection, ids: &[i64]) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE outbox_events SET published_at = NOW(), attempts = attempts + 1 WHERE id = ANY($1)")
+            .bind(ids)
+            .execute(conn)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn mark_failed(conn: &mut PgConnection, ids: &[i64], error: &str) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE outbox_events SET attempts = attempts + 1, last_error = $2 WHERE id = ANY($1)")
+            .bind(ids)
+            .bind(error)
+            .execute(conn)
+            .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,258 @@
+use std::fmt;
+
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgPool};
+
+use crate::db::orders::{
+    consume_reservations, set_status, Order, STATUS_CANCELLED, STATUS_DELIVERED, STATUS_EXPIRED, STATUS_PAID,
+    STATUS_PARTIALLY_PAID, STATUS_PENDING_PAYMENT, STATUS_REFUNDED,
+};
+
+pub const KIND_PAYMENT: &str = "payment";
+pub const KIND_REFUND: &str = "refund";
+
+pub const PROVIDER_MANUAL: &str = "manual";
+
+pub const METHODS: [&str; 4] = ["cash", "upi", "card", "bank_transfer"];
+
+#[derive(FromRow, Serialize, Deserialize, Debug, Clone)]
+pub struct Payment {
+    pub id: i32,
+    pub order_id: i32,
+    pub kind: String,
+    pub method: String,
+    pub amount_paise: i64,
+    pub provider: String,
+    pub provider_reference: Option<String>,
+    pub note: Option<String>,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct NewPayment {
+    pub kind: String,
+    pub method: String,
+    pub amount_paise: i64,
+    pub provider: String,
+    pub provider_reference: Option<String>,
+    pub note: Option<String>,
+}
+
+// Per-order totals used to spot orders whose status and money disagree
+#[derive(FromRow, Serialize, Deserialize, Debug, Clone)]
+pub struct Reconciliation {
+    pub order_id: i32,
+    pub status: String,
+    pub grand_total_paise: i64,
+    pub paid_paise: i64,
+    pub refunded_paise: i64,
+    pub ledger_paid_paise: i64,
+    pub ledger_refunded_paise: i64,
+    pub balance_paise: i64,
+}
+
+#[derive(Debug)]
+pub enum PaymentError {
+    Db(sqlx::Error),
+    OrderNotFound(i32),
+    NotOwner(i32),
+    InvalidKind(String),
+    InvalidMethod(String),
+    InvalidAmount,
+    OrderClosed(String),
+    RefundExceedsPaid { requested: i64, refundable: i64 },
+    ReferenceInUse(String),
+}
+
+impl From<sqlx::Error> for PaymentError {
+    fn from(e: sqlx::Error) -> Self {
+        PaymentError::Db(e)
+    }
+}
+
+impl fmt::Display for PaymentError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PaymentError::Db(_) => write!(f, "DB Error"),
+            PaymentError::OrderNotFound(order_id) => write!(f, "Order {} not found", order_id),
+            PaymentError::NotOwner(admin_id) => write!(f, "Admin {} does not own this order", admin_id),
+            PaymentError::InvalidKind(kind) => write!(f, "Unknown payment kind '{}'", kind),
+            PaymentError::InvalidMethod(method) => write!(f, "Unknown payment method '{}'", method),
+            PaymentError::InvalidAmount => write!(f, "Amount must be greater than zero"),
+            PaymentError::OrderClosed(status) => write!(f, "Order does not accept payments in status '{}'", status),
+            PaymentError::RefundExceedsPaid { requested, refundable } => write!(
+                f,
+                "Refund of {} paise exceeds the {} paise still refundable",
+                requested, refundable
+            ),
+            PaymentError::ReferenceInUse(reference) => {
+                write!(f, "Provider reference '{}' is already recorded on another order", reference)
+            }
+        }
+    }
+}
+
+impl Payment {
+    // Records a payment or refund and moves the order along:
+    // pending -> partially_paid -> paid (consuming the stock reservation), paid -> refunded.
+    // Refunds are still accepted once a part-paid order was cancelled or expired.
+    // A provider event seen before on this order is returned as-is so webhook retries are harmless.
+    // Manual entries name the admin recording them, who has to own the order; provider events
+    // carry no admin and are trusted on their signature alone.
+    pub async fn record(
+        pool: &PgPool,
+        order_id: i32,
+        admin_id: Option<i32>,
+        payment: &NewPayment,
+    ) -> Result<(Payment, Order), PaymentError> {
+        if payment.kind != KIND_PAYMENT && payment.kind != KIND_REFUND {
+            return Err(PaymentError::InvalidKind(payment.kind.clone()));
+        }
+        if !METHODS.contains(&payment.method.as_str()) {
+            return Err(PaymentError::InvalidMethod(payment.method.clone()));
+        }
+        if payment.amount_paise <= 0 {
+            return Err(PaymentError::InvalidAmount);
+        }
+
+        let mut tx = pool.begin().await?;
+
+        let order = sqlx::query_as::<_, Order>("SELECT * FROM orders WHERE id = $1 FOR UPDATE")
+            .bind(order_id)
+            .fetch_optional(&mut *tx)
+            .await?
+            .ok_or(PaymentError::OrderNotFound(order_id))?;
+        if let Some(admin_id) = admin_id.filter(|&admin_id| admin_id != order.admin_id) {
+            return Err(PaymentError::NotOwner(admin_id));
+        }
+
+        if let Some(reference) = &payment.provider_reference {
+            let existing = sqlx::query_as::<_, Payment>(
+                "SELECT * FROM payments WHERE provider = $1 AND provider_reference = $2 AND order_id = $3"
+            )
+            .bind(&payment.provider)
+            .bind(reference)
+            .bind(order_id)
+            .fetch_optional(&mut *tx)
+            .await?;
+            if let Some(existing) = existing {
+                return Ok((existing, order));
+            }
+
+            let elsewhere: Option<(i32,)> =
+                sqlx::query_as("SELECT id FROM payments WHERE provider = $1 AND provider_reference = $2")
+                    .bind(&payment.provider)
+                    .bind(reference)
+                    .fetch_optional(&mut *tx)
+                    .await?;
+            if elsewhere.is_some() {
+                return Err(PaymentError::ReferenceInUse(reference.clone()));
+            }
+        }
+
+        let (paid, refunded) = if payment.kind == KIND_PAYMENT {
+            if order.status == STATUS_CANCELLED || order.status == STATUS_EXPIRED {
+                return Err(PaymentError::OrderClosed(order.status));
+            }
+            (order.paid_paise + payment.amount_paise, order.refunded_paise)
+        } else {
+            let refundable = order.paid_paise - order.refunded_paise;
+            if payment.amount_paise > refundable {
+                return Err(PaymentError::RefundExceedsPaid {
+                    requested: payment.amount_paise,
+                    refundable,
+                });
+            }
+            (order.paid_paise, order.refunded_paise + payment.amount_paise)
+        };
+
+        let recorded = sqlx::query_as::<_, Payment>(
+            "INSERT INTO payments (order_id, kind, method, amount_paise, provider, provider_reference, note)
+             VALUES ($1, $2, $3, $4, $5, $6, $7)
+             RETURNING *"
+        )
+        .bind(order_id)
+        .bind(&payment.kind)
+        .bind(&payment.method)
+        .bind(payment.amount_paise)
+        .bind(&payment.provider)
+        .bind(&payment.provider_reference)
+        .bind(&payment.note)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        sqlx::query("UPDATE orders SET paid_paise = $1, refunded_paise = $2, updated_at = NOW() WHERE id = $3")
+            .bind(paid)
+            .bind(refunded)
+            .bind(order_id)
+            .execute(&mut *tx)
+            .await?;
+
+        let open = order.status == STATUS_PENDING_PAYMENT || order.status == STATUS_PARTIALLY_PAID;
+        let net = paid - refunded;
+        let next_status = if open && net >= order.grand_total_paise {
+            consume_reservations(&mut tx, order_id).await?;
+            Some(STATUS_PAID)
+        } else if open && net > 0 {
+            Some(STATUS_PARTIALLY_PAID)
+        } else if (order.status == STATUS_PAID || order.status == STATUS_DELIVERED) && net == 0 {
+            Some(STATUS_REFUNDED)
+        } else {
+            None
+        };
+
+        let order = match next_status {
+            Some(status) if status != order.status => set_status(&mut tx, order_id, status).await?,
+            _ => {
+                sqlx::query_as::<_, Order>("SELECT * FROM orders WHERE id = $1")
+                    .bind(order_id)
+                    .fetch_one(&mut *tx)
+                    .await?
+            }
+        };
+
+        tx.commit().await?;
+
+        Ok((recorded, order))
+    }
+
+    pub async fn fetch_for_order(pool: &PgPool, admin_id: i32, order_id: i32) -> Result<Vec<Payment>, sqlx::Error> {
+        sqlx::query_as::<_, Payment>(
+            "SELECT p.* FROM payments p
+             JOIN orders o ON o.id = p.order_id
+             WHERE p.order_id = $1 AND o.admin_id = $2
+             ORDER BY p.id ASC"
+        )
+        .bind(order_id)
+        .bind(admin_id)
+        .fetch_all(pool)
+        .await
+    }
+}
+
+impl Reconciliation {
+    // Orders where the running totals on the order disagree with the payments ledger,
+    // or where the money received doesn't match the status (paid but short, or overpaid)
+    pub async fn fetch_mismatches(pool: &PgPool, admin_id: i32) -> Result<Vec<Reconciliation>, sqlx::Error> {
+        sqlx::query_as::<_, Reconciliation>(
+            "SELECT o.id AS order_id, o.status, o.grand_total_paise, o.paid_paise, o.refunded_paise,
+                    COALESCE(SUM(p.amount_paise) FILTER (WHERE p.kind = 'payment'), 0)::BIGINT AS ledger_paid_paise,
+                    COALESCE(SUM(p.amount_paise) FILTER (WHERE p.kind = 'refund'), 0)::BIGINT AS ledger_refunded_paise,
+                    (o.grand_total_paise - o.paid_paise + o.refunded_paise) AS balance_paise
+             FROM orders o
+             LEFT JOIN payments p ON p.order_id = o.id
+             WHERE o.admin_id = $1
+             GROUP BY o.id
+             HAVING o.paid_paise <> COALESCE(SUM(p.amount_paise) FILTER (WHERE p.kind = 'payment'), 0)
+                 OR o.refunded_paise <> COALESCE(SUM(p.amount_paise) FILTER (WHERE p.kind = 'refund'), 0)
+                 OR (o.status IN ('paid', 'delivered') AND o.paid_paise - o.refunded_paise < o.grand_total_paise)
+                 OR (o.status IN ('cancelled', 'expired') AND o.paid_paise - o.refunded_paise > 0)
+                 OR o.paid_paise - o.refunded_paise > o.grand_total_paise
+             ORDER BY o.id ASC"
+        )
+        .bind(admin_id)
+        .fetch_all(pool)
+        .await
+    }
+}
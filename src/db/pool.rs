@@ -0,0 +1,123 @@
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use sqlx::migrate::MigrateError;
+use sqlx::postgres::{PgConnectOptions, PgPoolOptions};
+use sqlx::{ConnectOptions, Connection, PgPool};
+use tracing::{error, info, warn};
+
+use crate::config::Config;
+use crate::shutdown::Shutdown;
+
+// Whether the database is reachable and migrated. Set right away on a normal start;
+// in degraded mode only once `recover` gets through.
+#[derive(Clone, Default)]
+pub struct DbReady(Arc<AtomicBool>);
+
+impl DbReady {
+    pub fn is_ready(&self) -> bool {
+        self.0.load(Ordering::Acquire)
+    }
+
+    pub fn mark_ready(&self) {
+        self.0.store(true, Ordering::Release);
+    }
+}
+
+pub fn connect_options(config: &Config) -> Result<PgConnectOptions, sqlx::Error> {
+    let mut options = PgConnectOptions::from_str(config.database_url.expose())?;
+    if let Some(password) = &config.database_password {
+        options = options.password(password.expose());
+    }
+    Ok(options)
+}
+
+fn pool_options(config: &Config) -> PgPoolOptions {
+    let idle_timeout = match config.db_idle_timeout_secs {
+        0 => None,
+        secs => Some(Duration::from_secs(secs)),
+    };
+    PgPoolOptions::new()
+        .max_connections(config.db_max_connections)
+        .min_connections(config.db_min_connections)
+        .acquire_timeout(Duration::from_secs(config.db_acquire_timeout_secs))
+        .idle_timeout(idle_timeout)
+}
+
+// Delay before the next attempt after `failures` failed ones: doubles from the base up to the cap
+fn backoff(config: &Config, failures: u32) -> Duration {
+    let delay = config
+        .db_connect_backoff_ms
+        .saturating_mul(2u64.saturating_pow(failures.saturating_sub(1)));
+    Duration::from_millis(delay.min(config.db_connect_backoff_max_ms))
+}
+
+pub async fn migrate(pool: &PgPool) -> Result<(), MigrateError> {
+    sqlx::migrate!("./migrations").run(pool).await
+}
+
+// Tries once plus `db_connect_retries` more times. None once retries run out or shutdown is requested.
+pub async fn connect(config: &Config, options: PgConnectOptions, shutdown: &Shutdown) -> Option<PgPool> {
+    let mut failures = 0;
+    loop {
+        let attempt = tokio::select! {
+            attempt = try_connect(config, &options) => attempt,
+            _ = shutdown.requested() => return None,
+        };
+        match attempt {
+            Ok(pool) => return Some(pool),
+            Err(e) if failures >= config.db_connect_retries => {
+                error!(attempts = failures + 1, "❌ Failed to connect to DB: {}", e);
+                return None;
+            }
+            Err(e) => {
+                failures += 1;
+                let delay = backoff(config, failures);
+                warn!(attempt = failures, retry_in_ms = delay.as_millis() as u64, "Failed to connect to DB: {}", e);
+                tokio::select! {
+                    _ = tokio::time::sleep(delay) => {}
+                    _ = shutdown.requested() => return None,
+                }
+            }
+        }
+    }
+}
+
+// The pool's own connect keeps retrying internally until the acquire timeout, so each
+// attempt first opens one connection directly and gives up on the first error
+async fn try_connect(config: &Config, options: &PgConnectOptions) -> Result<PgPool, sqlx::Error> {
+    let timeout = Duration::from_secs(config.db_acquire_timeout_secs);
+    let connection = tokio::time::timeout(timeout, options.connect())
+        .await
+        .map_err(|_| sqlx::Error::PoolTimedOut)??;
+    let _ = connection.close().await;
+    pool_options(config).connect_with(options.clone()).await
+}
+
+// Connects on first use, so the server can start while the database is down
+pub fn connect_degraded(config: &Config, options: PgConnectOptions) -> PgPool {
+    pool_options(config).connect_lazy_with(options)
+}
+
+// Degraded mode: keeps trying to reach the database (backing off up to the cap) and run
+// migrations, then marks it ready
+pub async fn recover(pool: PgPool, ready: DbReady, config: Arc<Config>, shutdown: Shutdown) {
+    let mut failures = 0u32;
+    loop {
+        failures = failures.saturating_add(1);
+        tokio::select! {
+            _ = tokio::time::sleep(backoff(&config, failures)) => {}
+            _ = shutdown.requested() => return,
+        }
+        match migrate(&pool).await {
+            Ok(()) => {
+                ready.mark_ready();
+                info!("✅ Database reachable, migrations applied, leaving degraded mode");
+                return;
+            }
+            Err(e) => warn!(attempt = failures, "Database still unavailable: {}", e),
+        }
+    }
+}
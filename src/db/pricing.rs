@@ -0,0 +1,275 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use sqlx::{FromRow, PgConnection, PgPool};
+
+#[derive(FromRow, Serialize, Deserialize, Debug, Clone)]
+pub struct PriceList {
+    pub id: i32,
+    pub admin_id: i32,
+    pub name: String,
+    pub customer_email: Option<String>,
+    pub valid_from: Option<NaiveDateTime>,
+    pub valid_to: Option<NaiveDateTime>,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(FromRow, Serialize, Deserialize, Debug, Clone)]
+pub struct PriceListItem {
+    pub id: i32,
+    pub price_list_id: i32,
+    pub product_id: i32,
+    pub min_quantity: i32,
+    pub unit_price_paise: i64,
+}
+
+// A price list item together with whether its list targets one customer
+#[derive(FromRow, Debug, Clone)]
+pub struct ApplicablePrice {
+    pub price_list_id: i32,
+    pub product_id: i32,
+    pub min_quantity: i32,
+    pub unit_price_paise: i64,
+    pub customer_specific: bool,
+}
+
+#[derive(FromRow, Serialize, Deserialize, Debug, Clone)]
+pub struct Discount {
+    pub id: i32,
+    pub admin_id: i32,
+    pub name: String,
+    pub product_id: Option<i32>,
+    pub kind: String,
+    pub value: i64,
+    pub min_quantity: i32,
+    pub valid_from: Option<NaiveDateTime>,
+    pub valid_to: Option<NaiveDateTime>,
+    pub active: bool,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(FromRow, Serialize, Deserialize, Debug, Clone)]
+pub struct Coupon {
+    pub id: i32,
+    pub admin_id: i32,
+    pub code: String,
+    pub kind: String,
+    pub value: i64,
+    pub max_uses: Option<i32>,
+    pub used_count: i32,
+    pub valid_from: Option<NaiveDateTime>,
+    pub valid_to: Option<NaiveDateTime>,
+    pub active: bool,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Deserialize, Debug, Clone, ToSchema)]
+pub struct NewDiscount {
+    pub name: String,
+    pub product_id: Option<i32>,
+    pub kind: String,
+    pub value: i64,
+    pub min_quantity: Option<i32>,
+    pub valid_from: Option<NaiveDateTime>,
+    pub valid_to: Option<NaiveDateTime>,
+}
+
+#[derive(Deserialize, Debug, Clone, ToSchema)]
+pub struct NewCoupon {
+    pub code: String,
+    pub kind: String,
+    pub value: i64,
+    pub max_uses: Option<i32>,
+    pub valid_from: Option<NaiveDateTime>,
+    pub valid_to: Option<NaiveDateTime>,
+}
+
+impl PriceList {
+    pub async fn create_price_list(
+        pool: &PgPool,
+        admin_id: i32,
+        name: &str,
+        customer_email: Option<&str>,
+        valid_from: Option<NaiveDateTime>,
+        valid_to: Option<NaiveDateTime>,
+    ) -> Result<PriceList, sqlx::Error> {
+        sqlx::query_as::<_, PriceList>(
+            "INSERT INTO price_lists (admin_id, name, customer_email, valid_from, valid_to)
+             VALUES ($1, $2, $3, $4, $5)
+             RETURNING *"
+        )
+        .bind(admin_id)
+        .bind(name)
+        .bind(customer_email)
+        .bind(valid_from)
+        .bind(valid_to)
+        .fetch_one(pool)
+        .await
+    }
+
+    // Whether the admin owns the list and the product; items may only price the list owner's products
+    pub async fn ownership(
+        pool: &PgPool,
+        admin_id: i32,
+        price_list_id: i32,
+        product_id: i32,
+    ) -> Result<(bool, bool), sqlx::Error> {
+        sqlx::query_as(
+            "SELECT EXISTS (SELECT 1 FROM price_lists WHERE id = $1 AND admin_id = $3),
+                    EXISTS (SELECT 1 FROM products WHERE id = $2 AND admin_id = $3)"
+        )
+        .bind(price_list_id)
+        .bind(product_id)
+        .bind(admin_id)
+        .fetch_one(pool)
+        .await
+    }
+
+    pub async fn upsert_item(
+        pool: &PgPool,
+        price_list_id: i32,
+        product_id: i32,
+        min_quantity: i32,
+        unit_price_paise: i64,
+    ) -> Result<PriceListItem, sqlx::Error> {
+        sqlx::query_as::<_, PriceListItem>(
+            "INSERT INTO price_list_items (price_list_id, product_id, min_quantity, unit_price_paise)
+             VALUES ($1, $2, $3, $4)
+             ON CONFLICT (price_list_id, product_id, min_quantity)
+             DO UPDATE SET unit_price_paise = EXCLUDED.unit_price_paise
+             RETURNING *"
+        )
+        .bind(price_list_id)
+        .bind(product_id)
+        .bind(min_quantity)
+        .bind(unit_price_paise)
+        .fetch_one(pool)
+        .await
+    }
+}
+
+impl ApplicablePrice {
+    // Items from the admin's general lists and from lists made for this customer
+    pub async fn fetch(
+        conn: &mut PgConnection,
+        admin_id: i32,
+        now: NaiveDateTime,
+        customer_email: &str,
+        product_ids: &[i32],
+    ) -> Result<Vec<ApplicablePrice>, sqlx::Error> {
+        sqlx::query_as::<_, ApplicablePrice>(
+            "SELECT i.price_list_id, i.product_id, i.min_quantity, i.unit_price_paise,
+                    (l.customer_email IS NOT NULL) AS customer_specific
+             FROM price_list_items i
+             JOIN price_lists l ON l.id = i.price_list_id
+             WHERE l.admin_id = $1
+               AND (l.valid_from IS NULL OR l.valid_from <= $2)
+               AND (l.valid_to IS NULL OR l.valid_to > $2)
+               AND (l.customer_email IS NULL OR l.customer_email = $3)
+               AND i.product_id = ANY($4)"
+        )
+        .bind(admin_id)
+        .bind(now)
+        .bind(customer_email)
+        .bind(product_ids)
+        .fetch_all(conn)
+        .await
+    }
+}
+
+impl Discount {
+    pub async fn create_discount(pool: &PgPool, admin_id: i32, discount: &NewDiscount) -> Result<Discount, sqlx::Error> {
+        sqlx::query_as::<_, Discount>(
+            "INSERT INTO discounts (admin_id, name, product_id, kind, value, min_quantity, valid_from, valid_to)
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+             RETURNING *"
+        )
+        .bind(admin_id)
+        .bind(&discount.name)
+        .bind(discount.product_id)
+        .bind(&discount.kind)
+        .bind(discount.value)
+        .bind(discount.min_quantity.unwrap_or(1))
+        .bind(discount.valid_from)
+        .bind(discount.valid_to)
+        .fetch_one(pool)
+        .await
+    }
+
+    pub async fn fetch_active(conn: &mut PgConnection, admin_id: i32, now: NaiveDateTime) -> Result<Vec<Discount>, sqlx::Error> {
+        sqlx::query_as::<_, Discount>(
+            "SELECT * FROM discounts
+             WHERE admin_id = $1 AND active
+               AND (valid_from IS NULL OR valid_from <= $2)
+               AND (valid_to IS NULL OR valid_to > $2)"
+        )
+        .bind(admin_id)
+        .bind(now)
+        .fetch_all(conn)
+        .await
+    }
+}
+
+impl Coupon {
+    pub async fn create_coupon(pool: &PgPool, admin_id: i32, coupon: &NewCoupon) -> Result<Coupon, sqlx::Error> {
+        sqlx::query_as::<_, Coupon>(
+            "INSERT INTO coupons (admin_id, code, kind, value, max_uses, valid_from, valid_to)
+             VALUES ($1, $2, $3, $4, $5, $6, $7)
+             RETURNING *"
+        )
+        .bind(admin_id)
+        .bind(&coupon.code)
+        .bind(&coupon.kind)
+        .bind(coupon.value)
+        .bind(coupon.max_uses)
+        .bind(coupon.valid_from)
+        .bind(coupon.valid_to)
+        .fetch_one(pool)
+        .await
+    }
+
+    // Locks the coupon row so concurrent orders can't exceed max_uses
+    pub async fn lock_by_code(conn: &mut PgConnection, admin_id: i32, code: &str) -> Result<Option<Coupon>, sqlx::Error> {
+        sqlx::query_as::<_, Coupon>("SELECT * FROM coupons WHERE admin_id = $1 AND code = $2 FOR UPDATE")
+            .bind(admin_id)
+            .bind(code)
+            .fetch_optional(conn)
+            .await
+    }
+
+    pub async fn fetch_by_code(
+        conn: &mut PgConnection,
+        admin_id: i32,
+        code: &str,
+    ) -> Result<Option<Coupon>, sqlx::Error> {
+        sqlx::query_as::<_, Coupon>("SELECT * FROM coupons WHERE admin_id = $1 AND code = $2")
+            .bind(admin_id)
+            .bind(code)
+            .fetch_optional(conn)
+            .await
+    }
+
+    pub async fn redeem(conn: &mut PgConnection, coupon_id: i32) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE coupons SET used_count = used_count + 1 WHERE id = $1")
+            .bind(coupon_id)
+            .execute(conn)
+            .await
+            .map(|_| ())
+    }
+
+    // Gives the use back when an order is cancelled or expires unpaid
+    pub async fn release(conn: &mut PgConnection, coupon_id: i32) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE coupons SET used_count = GREATEST(used_count - 1, 0) WHERE id = $1")
+            .bind(coupon_id)
+            .execute(conn)
+            .await
+            .map(|_| ())
+    }
+
+    pub fn is_usable(&self, now: NaiveDateTime) -> bool {
+        self.active
+            && self.valid_from.is_none_or(|from| from <= now)
+            && self.valid_to.is_none_or(|to| to > now)
+            && self.max_uses.is_none_or(|max| self.used_count < max)
+    }
+}
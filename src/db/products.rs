@@ -0,0 +1,90 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use sqlx::{FromRow, PgPool};
+
+use crate::db::inventory::{lock_product_in_warehouse, InventoryError, MovementType, Posting, StockMovement};
+
+#[derive(FromRow, Serialize, Deserialize, Debug, Clone)]
+pub struct Product {
+    pub id: i32,
+    pub admin_id: i32,
+    pub sku: String,
+    pub name: String,
+    pub unit_price_paise: i64,
+    pub stock_on_hand: i32,
+    pub stock_reserved: i32,
+    pub created_at: NaiveDateTime,
+    pub hsn_code: Option<String>,
+    pub gst_rate_bp: i32,
+}
+
+#[derive(Deserialize, Debug, Clone, ToSchema)]
+pub struct NewProduct {
+    pub sku: String,
+    pub name: String,
+    pub unit_price_paise: i64,
+    pub stock_on_hand: i32,
+    pub warehouse_id: Option<i32>,
+    pub hsn_code: Option<String>,
+    pub gst_rate_bp: i32,
+}
+
+impl Product {
+    // Creates the product and, if given, books its opening stock into a warehouse
+    pub async fn create_product(
+        pool: &PgPool,
+        admin_id: i32,
+        new_product: &NewProduct,
+        opening_stock: Option<(i32, i32)>,
+    ) -> Result<Product, InventoryError> {
+        let mut tx = pool.begin().await?;
+
+        let mut product = sqlx::query_as::<_, Product>(
+            "INSERT INTO products (admin_id, sku, name, unit_price_paise, hsn_code, gst_rate_bp)
+             VALUES ($1, $2, $3, $4, $5, $6)
+             RETURNING *"
+        )
+        .bind(admin_id)
+        .bind(&new_product.sku)
+        .bind(&new_product.name)
+        .bind(new_product.unit_price_paise)
+        .bind(&new_product.hsn_code)
+        .bind(new_product.gst_rate_bp)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        if let Some((warehouse_id, quantity)) = opening_stock {
+            lock_product_in_warehouse(&mut tx, admin_id, product.id, warehouse_id).await?;
+            StockMovement::post(
+                &mut tx,
+                &Posting {
+                    product_id: product.id,
+                    warehouse_id,
+                    movement_type: MovementType::Receipt,
+                    on_hand_delta: quantity,
+                    reserved_delta: 0,
+                    reference: None,
+                    note: Some("opening stock"),
+                },
+            )
+            .await?;
+            product.stock_on_hand += quantity;
+        }
+
+        tx.commit().await?;
+
+        Ok(product)
+    }
+
+    pub async fn fetch_for_admin(pool: &PgPool, admin_id: i32) -> Result<Vec<Product>, sqlx::Error> {
+        let products = sqlx::query_as::<_, Product>(
+            "SELECT * FROM products WHERE admin_id = $1 ORDER BY id ASC"
+        )
+        .bind(admin_id)
+        .fetch_all(pool)
+        .await?;
+
+        Ok(products)
+    }
+}
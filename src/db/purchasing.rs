@@ -0,0 +1,770 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use sqlx::{FromRow, PgConnection, PgPool};
+
+use crate::db::inventory::{MovementType, Posting, StockMovement};
+
+pub const PO_OPEN: &str = "open";
+pub const PO_PARTIALLY_RECEIVED: &str = "partially_received";
+pub const PO_RECEIVED: &str = "received";
+pub const PO_CANCELLED: &str = "cancelled";
+
+pub const MATCH_MATCHED: &str = "matched";
+pub const MATCH_PENDING: &str = "pending";
+pub const MATCH_MISMATCH: &str = "mismatch";
+
+#[derive(FromRow, Serialize, Deserialize, Debug, Clone)]
+pub struct Supplier {
+    pub id: i32,
+    pub admin_id: i32,
+    pub name: String,
+    pub email: Option<String>,
+    pub gstin: Option<String>,
+    pub pincode: Option<String>,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Deserialize, Debug, Clone, ToSchema)]
+pub struct NewSupplier {
+    pub name: String,
+    pub email: Option<String>,
+    pub gstin: Option<String>,
+    pub pincode: Option<String>,
+}
+
+#[derive(FromRow, Serialize, Deserialize, Debug, Clone)]
+pub struct PurchaseOrder {
+    pub id: i32,
+    pub admin_id: i32,
+    pub supplier_id: i32,
+    pub warehouse_id: i32,
+    pub status: String,
+    pub total_paise: i64,
+    pub note: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(FromRow, Serialize, Deserialize, Debug, Clone)]
+pub struct PurchaseOrderLine {
+    pub id: i32,
+    pub purchase_order_id: i32,
+    pub product_id: i32,
+    pub quantity: i32,
+    pub unit_cost_paise: i64,
+    pub received_quantity: i32,
+}
+
+#[derive(Deserialize, Debug, Clone, ToSchema)]
+pub struct NewPurchaseOrderLine {
+    pub product_id: i32,
+    pub quantity: i32,
+    pub unit_cost_paise: i64,
+}
+
+#[derive(Deserialize, Debug, Clone, ToSchema)]
+pub struct NewPurchaseOrder {
+    pub supplier_id: i32,
+    pub warehouse_id: i32,
+    pub note: Option<String>,
+    pub lines: Vec<NewPurchaseOrderLine>,
+}
+
+#[derive(FromRow, Serialize, Deserialize, Debug, Clone)]
+pub struct GoodsReceipt {
+    pub id: i32,
+    pub purchase_order_id: i32,
+    pub warehouse_id: i32,
+    pub note: Option<String>,
+    pub received_at: NaiveDateTime,
+}
+
+#[derive(FromRow, Serialize, Deserialize, Debug, Clone)]
+pub struct GoodsReceiptLine {
+    pub id: i32,
+    pub goods_receipt_id: i32,
+    pub purchase_order_line_id: i32,
+    pub quantity: i32,
+}
+
+#[derive(Deserialize, Debug, Clone, ToSchema)]
+pub struct NewReceiptLine {
+    pub purchase_order_line_id: i32,
+    pub quantity: i32,
+}
+
+#[derive(Deserialize, Debug, Clone, ToSchema)]
+pub struct NewGoodsReceipt {
+    pub note: Option<String>,
+    pub lines: Vec<NewReceiptLine>,
+}
+
+#[derive(FromRow, Serialize, Deserialize, Debug, Clone)]
+pub struct SupplierInvoice {
+    pub id: i32,
+    pub purchase_order_id: i32,
+    pub supplier_id: i32,
+    pub invoice_number: String,
+    pub total_paise: i64,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(FromRow, Serialize, Deserialize, Debug, Clone)]
+pub struct SupplierInvoiceLine {
+    pub id: i32,
+    pub supplier_invoice_id: i32,
+    pub purchase_order_line_id: i32,
+    pub quantity: i32,
+    pub unit_cost_paise: i64,
+}
+
+#[derive(Deserialize, Debug, Clone, ToSchema)]
+pub struct NewSupplierInvoiceLine {
+    pub purchase_order_line_id: i32,
+    pub quantity: i32,
+    pub unit_cost_paise: i64,
+}
+
+#[derive(Deserialize, Debug, Clone, ToSchema)]
+pub struct NewSupplierInvoice {
+    pub invoice_number: String,
+    pub lines: Vec<NewSupplierInvoiceLine>,
+}
+
+// One PO line compared across what was ordered, received and billed
+#[derive(Serialize, Debug, Clone)]
+pub struct MatchLine {
+    pub purchase_order_line_id: i32,
+    pub product_id: i32,
+    pub ordered_quantity: i32,
+    pub received_quantity: i32,
+    pub invoiced_quantity: i64,
+    pub unit_cost_paise: i64,
+    pub invoiced_paise: i64,
+    pub status: &'static str,
+    pub exceptions: Vec<String>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct MatchReport {
+    pub purchase_order_id: i32,
+    pub status: &'static str,
+    pub lines: Vec<MatchLine>,
+}
+
+#[derive(Debug)]
+pub enum PurchasingError {
+    Db(sqlx::Error),
+    EmptyLines,
+    InvalidQuantity(i32),
+    InvalidCost(i32),
+    AmountOverflow(i32),
+    SupplierNotFound(i32),
+    WarehouseNotFound(i32),
+    ProductNotFound(i32),
+    NotFound(i32),
+    LineNotInPurchaseOrder(i32),
+    OverReceipt { purchase_order_line_id: i32, requested: i64, outstanding: i64 },
+    InvalidStatus(String),
+}
+
+impl From<sqlx::Error> for PurchasingError {
+    fn from(e: sqlx::Error) -> Self {
+        PurchasingError::Db(e)
+    }
+}
+
+impl fmt::Display for PurchasingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PurchasingError::Db(_) => write!(f, "DB Error"),
+            PurchasingError::EmptyLines => write!(f, "At least one line is required"),
+            PurchasingError::InvalidQuantity(id) => write!(f, "Quantity for line {} must be greater than zero", id),
+            PurchasingError::InvalidCost(id) => write!(f, "Unit cost for line {} cannot be negative", id),
+            PurchasingError::AmountOverflow(id) => write!(f, "Amount for line {} is too large", id),
+            PurchasingError::SupplierNotFound(id) => write!(f, "Supplier {} not found", id),
+            PurchasingError::WarehouseNotFound(id) => write!(f, "Warehouse {} not found", id),
+            PurchasingError::ProductNotFound(id) => write!(f, "Product {} not found", id),
+            PurchasingError::NotFound(id) => write!(f, "Purchase order {} not found", id),
+            PurchasingError::LineNotInPurchaseOrder(id) => {
+                write!(f, "Line {} does not belong to this purchase order", id)
+            }
+            PurchasingError::OverReceipt { purchase_order_line_id, requested, outstanding } => write!(
+                f,
+                "Cannot receive {} against line {}: only {} outstanding",
+                requested, purchase_order_line_id, outstanding
+            ),
+            PurchasingError::InvalidStatus(status) => {
+                write!(f, "Purchase order cannot be changed in status '{}'", status)
+            }
+        }
+    }
+}
+
+impl Supplier {
+    pub async fn create_supplier(pool: &PgPool, admin_id: i32, supplier: &NewSupplier) -> Result<Supplier, sqlx::Error> {
+        sqlx::query_as::<_, Supplier>(
+            "INSERT INTO suppliers (admin_id, name, email, gstin, pincode)
+             VALUES ($1, $2, $3, $4, $5)
+             RETURNING *"
+        )
+        .bind(admin_id)
+        .bind(&supplier.name)
+        .bind(&supplier.email)
+        .bind(&supplier.gstin)
+        .bind(&supplier.pincode)
+        .fetch_one(pool)
+        .await
+    }
+
+    pub async fn fetch_for_admin(pool: &PgPool, admin_id: i32) -> Result<Vec<Supplier>, sqlx::Error> {
+        sqlx::query_as::<_, Supplier>("SELECT * FROM suppliers WHERE admin_id = $1 ORDER BY name ASC")
+            .bind(admin_id)
+            .fetch_all(pool)
+            .await
+    }
+}
+
+// Another admin's purchase order is reported as not found
+async fn lock_purchase_order(
+    conn: &mut PgConnection,
+    admin_id: i32,
+    purchase_order_id: i32,
+) -> Result<PurchaseOrder, PurchasingError> {
+    sqlx::query_as::<_, PurchaseOrder>("SELECT * FROM purchase_orders WHERE id = $1 AND admin_id = $2 FOR UPDATE")
+        .bind(purchase_order_id)
+        .bind(admin_id)
+        .fetch_optional(conn)
+        .await?
+        .ok_or(PurchasingError::NotFound(purchase_order_id))
+}
+
+async fn fetch_lines(conn: &mut PgConnection, purchase_order_id: i32) -> Result<Vec<PurchaseOrderLine>, sqlx::Error> {
+    sqlx::query_as::<_, PurchaseOrderLine>(
+        "SELECT * FROM purchase_order_lines WHERE purchase_order_id = $1 ORDER BY id ASC"
+    )
+    .bind(purchase_order_id)
+    .fetch_all(conn)
+    .await
+}
+
+// Sums requested quantities per PO line and checks every line belongs to the PO
+fn merge_line_quantities(
+    requested: impl Iterator<Item = (i32, i32)>,
+    lines: &[PurchaseOrderLine],
+) -> Result<BTreeMap<i32, i64>, PurchasingError> {
+    let mut merged: BTreeMap<i32, i64> = BTreeMap::new();
+    for (line_id, quantity) in requested {
+        if quantity <= 0 {
+            return Err(PurchasingError::InvalidQuantity(line_id));
+        }
+        if !lines.iter().any(|l| l.id == line_id) {
+            return Err(PurchasingError::LineNotInPurchaseOrder(line_id));
+        }
+        *merged.entry(line_id).or_insert(0) += i64::from(quantity);
+    }
+    if merged.is_empty() {
+        return Err(PurchasingError::EmptyLines);
+    }
+    Ok(merged)
+}
+
+// Quantity times unit cost summed over (line, quantity, unit cost), refusing amounts that don't fit
+fn total_cost(lines: impl IntoIterator<Item = (i32, i32, i64)>) -> Result<i64, PurchasingError> {
+    lines.into_iter().try_fold(0i64, |total, (line_id, quantity, unit_cost_paise)| {
+        unit_cost_paise
+            .checked_mul(i64::from(quantity))
+            .and_then(|amount| total.checked_add(amount))
+            .ok_or(PurchasingError::AmountOverflow(line_id))
+    })
+}
+
+impl PurchaseOrder {
+    pub async fn create_purchase_order(
+        pool: &PgPool,
+        admin_id: i32,
+        purchase_order: &NewPurchaseOrder,
+    ) -> Result<(PurchaseOrder, Vec<PurchaseOrderLine>), PurchasingError> {
+        if purchase_order.lines.is_empty() {
+            return Err(PurchasingError::EmptyLines);
+        }
+        for line in &purchase_order.lines {
+            if line.quantity <= 0 {
+                return Err(PurchasingError::InvalidQuantity(line.product_id));
+            }
+            if line.unit_cost_paise < 0 {
+                return Err(PurchasingError::InvalidCost(line.product_id));
+            }
+        }
+
+        let mut tx = pool.begin().await?;
+
+        let supplier: Option<(i32,)> = sqlx::query_as("SELECT id FROM suppliers WHERE id = $1 AND admin_id = $2")
+            .bind(purchase_order.supplier_id)
+            .bind(admin_id)
+            .fetch_optional(&mut *tx)
+            .await?;
+        if supplier.is_none() {
+            return Err(PurchasingError::SupplierNotFound(purchase_order.supplier_id));
+        }
+
+        let warehouse: Option<(i32,)> = sqlx::query_as("SELECT id FROM warehouses WHERE id = $1 AND admin_id = $2")
+            .bind(purchase_order.warehouse_id)
+            .bind(admin_id)
+            .fetch_optional(&mut *tx)
+            .await?;
+        if warehouse.is_none() {
+            return Err(PurchasingError::WarehouseNotFound(purchase_order.warehouse_id));
+        }
+
+        for line in &purchase_order.lines {
+            let product: Option<(i32,)> = sqlx::query_as("SELECT id FROM products WHERE id = $1 AND admin_id = $2")
+                .bind(line.product_id)
+                .bind(admin_id)
+                .fetch_optional(&mut *tx)
+                .await?;
+            if product.is_none() {
+                return Err(PurchasingError::ProductNotFound(line.product_id));
+            }
+        }
+
+        let total_paise = total_cost(purchase_order.lines.iter().map(|l| (l.product_id, l.quantity, l.unit_cost_paise)))?;
+
+        let order = sqlx::query_as::<_, PurchaseOrder>(
+            "INSERT INTO purchase_orders (admin_id, supplier_id, warehouse_id, status, total_paise, note)
+             VALUES ($1, $2, $3, $4, $5, $6)
+             RETURNING *"
+        )
+        .bind(admin_id)
+        .bind(purchase_order.supplier_id)
+        .bind(purchase_order.warehouse_id)
+        .bind(PO_OPEN)
+        .bind(total_paise)
+        .bind(&purchase_order.note)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let mut lines = Vec::with_capacity(purchase_order.lines.len());
+        for line in &purchase_order.lines {
+            let inserted = sqlx::query_as::<_, PurchaseOrderLine>(
+                "INSERT INTO purchase_order_lines (purchase_order_id, product_id, quantity, unit_cost_paise)
+                 VALUES ($1, $2, $3, $4)
+                 RETURNING *"
+            )
+            .bind(order.id)
+            .bind(line.product_id)
+            .bind(line.quantity)
+            .bind(line.unit_cost_paise)
+            .fetch_one(&mut *tx)
+            .await?;
+            lines.push(inserted);
+        }
+
+        tx.commit().await?;
+
+        Ok((order, lines))
+    }
+
+    pub async fn fetch_purchase_order(
+        pool: &PgPool,
+        admin_id: i32,
+        purchase_order_id: i32,
+    ) -> Result<Option<(PurchaseOrder, Vec<PurchaseOrderLine>)>, sqlx::Error> {
+        let mut conn = pool.acquire().await?;
+
+        let order = sqlx::query_as::<_, PurchaseOrder>("SELECT * FROM purchase_orders WHERE id = $1 AND admin_id = $2")
+            .bind(purchase_order_id)
+            .bind(admin_id)
+            .fetch_optional(&mut *conn)
+            .await?;
+
+        match order {
+            Some(order) => {
+                let lines = fetch_lines(&mut conn, purchase_order_id).await?;
+                Ok(Some((order, lines)))
+            }
+            None => Ok(None),
+        }
+    }
+
+    // Only purchase orders with nothing received yet can be cancelled
+    pub async fn cancel_purchase_order(
+        pool: &PgPool,
+        admin_id: i32,
+        purchase_order_id: i32,
+    ) -> Result<PurchaseOrder, PurchasingError> {
+        let mut tx = pool.begin().await?;
+
+        let order = lock_purchase_order(&mut tx, admin_id, purchase_order_id).await?;
+        if order.status != PO_OPEN {
+            return Err(PurchasingError::InvalidStatus(order.status));
+        }
+
+        let order = sqlx::query_as::<_, PurchaseOrder>(
+            "UPDATE purchase_orders SET status = $1, updated_at = NOW() WHERE id = $2 RETURNING *"
+        )
+        .bind(PO_CANCELLED)
+        .bind(purchase_order_id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(order)
+    }
+}
+
+impl GoodsReceipt {
+    // Records a goods receipt note against a PO and posts a ledger receipt per product
+    // into the PO's warehouse. Receiving more than is outstanding on a line is refused.
+    pub async fn receive(
+        pool: &PgPool,
+        admin_id: i32,
+        purchase_order_id: i32,
+        receipt: &NewGoodsReceipt,
+    ) -> Result<(GoodsReceipt, Vec<GoodsReceiptLine>, PurchaseOrder), PurchasingError> {
+        let mut tx = pool.begin().await?;
+
+        let order = lock_purchase_order(&mut tx, admin_id, purchase_order_id).await?;
+        if order.status != PO_OPEN && order.status != PO_PARTIALLY_RECEIVED {
+            return Err(PurchasingError::InvalidStatus(order.status));
+        }
+
+        let lines = fetch_lines(&mut tx, purchase_order_id).await?;
+        let quantities = merge_line_quantities(
+            receipt.lines.iter().map(|l| (l.purchase_order_line_id, l.quantity)),
+            &lines,
+        )?;
+
+        let mut per_product: BTreeMap<i32, i32> = BTreeMap::new();
+        for (&line_id, &requested) in &quantities {
+            let line = lines.iter().find(|l| l.id == line_id).expect("checked by merge_line_quantities");
+            let outstanding = i64::from(line.quantity - line.received_quantity);
+            if requested > outstanding {
+                return Err(PurchasingError::OverReceipt { purchase_order_line_id: line_id, requested, outstanding });
+            }
+            *per_product.entry(line.product_id).or_insert(0) += requested as i32;
+        }
+
+        let goods_receipt = sqlx::query_as::<_, GoodsReceipt>(
+            "INSERT INTO goods_receipts (purchase_order_id, warehouse_id, note)
+             VALUES ($1, $2, $3)
+             RETURNING *"
+        )
+        .bind(purchase_order_id)
+        .bind(order.warehouse_id)
+        .bind(&receipt.note)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let mut receipt_lines = Vec::with_capacity(quantities.len());
+        for (&line_id, &quantity) in &quantities {
+            let inserted = sqlx::query_as::<_, GoodsReceiptLine>(
+                "INSERT INTO goods_receipt_lines (goods_receipt_id, purchase_order_line_id, quantity)
+                 VALUES ($1, $2, $3)
+                 RETURNING *"
+            )
+            .bind(goods_receipt.id)
+            .bind(line_id)
+            .bind(quantity as i32)
+            .fetch_one(&mut *tx)
+            .await?;
+            receipt_lines.push(inserted);
+
+            sqlx::query("UPDATE purchase_order_lines SET received_quantity = received_quantity + $1 WHERE id = $2")
+                .bind(quantity as i32)
+                .bind(line_id)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        // Product rows are locked in id order, same as order placement, to avoid deadlocks
+        let reference = format!("grn:{}", goods_receipt.id);
+        for (product_id, quantity) in per_product {
+            sqlx::query("SELECT id FROM products WHERE id = $1 FOR UPDATE")
+                .bind(product_id)
+                .execute(&mut *tx)
+                .await?;
+            StockMovement::post(
+                &mut tx,
+                &Posting {
+                    product_id,
+                    warehouse_id: order.warehouse_id,
+                    movement_type: MovementType::Receipt,
+                    on_hand_delta: quantity,
+                    reserved_delta: 0,
+                    reference: Some(&reference),
+                    note: receipt.note.as_deref(),
+                },
+            )
+            .await?;
+        }
+
+        let fully_received = fetch_lines(&mut tx, purchase_order_id)
+            .await?
+            .iter()
+            .all(|l| l.received_quantity == l.quantity);
+        let status = if fully_received { PO_RECEIVED } else { PO_PARTIALLY_RECEIVED };
+
+        let order = sqlx::query_as::<_, PurchaseOrder>(
+            "UPDATE purchase_orders SET status = $1, updated_at = NOW() WHERE id = $2 RETURNING *"
+        )
+        .bind(status)
+        .bind(purchase_order_id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok((goods_receipt, receipt_lines, order))
+    }
+}
+
+impl SupplierInvoice {
+    // Records the supplier's bill against a PO. Differences from the PO or the receipts
+    // are not rejected here; they show up in the three-way match.
+    pub async fn record(
+        pool: &PgPool,
+        admin_id: i32,
+        purchase_order_id: i32,
+        invoice: &NewSupplierInvoice,
+    ) -> Result<(SupplierInvoice, Vec<SupplierInvoiceLine>), PurchasingError> {
+        for line in &invoice.lines {
+            if line.unit_cost_paise < 0 {
+                return Err(PurchasingError::InvalidCost(line.purchase_order_line_id));
+            }
+        }
+
+        let mut tx = pool.begin().await?;
+
+        let order = lock_purchase_order(&mut tx, admin_id, purchase_order_id).await?;
+        if order.status == PO_CANCELLED {
+            return Err(PurchasingError::InvalidStatus(order.status));
+        }
+
+        let lines = fetch_lines(&mut tx, purchase_order_id).await?;
+        merge_line_quantities(invoice.lines.iter().map(|l| (l.purchase_order_line_id, l.quantity)), &lines)?;
+
+        let total_paise = total_cost(invoice.lines.iter().map(|l| (l.purchase_order_line_id, l.quantity, l.unit_cost_paise)))?;
+
+        let recorded = sqlx::query_as::<_, SupplierInvoice>(
+            "INSERT INTO supplier_invoices (purchase_order_id, supplier_id, invoice_number, total_paise)
+             VALUES ($1, $2, $3, $4)
+             RETURNING *"
+        )
+        .bind(purchase_order_id)
+        .bind(order.supplier_id)
+        .bind(&invoice.invoice_number)
+        .bind(total_paise)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let mut recorded_lines = Vec::with_capacity(invoice.lines.len());
+        for line in &invoice.lines {
+            let inserted = sqlx::query_as::<_, SupplierInvoiceLine>(
+                "INSERT INTO supplier_invoice_lines
+                    (supplier_invoice_id, purchase_order_line_id, quantity, unit_cost_paise)
+                 VALUES ($1, $2, $3, $4)
+                 RETURNING *"
+            )
+            .bind(recorded.id)
+            .bind(line.purchase_order_line_id)
+            .bind(line.quantity)
+            .bind(line.unit_cost_paise)
+            .fetch_one(&mut *tx)
+            .await?;
+            recorded_lines.push(inserted);
+        }
+
+        tx.commit().await?;
+
+        Ok((recorded, recorded_lines))
+    }
+}
+
+// Compares each PO line with what was received and what suppliers billed for it.
+// A line matches when everything received has been billed at the PO cost; billing
+// less than received is still pending, anything else is a mismatch.
+pub fn three_way_match(
+    purchase_order_id: i32,
+    lines: &[PurchaseOrderLine],
+    invoice_lines: &[SupplierInvoiceLine],
+) -> MatchReport {
+    let mut billed: HashMap<i32, Vec<&SupplierInvoiceLine>> = HashMap::new();
+    for invoice_line in invoice_lines {
+        billed.entry(invoice_line.purchase_order_line_id).or_default().push(invoice_line);
+    }
+
+    let lines: Vec<MatchLine> = lines
+        .iter()
+        .map(|line| {
+            let billed = billed.get(&line.id).map(Vec::as_slice).unwrap_or_default();
+            let invoiced_quantity: i64 = billed.iter().map(|b| i64::from(b.quantity)).sum();
+            let invoiced_paise: i64 = billed.iter().map(|b| i64::from(b.quantity) * b.unit_cost_paise).sum();
+            let received = i64::from(line.received_quantity);
+
+            let mut exceptions = Vec::new();
+            if invoiced_quantity > received {
+                exceptions.push(format!("billed {} but received {}", invoiced_quantity, received));
+            }
+            for b in billed.iter().filter(|b| b.unit_cost_paise != line.unit_cost_paise) {
+                exceptions.push(format!(
+                    "billed at {} paise per unit, PO cost is {}",
+                    b.unit_cost_paise, line.unit_cost_paise
+                ));
+            }
+
+            let status = if !exceptions.is_empty() {
+                MATCH_MISMATCH
+            } else if invoiced_quantity < received || received == 0 {
+                MATCH_PENDING
+            } else {
+                MATCH_MATCHED
+            };
+
+            MatchLine {
+                purchase_order_line_id: line.id,
+                product_id: line.product_id,
+                ordered_quantity: line.quantity,
+                received_quantity: line.received_quantity,
+                invoiced_quantity,
+                unit_cost_paise: line.unit_cost_paise,
+                invoiced_paise,
+                status,
+                exceptions,
+            }
+        })
+        .collect();
+
+    let status = if lines.iter().any(|l| l.status == MATCH_MISMATCH) {
+        MATCH_MISMATCH
+    } else if lines.iter().all(|l| l.status == MATCH_MATCHED) {
+        MATCH_MATCHED
+    } else {
+        MATCH_PENDING
+    };
+
+    MatchReport { purchase_order_id, status, lines }
+}
+
+impl MatchReport {
+    pub async fn fetch(pool: &PgPool, admin_id: i32, purchase_order_id: i32) -> Result<Option<MatchReport>, sqlx::Error> {
+        let Some((_, lines)) = PurchaseOrder::fetch_purchase_order(pool, admin_id, purchase_order_id).await? else {
+            return Ok(None);
+        };
+
+        let invoice_lines = sqlx::query_as::<_, SupplierInvoiceLine>(
+            "SELECT il.*
+             FROM supplier_invoice_lines il
+             JOIN supplier_invoices i ON i.id = il.supplier_invoice_id
+             WHERE i.purchase_order_id = $1
+             ORDER BY il.id ASC"
+        )
+        .bind(purchase_order_id)
+        .fetch_all(pool)
+        .await?;
+
+        Ok(Some(three_way_match(purchase_order_id, &lines, &invoice_lines)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        three_way_match, total_cost, PurchaseOrderLine, PurchasingError, SupplierInvoiceLine, MATCH_MATCHED,
+        MATCH_MISMATCH, MATCH_PENDING,
+    };
+
+    fn po_line(id: i32, quantity: i32, received_quantity: i32, unit_cost_paise: i64) -> PurchaseOrderLine {
+        PurchaseOrderLine {
+            id,
+            purchase_order_id: 1,
+            product_id: id * 10,
+            quantity,
+            unit_cost_paise,
+            received_quantity,
+        }
+    }
+
+    fn billed(purchase_order_line_id: i32, quantity: i32, unit_cost_paise: i64) -> SupplierInvoiceLine {
+        SupplierInvoiceLine {
+            id: purchase_order_line_id * 100 + quantity,
+            supplier_invoice_id: 1,
+            purchase_order_line_id,
+            quantity,
+            unit_cost_paise,
+        }
+    }
+
+    #[test]
+    fn fully_received_and_billed_at_po_cost_matches() {
+        let lines = [po_line(1, 10, 10, 500), po_line(2, 4, 4, 250)];
+        let invoiced = [billed(1, 6, 500), billed(1, 4, 500), billed(2, 4, 250)];
+
+        let report = three_way_match(1, &lines, &invoiced);
+
+        assert_eq!(report.status, MATCH_MATCHED);
+        assert_eq!(report.lines[0].invoiced_quantity, 10);
+        assert_eq!(report.lines[0].invoiced_paise, 5000);
+        assert!(report.lines.iter().all(|l| l.exceptions.is_empty()));
+    }
+
+    #[test]
+    fn short_receipt_matches_only_what_was_received() {
+        let lines = [po_line(1, 10, 6, 500)];
+
+        let billed_for_receipt = three_way_match(1, &lines, &[billed(1, 6, 500)]);
+        assert_eq!(billed_for_receipt.status, MATCH_MATCHED);
+
+        let not_billed_yet = three_way_match(1, &lines, &[]);
+        assert_eq!(not_billed_yet.status, MATCH_PENDING);
+
+        let nothing_received = three_way_match(1, &[po_line(1, 10, 0, 500)], &[]);
+        assert_eq!(nothing_received.status, MATCH_PENDING);
+    }
+
+    #[test]
+    fn billing_more_than_received_is_a_mismatch() {
+        let lines = [po_line(1, 10, 6, 500), po_line(2, 4, 4, 250)];
+        let invoiced = [billed(1, 10, 500), billed(2, 4, 250)];
+
+        let report = three_way_match(1, &lines, &invoiced);
+
+        assert_eq!(report.status, MATCH_MISMATCH);
+        assert_eq!(report.lines[0].status, MATCH_MISMATCH);
+        assert_eq!(report.lines[0].exceptions, vec!["billed 10 but received 6".to_string()]);
+        assert_eq!(report.lines[1].status, MATCH_MATCHED);
+    }
+
+    #[test]
+    fn unit_price_other_than_po_cost_is_a_mismatch() {
+        let lines = [po_line(1, 10, 10, 500)];
+        let invoiced = [billed(1, 5, 500), billed(1, 5, 550)];
+
+        let report = three_way_match(1, &lines, &invoiced);
+
+        assert_eq!(report.status, MATCH_MISMATCH);
+        assert_eq!(report.lines[0].invoiced_paise, 5250);
+        assert_eq!(
+            report.lines[0].exceptions,
+            vec!["billed at 550 paise per unit, PO cost is 500".to_string()]
+        );
+    }
+
+    #[test]
+    fn total_cost_rejects_amounts_that_overflow() {
+        assert_eq!(total_cost([(1, 10, 500), (2, 4, 250)]).unwrap(), 6000);
+        assert!(matches!(
+            total_cost([(1, 3, i64::MAX / 2)]),
+            Err(PurchasingError::AmountOverflow(1))
+        ));
+        assert!(matches!(
+            total_cost([(1, 1, i64::MAX / 2), (2, 1, i64::MAX / 2), (3, 1, 2)]),
+            Err(PurchasingError::AmountOverflow(3))
+        ));
+    }
+}
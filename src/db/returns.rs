@@ -0,0 +1,513 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use chrono::{NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use sqlx::types::Json;
+use sqlx::{FromRow, PgConnection, PgPool};
+
+use crate::db::inventory::{MovementType, Posting, StockMovement};
+use crate::db::invoices::{document_number, next_sequence, Invoice, SERIES_CREDIT_NOTE};
+use crate::db::orders::{Order, STATUS_DELIVERED};
+use crate::gst;
+
+pub const RETURN_REQUESTED: &str = "requested";
+pub const RETURN_APPROVED: &str = "approved";
+pub const RETURN_REJECTED: &str = "rejected";
+
+#[derive(FromRow, Serialize, Deserialize, Debug, Clone)]
+pub struct ReturnRequest {
+    pub id: i32,
+    pub order_id: i32,
+    pub admin_id: i32,
+    pub status: String,
+    pub reason: String,
+    pub decision_note: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub decided_at: Option<NaiveDateTime>,
+}
+
+#[derive(FromRow, Serialize, Deserialize, Debug, Clone)]
+pub struct ReturnLine {
+    pub id: i32,
+    pub return_id: i32,
+    pub order_line_id: i32,
+    pub quantity: i32,
+}
+
+#[derive(FromRow, Serialize, Deserialize, Debug, Clone)]
+pub struct CreditNote {
+    pub id: i32,
+    pub admin_id: i32,
+    pub return_id: i32,
+    pub invoice_id: i32,
+    pub credit_note_number: String,
+    pub financial_year: String,
+    pub sequence_number: i32,
+    pub supply_type: String,
+    pub taxable_paise: i64,
+    pub cgst_paise: i64,
+    pub sgst_paise: i64,
+    pub igst_paise: i64,
+    pub total_paise: i64,
+    pub lines: Json<Vec<CreditNoteLine>>,
+    pub issued_at: NaiveDateTime,
+}
+
+// Returned share of an invoiced line, with the GST that is being reversed
+#[derive(FromRow, Serialize, Deserialize, Debug, Clone)]
+pub struct CreditNoteLine {
+    pub order_line_id: i32,
+    pub product_id: i32,
+    pub sku: String,
+    pub name: String,
+    pub hsn_code: Option<String>,
+    pub quantity: i32,
+    pub taxable_paise: i64,
+    pub gst_rate_bp: i32,
+    pub cgst_paise: i64,
+    pub sgst_paise: i64,
+    pub igst_paise: i64,
+}
+
+#[derive(Deserialize, Debug, Clone, ToSchema)]
+pub struct NewReturnLine {
+    pub order_line_id: i32,
+    pub quantity: i32,
+}
+
+#[derive(Deserialize, Debug, Clone, ToSchema)]
+pub struct NewReturn {
+    pub reason: String,
+    pub lines: Vec<NewReturnLine>,
+}
+
+// Order line joined with what has already been claimed by other returns
+#[derive(FromRow)]
+struct ReturnableLine {
+    id: i32,
+    product_id: i32,
+    sku: String,
+    name: String,
+    hsn_code: Option<String>,
+    quantity: i32,
+    line_total_paise: i64,
+    gst_rate_bp: i32,
+    cgst_paise: i64,
+    sgst_paise: i64,
+    igst_paise: i64,
+    returned: i64,
+}
+
+// What earlier credit notes on an invoice have already credited for one order line
+#[derive(FromRow, Clone, Copy, Default)]
+struct Credited {
+    order_line_id: i32,
+    quantity: i64,
+    taxable_paise: i64,
+    cgst_paise: i64,
+    sgst_paise: i64,
+    igst_paise: i64,
+}
+
+#[derive(Debug)]
+pub enum ReturnError {
+    Db(sqlx::Error),
+    EmptyReturn,
+    InvalidQuantity(i32),
+    OrderNotFound(i32),
+    NotFound(i32),
+    NotReturnable(String),
+    LineNotInOrder(i32),
+    QuantityExceeded { order_line_id: i32, requested: i64, returnable: i64 },
+    NotOwner(i32),
+    AlreadyDecided(String),
+    NotInvoiced(i32),
+}
+
+impl From<sqlx::Error> for ReturnError {
+    fn from(e: sqlx::Error) -> Self {
+        ReturnError::Db(e)
+    }
+}
+
+impl fmt::Display for ReturnError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReturnError::Db(_) => write!(f, "DB Error"),
+            ReturnError::EmptyReturn => write!(f, "Return must contain at least one line"),
+            ReturnError::InvalidQuantity(order_line_id) => {
+                write!(f, "Quantity for order line {} must be greater than zero", order_line_id)
+            }
+            ReturnError::OrderNotFound(order_id) => write!(f, "Order {} not found", order_id),
+            ReturnError::NotFound(return_id) => write!(f, "Return {} not found", return_id),
+            ReturnError::NotReturnable(status) => write!(f, "Order cannot be returned in status '{}'", status),
+            ReturnError::LineNotInOrder(order_line_id) => {
+                write!(f, "Order line {} does not belong to this order", order_line_id)
+            }
+            ReturnError::QuantityExceeded { order_line_id, requested, returnable } => write!(
+                f,
+                "Cannot return {} of order line {}: only {} left to return",
+                requested, order_line_id, returnable
+            ),
+            ReturnError::NotOwner(admin_id) => write!(f, "Admin {} does not own this order", admin_id),
+            ReturnError::AlreadyDecided(status) => write!(f, "Return is already {}", status),
+            ReturnError::NotInvoiced(order_id) => write!(f, "Order {} has no invoice to credit", order_id),
+        }
+    }
+}
+
+// Share of a line amount for `quantity` out of `of` units, rounded half-up
+fn pro_rata(amount: i64, quantity: i64, of: i32) -> i64 {
+    let of = i64::from(of);
+    (amount * quantity * 2 + of) / (of * 2)
+}
+
+// Rounding each return on its own can add up to more than the line was invoiced for, so the
+// credit is what all returned units are worth so far minus what was credited before
+fn credit(amount: i64, credited: i64, returned: i64, of: i32) -> i64 {
+    pro_rata(amount, returned, of) - credited
+}
+
+async fn credited_lines(conn: &mut PgConnection, invoice_id: i32) -> Result<Vec<Credited>, sqlx::Error> {
+    sqlx::query_as::<_, Credited>(
+        "SELECT (l->>'order_line_id')::INT AS order_line_id,
+                SUM((l->>'quantity')::BIGINT)::BIGINT AS quantity,
+                SUM((l->>'taxable_paise')::BIGINT)::BIGINT AS taxable_paise,
+                SUM((l->>'cgst_paise')::BIGINT)::BIGINT AS cgst_paise,
+                SUM((l->>'sgst_paise')::BIGINT)::BIGINT AS sgst_paise,
+                SUM((l->>'igst_paise')::BIGINT)::BIGINT AS igst_paise
+         FROM credit_notes c, jsonb_array_elements(c.lines) AS l
+         WHERE c.invoice_id = $1
+         GROUP BY 1"
+    )
+    .bind(invoice_id)
+    .fetch_all(conn)
+    .await
+}
+
+// Order lines with the quantity already claimed by returns that weren't rejected
+async fn returnable_lines(conn: &mut PgConnection, order_id: i32) -> Result<Vec<ReturnableLine>, sqlx::Error> {
+    sqlx::query_as::<_, ReturnableLine>(
+        "SELECT l.id, l.product_id, p.sku, p.name, l.hsn_code, l.quantity, l.line_total_paise,
+                l.gst_rate_bp, l.cgst_paise, l.sgst_paise, l.igst_paise,
+                COALESCE((SELECT SUM(rl.quantity)
+                          FROM return_lines rl
+                          JOIN return_requests r ON r.id = rl.return_id
+                          WHERE rl.order_line_id = l.id AND r.status <> 'rejected'), 0)::BIGINT AS returned
+         FROM order_lines l
+         JOIN products p ON p.id = l.product_id
+         WHERE l.order_id = $1
+         ORDER BY l.id ASC"
+    )
+    .bind(order_id)
+    .fetch_all(conn)
+    .await
+}
+
+async fn lock_return(conn: &mut PgConnection, return_id: i32, admin_id: i32) -> Result<ReturnRequest, ReturnError> {
+    let request = sqlx::query_as::<_, ReturnRequest>("SELECT * FROM return_requests WHERE id = $1 FOR UPDATE")
+        .bind(return_id)
+        .fetch_optional(conn)
+        .await?
+        .ok_or(ReturnError::NotFound(return_id))?;
+
+    if request.admin_id != admin_id {
+        return Err(ReturnError::NotOwner(admin_id));
+    }
+    if request.status != RETURN_REQUESTED {
+        return Err(ReturnError::AlreadyDecided(request.status));
+    }
+
+    Ok(request)
+}
+
+async fn decide(
+    conn: &mut PgConnection,
+    return_id: i32,
+    status: &str,
+    note: Option<&str>,
+) -> Result<ReturnRequest, sqlx::Error> {
+    sqlx::query_as::<_, ReturnRequest>(
+        "UPDATE return_requests SET status = $1, decision_note = $2, decided_at = $3 WHERE id = $4 RETURNING *"
+    )
+    .bind(status)
+    .bind(note)
+    .bind(Utc::now().naive_utc())
+    .bind(return_id)
+    .fetch_one(conn)
+    .await
+}
+
+impl ReturnRequest {
+    // Opens a return against lines of a delivered order. Quantities are checked against
+    // what is still returnable, counting returns that are pending or already approved.
+    pub async fn create(
+        pool: &PgPool,
+        order_id: i32,
+        admin_id: i32,
+        new_return: &NewReturn,
+    ) -> Result<ReturnRequest, ReturnError> {
+        if new_return.lines.is_empty() {
+            return Err(ReturnError::EmptyReturn);
+        }
+        let mut quantities: BTreeMap<i32, i64> = BTreeMap::new();
+        for line in &new_return.lines {
+            if line.quantity <= 0 {
+                return Err(ReturnError::InvalidQuantity(line.order_line_id));
+            }
+            *quantities.entry(line.order_line_id).or_insert(0) += i64::from(line.quantity);
+        }
+
+        let mut tx = pool.begin().await?;
+
+        // Serialises concurrent returns for the same order
+        let order = sqlx::query_as::<_, Order>("SELECT * FROM orders WHERE id = $1 FOR UPDATE")
+            .bind(order_id)
+            .fetch_optional(&mut *tx)
+            .await?
+            .ok_or(ReturnError::OrderNotFound(order_id))?;
+        if order.admin_id != admin_id {
+            return Err(ReturnError::NotOwner(admin_id));
+        }
+
+        if order.status != STATUS_DELIVERED {
+            return Err(ReturnError::NotReturnable(order.status));
+        }
+
+        let lines = returnable_lines(&mut tx, order_id).await?;
+        for (&order_line_id, &requested) in &quantities {
+            let line = lines
+                .iter()
+                .find(|l| l.id == order_line_id)
+                .ok_or(ReturnError::LineNotInOrder(order_line_id))?;
+            let returnable = i64::from(line.quantity) - line.returned;
+            if requested > returnable {
+                return Err(ReturnError::QuantityExceeded { order_line_id, requested, returnable });
+            }
+        }
+
+        let request = sqlx::query_as::<_, ReturnRequest>(
+            "INSERT INTO return_requests (order_id, admin_id, status, reason)
+             VALUES ($1, $2, $3, $4)
+             RETURNING *"
+        )
+        .bind(order_id)
+        .bind(order.admin_id)
+        .bind(RETURN_REQUESTED)
+        .bind(&new_return.reason)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        for (order_line_id, quantity) in quantities {
+            sqlx::query("INSERT INTO return_lines (return_id, order_line_id, quantity) VALUES ($1, $2, $3)")
+                .bind(request.id)
+                .bind(order_line_id)
+                .bind(quantity as i32)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        tx.commit().await?;
+
+        Ok(request)
+    }
+
+    // Approval by the owning admin puts the goods back on the shelf and issues a credit note
+    // against the original invoice, all in one transaction.
+    pub async fn approve(
+        pool: &PgPool,
+        return_id: i32,
+        admin_id: i32,
+        note: Option<&str>,
+    ) -> Result<(ReturnRequest, CreditNote), ReturnError> {
+        let mut tx = pool.begin().await?;
+
+        let request = lock_return(&mut tx, return_id, admin_id).await?;
+
+        // Approvals of the same order take turns so each sees the credit notes before it
+        let order = sqlx::query_as::<_, Order>("SELECT * FROM orders WHERE id = $1 FOR UPDATE")
+            .bind(request.order_id)
+            .fetch_one(&mut *tx)
+            .await?;
+        let invoice = Invoice::fetch_for_order(&mut tx, order.id)
+            .await?
+            .ok_or(ReturnError::NotInvoiced(order.id))?;
+
+        let returned = ReturnLine::fetch_for_return(&mut tx, return_id).await?;
+        let order_lines = returnable_lines(&mut tx, order.id).await?;
+        let credited = credited_lines(&mut tx, invoice.id).await?;
+
+        let mut lines = Vec::with_capacity(returned.len());
+        for item in &returned {
+            let Some(line) = order_lines.iter().find(|l| l.id == item.order_line_id) else {
+                continue;
+            };
+            let before = credited.iter().find(|c| c.order_line_id == line.id).copied().unwrap_or_default();
+            let returned = before.quantity + i64::from(item.quantity);
+            lines.push(CreditNoteLine {
+                order_line_id: line.id,
+                product_id: line.product_id,
+                sku: line.sku.clone(),
+                name: line.name.clone(),
+                hsn_code: line.hsn_code.clone(),
+                quantity: item.quantity,
+                taxable_paise: credit(line.line_total_paise, before.taxable_paise, returned, line.quantity),
+                gst_rate_bp: line.gst_rate_bp,
+                cgst_paise: credit(line.cgst_paise, before.cgst_paise, returned, line.quantity),
+                sgst_paise: credit(line.sgst_paise, before.sgst_paise, returned, line.quantity),
+                igst_paise: credit(line.igst_paise, before.igst_paise, returned, line.quantity),
+            });
+        }
+
+        // Orders placed before warehouses existed have nowhere to restock into
+        if let Some(warehouse_id) = order.warehouse_id {
+            let mut restock: BTreeMap<i32, i32> = BTreeMap::new();
+            for line in &lines {
+                *restock.entry(line.product_id).or_insert(0) += line.quantity;
+            }
+            let reference = format!("return:{}", return_id);
+            for (product_id, quantity) in restock {
+                sqlx::query("SELECT id FROM products WHERE id = $1 FOR UPDATE")
+                    .bind(product_id)
+                    .execute(&mut *tx)
+                    .await?;
+                StockMovement::post(
+                    &mut tx,
+                    &Posting {
+                        product_id,
+                        warehouse_id,
+                        movement_type: MovementType::Receipt,
+                        on_hand_delta: quantity,
+                        reserved_delta: 0,
+                        reference: Some(&reference),
+                        note: Some("customer return"),
+                    },
+                )
+                .await?;
+            }
+        }
+
+        let financial_year = gst::financial_year(Utc::now().naive_utc());
+        let sequence_number = next_sequence(&mut tx, order.admin_id, SERIES_CREDIT_NOTE, &financial_year).await?;
+        let credit_note_number = document_number(SERIES_CREDIT_NOTE, &financial_year, sequence_number);
+
+        let taxable_paise: i64 = lines.iter().map(|l| l.taxable_paise).sum();
+        let cgst_paise: i64 = lines.iter().map(|l| l.cgst_paise).sum();
+        let sgst_paise: i64 = lines.iter().map(|l| l.sgst_paise).sum();
+        let igst_paise: i64 = lines.iter().map(|l| l.igst_paise).sum();
+
+        let credit_note = sqlx::query_as::<_, CreditNote>(
+            "INSERT INTO credit_notes
+                (admin_id, return_id, invoice_id, credit_note_number, financial_year, sequence_number,
+                 supply_type, taxable_paise, cgst_paise, sgst_paise, igst_paise, total_paise, lines)
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)
+             RETURNING *"
+        )
+        .bind(order.admin_id)
+        .bind(return_id)
+        .bind(invoice.id)
+        .bind(&credit_note_number)
+        .bind(&financial_year)
+        .bind(sequence_number)
+        .bind(&invoice.supply_type)
+        .bind(taxable_paise)
+        .bind(cgst_paise)
+        .bind(sgst_paise)
+        .bind(igst_paise)
+        .bind(taxable_paise + cgst_paise + sgst_paise + igst_paise)
+        .bind(Json(&lines))
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let request = decide(&mut tx, request.id, RETURN_APPROVED, note).await?;
+
+        tx.commit().await?;
+
+        Ok((request, credit_note))
+    }
+
+    pub async fn reject(
+        pool: &PgPool,
+        return_id: i32,
+        admin_id: i32,
+        note: Option<&str>,
+    ) -> Result<ReturnRequest, ReturnError> {
+        let mut tx = pool.begin().await?;
+
+        lock_return(&mut tx, return_id, admin_id).await?;
+        let request = decide(&mut tx, return_id, RETURN_REJECTED, note).await?;
+
+        tx.commit().await?;
+
+        Ok(request)
+    }
+
+    pub async fn fetch_return(
+        pool: &PgPool,
+        return_id: i32,
+    ) -> Result<Option<(ReturnRequest, Vec<ReturnLine>)>, sqlx::Error> {
+        let mut conn = pool.acquire().await?;
+
+        let request = sqlx::query_as::<_, ReturnRequest>("SELECT * FROM return_requests WHERE id = $1")
+            .bind(return_id)
+            .fetch_optional(&mut *conn)
+            .await?;
+
+        match request {
+            Some(request) => {
+                let lines = ReturnLine::fetch_for_return(&mut conn, return_id).await?;
+                Ok(Some((request, lines)))
+            }
+            None => Ok(None),
+        }
+    }
+}
+
+impl ReturnLine {
+    pub async fn fetch_for_return(conn: &mut PgConnection, return_id: i32) -> Result<Vec<ReturnLine>, sqlx::Error> {
+        sqlx::query_as::<_, ReturnLine>("SELECT * FROM return_lines WHERE return_id = $1 ORDER BY id ASC")
+            .bind(return_id)
+            .fetch_all(conn)
+            .await
+    }
+}
+
+impl CreditNote {
+    pub async fn fetch_for_return(pool: &PgPool, return_id: i32) -> Result<Option<CreditNote>, sqlx::Error> {
+        sqlx::query_as::<_, CreditNote>("SELECT * FROM credit_notes WHERE return_id = $1")
+            .bind(return_id)
+            .fetch_optional(pool)
+            .await
+    }
+
+    pub async fn fetch_credit_note(pool: &PgPool, credit_note_id: i32) -> Result<Option<CreditNote>, sqlx::Error> {
+        sqlx::query_as::<_, CreditNote>("SELECT * FROM credit_notes WHERE id = $1")
+            .bind(credit_note_id)
+            .fetch_optional(pool)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::credit;
+
+    #[test]
+    fn partial_returns_never_credit_more_than_invoiced() {
+        // 200 paise over 3 units: 67 + 66 + 67, where rounding each return alone gives 201
+        let mut credited = 0;
+        let mut returned = 0;
+        for expected in [67, 66, 67] {
+            returned += 1;
+            let amount = credit(200, credited, returned, 3);
+            assert_eq!(amount, expected);
+            credited += amount;
+        }
+        assert_eq!(credited, 200);
+
+        assert_eq!(credit(200, 67, 3, 3), 133);
+        assert_eq!(credit(200, 0, 3, 3), 200);
+    }
+}
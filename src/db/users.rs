@@ -1,10 +1,10 @@
-use std::error::Error;
-
 use serde::{Deserialize, Serialize};
-use sqlx::{PgPool, FromRow, Error as SqlxError};
+use sqlx::{PgPool, FromRow};
 
-use sqlx::types::Json;
 use chrono::NaiveDateTime;
+
+use crate::db::outbox::OutboxEvent;
+use crate::events::{AdminCreated, AdminUserCreated, OtpIssued, UserRegistered};
 #[derive(FromRow, Serialize, Deserialize, Debug)]
 pub struct User {
     pub id: i32,           // Primary Key
@@ -27,10 +27,12 @@ pub struct Admin {
     pub user_name: String,
     pub mobile: String,
     pub email: String,
-    pub pincode: String
+    pub pincode: String,
+    pub gstin: Option<String>
 }
 
 
+#[allow(non_camel_case_types)]
 #[derive(FromRow, Serialize, Deserialize, Debug)]
 pub struct Admin_Users {
     pub id : i32,
@@ -46,36 +48,78 @@ pub struct Admin_Users {
 impl User {
     // Create a new user in the database and return the created user object
     pub async fn create_user(pool: &PgPool, username: &str, email: &str, mobile: &str) -> Result<User, sqlx::Error> {
+        let mut tx = pool.begin().await?;
+
         let user = sqlx::query_as::<_, User>(
             "INSERT INTO registration (username, email, mobile) VALUES ($1, $2, $3) RETURNING *"
         )
         .bind(username)
         .bind(email)
         .bind(mobile)
-        .fetch_one(pool)
+        .fetch_one(&mut *tx)
         .await?;
 
+        OutboxEvent::record(&mut tx, &UserRegistered {
+            user_id: user.id,
+            username: user.username.clone(),
+            email: user.email.clone(),
+            mobile: user.mobile.clone(),
+        })
+        .await?;
+
+        tx.commit().await?;
+
         Ok(user)
     }
 }
 
 
 impl Otp {
-    pub async fn add_otp(pool: &PgPool, otp: i32) -> Result<Otp, sqlx::Error> {
+    pub async fn add_otp(pool: &PgPool, otp: i32, email: &str, mobile: &str) -> Result<Otp, sqlx::Error> {
+        let mut tx = pool.begin().await?;
+
         let otp_record = sqlx::query_as::<_, Otp>(
-           "INSERT INTO otp (otp) VALUES (ARRAY[$1]) RETURNING id, otp, created_at"
+           "INSERT INTO otp (otp, email, mobile) VALUES (ARRAY[$1], $2, $3) RETURNING id, otp, created_at"
         )
         .bind(otp)
-        .fetch_one(pool)
+        .bind(email)
+        .bind(mobile)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        OutboxEvent::record(&mut tx, &OtpIssued {
+            otp_id: otp_record.id,
+            email: email.to_string(),
+            mobile: mobile.to_string(),
+        })
         .await?;
+
+        tx.commit().await?;
     
         Ok(otp_record)
     }
     
-    pub async fn fetch_all_otps(pool: &PgPool) -> Result<Vec<Otp>, sqlx::Error> {
-        let otps = sqlx::query_as::<_, Otp>("SELECT id, otp, created_at FROM otp")
-            .fetch_all(pool)
-            .await?;
+    pub async fn fetch_otp(pool: &PgPool, id: i32) -> Result<Option<Otp>, sqlx::Error> {
+        sqlx::query_as::<_, Otp>("SELECT id, otp, created_at FROM otp WHERE id = $1")
+            .bind(id)
+            .fetch_optional(pool)
+            .await
+    }
+
+    // OTPs sent to the email or the mobile; whichever is given has to match
+    pub async fn fetch_for_recipient(
+        pool: &PgPool,
+        email: Option<&str>,
+        mobile: Option<&str>,
+    ) -> Result<Vec<Otp>, sqlx::Error> {
+        let otps = sqlx::query_as::<_, Otp>(
+            "SELECT id, otp, created_at FROM otp
+             WHERE LOWER(email) = LOWER(TRIM($1)) OR mobile = TRIM($2)"
+        )
+        .bind(email)
+        .bind(mobile)
+        .fetch_all(pool)
+        .await?;
     
         Ok(otps)
     }
@@ -127,7 +171,7 @@ impl Otp {
 
     pub async fn generate_code_for_users(pool: &PgPool, admin_id: &i32) -> Result<String, sqlx::Error> {
         let mut lst = String::new();
-        let mut truncated_du_no = String::new();
+        let mut truncated_du_no: String;
     
         // Query to find records with the specified m_code
         let count: Vec<Admin> = sqlx::query_as("SELECT * FROM admins WHERE admin_id = $1")
@@ -144,7 +188,7 @@ impl Otp {
     
             // Sort by m_id in descending order
             let mut sorted_rows = find;
-            sorted_rows.sort_by(|a, b| b.id.cmp(&a.id));
+            sorted_rows.sort_by_key(|row| std::cmp::Reverse(row.id));
     
             if !sorted_rows.is_empty() {
                 let last_record = &sorted_rows[0];
@@ -160,7 +204,7 @@ impl Otp {
                     }
                 } 
                 // Check if last digits are greater than 9
-                else if last_record.regcode.len() >= 1 {
+                else if !last_record.regcode.is_empty() {
                     // Use regex to find the trailing digits
                     let re = regex::Regex::new(r"\d+$").unwrap();
                     if let Some(captures) = re.find(&last_record.regcode) {
@@ -188,20 +232,35 @@ impl Otp {
         user_name: &str,
         mobile: &str,
         email: &str,
-        pincode: &str
+        pincode: &str,
+        gstin: Option<&str>
     ) -> Result<Admin, sqlx::Error> {
+        let mut tx = pool.begin().await?;
+
         let admin_record = sqlx::query_as::<_, Admin>(
-            "INSERT INTO admins (regcode, user_name, mobile, email, pincode) 
-             VALUES ($1, $2, $3, $4, $5) 
-             RETURNING id, regcode, user_name, mobile, email, pincode"
+            "INSERT INTO admins (regcode, user_name, mobile, email, pincode, gstin) 
+             VALUES ($1, $2, $3, $4, $5, $6) 
+             RETURNING id, regcode, user_name, mobile, email, pincode, gstin"
         )
         .bind(code)
         .bind(user_name)
         .bind(mobile)
         .bind(email)
         .bind(pincode)
-        .fetch_one(pool)
+        .bind(gstin)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        OutboxEvent::record(&mut tx, &AdminCreated {
+            admin_id: admin_record.id,
+            regcode: admin_record.regcode.clone(),
+            user_name: admin_record.user_name.clone(),
+            email: admin_record.email.clone(),
+            pincode: admin_record.pincode.clone(),
+        })
         .await?;
+
+        tx.commit().await?;
     
         Ok(admin_record)
     }
@@ -215,10 +274,12 @@ impl Otp {
         email: &str,
         pincode: &str
     ) -> Result<Admin_Users, sqlx::Error> {
+        let mut tx = pool.begin().await?;
+
         let admin_record = sqlx::query_as::<_, Admin_Users>(
             "INSERT INTO admins_users (regcode, admin_id, user_name, mobile, email, pincode) 
              VALUES ($1, $2, $3, $4, $5, $6) 
-             RETURNING id, regcode, user_name, mobile, email, pincode"
+             RETURNING id, admin_id, regcode, user_name, mobile, email, pincode"
         )
         .bind(code)
         .bind(admin_id)
@@ -226,8 +287,19 @@ impl Otp {
         .bind(mobile)
         .bind(email)
         .bind(pincode)
-        .fetch_one(pool)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        OutboxEvent::record(&mut tx, &AdminUserCreated {
+            admin_user_id: admin_record.id,
+            admin_id: admin_record.admin_id,
+            regcode: admin_record.regcode.clone(),
+            user_name: admin_record.user_name.clone(),
+            email: admin_record.email.clone(),
+        })
         .await?;
+
+        tx.commit().await?;
     
         Ok(admin_record)
     }
@@ -0,0 +1,336 @@
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+use std::time::Duration;
+
+use amqprs::callbacks::ChannelCallback;
+use amqprs::channel::{BasicPublishArguments, Channel, ConfirmSelectArguments, ExchangeDeclareArguments};
+use amqprs::connection::{Connection, OpenConnectionArguments};
+use amqprs::{Ack, BasicProperties, Cancel, CloseChannel, FieldTable, Nack, Return};
+use async_trait::async_trait;
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
+use tracing::warn;
+
+use crate::db::outbox::OutboxEvent;
+
+const CONFIRM_TIMEOUT: Duration = Duration::from_secs(10);
+
+// A fact about something that happened, published as `<type>` with a version so
+// consumers can evolve independently. Payloads only ever gain fields within a version.
+pub trait DomainEvent: Serialize + Sync {
+    const TYPE: &'static str;
+    const VERSION: i32 = 1;
+    const AGGREGATE: &'static str;
+
+    fn aggregate_id(&self) -> i64;
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct UserRegistered {
+    pub user_id: i32,
+    pub username: String,
+    pub email: String,
+    pub mobile: String,
+}
+
+impl DomainEvent for UserRegistered {
+    const TYPE: &'static str = "user.registered";
+    const AGGREGATE: &'static str = "user";
+
+    fn aggregate_id(&self) -> i64 {
+        i64::from(self.user_id)
+    }
+}
+
+// The code itself is never part of the event
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct OtpIssued {
+    pub otp_id: i32,
+    pub email: String,
+    pub mobile: String,
+}
+
+impl DomainEvent for OtpIssued {
+    const TYPE: &'static str = "otp.issued";
+    const AGGREGATE: &'static str = "otp";
+
+    fn aggregate_id(&self) -> i64 {
+        i64::from(self.otp_id)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AdminCreated {
+    pub admin_id: i32,
+    pub regcode: String,
+    pub user_name: String,
+    pub email: String,
+    pub pincode: String,
+}
+
+impl DomainEvent for AdminCreated {
+    const TYPE: &'static str = "admin.created";
+    const AGGREGATE: &'static str = "admin";
+
+    fn aggregate_id(&self) -> i64 {
+        i64::from(self.admin_id)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AdminUserCreated {
+    pub admin_user_id: i32,
+    pub admin_id: i32,
+    pub regcode: String,
+    pub user_name: String,
+    pub email: String,
+}
+
+impl DomainEvent for AdminUserCreated {
+    const TYPE: &'static str = "admin_user.created";
+    const AGGREGATE: &'static str = "admin_user";
+
+    fn aggregate_id(&self) -> i64 {
+        i64::from(self.admin_user_id)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct OrderPlaced {
+    pub order_id: i32,
+    pub admin_id: i32,
+    pub customer_email: String,
+    pub grand_total_paise: i64,
+    pub payment_due_at: NaiveDateTime,
+}
+
+impl DomainEvent for OrderPlaced {
+    const TYPE: &'static str = "order.placed";
+    const AGGREGATE: &'static str = "order";
+
+    fn aggregate_id(&self) -> i64 {
+        i64::from(self.order_id)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct OrderStatusChanged {
+    pub order_id: i32,
+    pub admin_id: i32,
+    pub customer_email: String,
+    pub status: String,
+}
+
+impl DomainEvent for OrderStatusChanged {
+    const TYPE: &'static str = "order.status_changed";
+    const AGGREGATE: &'static str = "order";
+
+    fn aggregate_id(&self) -> i64 {
+        i64::from(self.order_id)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct StockMoved {
+    pub movement_id: i64,
+    pub product_id: i32,
+    pub warehouse_id: i32,
+    pub movement_type: String,
+    pub on_hand_delta: i32,
+    pub reserved_delta: i32,
+    pub reference: Option<String>,
+}
+
+impl DomainEvent for StockMoved {
+    const TYPE: &'static str = "stock.moved";
+    const AGGREGATE: &'static str = "product";
+
+    fn aggregate_id(&self) -> i64 {
+        i64::from(self.product_id)
+    }
+}
+
+// What goes on the wire; `id` is the outbox row id, stable across redeliveries
+#[derive(Serialize)]
+struct Envelope<'a> {
+    id: i64,
+    #[serde(rename = "type")]
+    event_type: &'a str,
+    version: i32,
+    aggregate_type: &'a str,
+    aggregate_id: i64,
+    occurred_at: NaiveDateTime,
+    data: &'a serde_json::Value,
+}
+
+// An event as a consumer receives it, with the payload decoded into its typed form.
+// Envelope fields a consumer doesn't need are ignored.
+#[derive(Deserialize, Debug, Clone)]
+pub struct ReceivedEvent<T> {
+    pub id: i64,
+    #[serde(rename = "type")]
+    pub event_type: String,
+    pub data: T,
+}
+
+#[derive(Debug)]
+pub enum PublishError {
+    Amqp(amqprs::error::Error),
+    Nacked(u64),
+    ConfirmTimeout,
+    ChannelClosed,
+}
+
+impl From<amqprs::error::Error> for PublishError {
+    fn from(e: amqprs::error::Error) -> Self {
+        PublishError::Amqp(e)
+    }
+}
+
+impl fmt::Display for PublishError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PublishError::Amqp(e) => write!(f, "AMQP error: {}", e),
+            PublishError::Nacked(tag) => write!(f, "Broker rejected message {}", tag),
+            PublishError::ConfirmTimeout => write!(f, "Timed out waiting for publisher confirms"),
+            PublishError::ChannelClosed => write!(f, "Channel closed while waiting for confirms"),
+        }
+    }
+}
+
+enum Confirm {
+    Ack { tag: u64, multiple: bool },
+    Nack { tag: u64 },
+}
+
+// Forwards publisher confirms from the channel's callback to the publisher
+struct ConfirmForwarder(mpsc::UnboundedSender<Confirm>);
+
+#[async_trait]
+impl ChannelCallback for ConfirmForwarder {
+    async fn close(&mut self, _channel: &Channel, close: CloseChannel) -> Result<(), amqprs::error::Error> {
+        warn!("AMQP channel closed by broker: {}", close);
+        Ok(())
+    }
+
+    async fn cancel(&mut self, _channel: &Channel, _cancel: Cancel) -> Result<(), amqprs::error::Error> {
+        Ok(())
+    }
+
+    async fn flow(&mut self, _channel: &Channel, _active: bool) -> Result<bool, amqprs::error::Error> {
+        Ok(true)
+    }
+
+    async fn publish_ack(&mut self, _channel: &Channel, ack: Ack) {
+        let _ = self.0.send(Confirm::Ack { tag: ack.delivery_tag(), multiple: ack.mutiple() });
+    }
+
+    async fn publish_nack(&mut self, _channel: &Channel, nack: Nack) {
+        let _ = self.0.send(Confirm::Nack { tag: nack.delivery_tag() });
+    }
+
+    async fn publish_return(&mut self, _channel: &Channel, ret: Return, _properties: BasicProperties, _content: Vec<u8>) {
+        warn!("AMQP message returned as unroutable: {}", ret);
+    }
+}
+
+// Consumers continue the trace of the request that recorded the event
+pub fn trace_headers(trace_context: &HashMap<String, String>) -> FieldTable {
+    let mut headers = FieldTable::new();
+    for (key, value) in trace_context {
+        if let Ok(name) = key.as_str().try_into() {
+            headers.insert(name, value.as_str().into());
+        }
+    }
+    headers
+}
+
+// Publishes outbox rows to a durable topic exchange with publisher confirms enabled,
+// routing each event by its type (e.g. `order.status_changed`).
+pub struct EventPublisher {
+    connection: Connection,
+    channel: Channel,
+    exchange: String,
+    confirms: mpsc::UnboundedReceiver<Confirm>,
+    next_tag: u64,
+}
+
+impl EventPublisher {
+    pub async fn connect(url: &str, exchange: &str) -> Result<Self, PublishError> {
+        let connection = Connection::open(&OpenConnectionArguments::try_from(url)?).await?;
+        let channel = connection.open_channel(None).await?;
+
+        let (sender, confirms) = mpsc::unbounded_channel();
+        channel.register_callback(ConfirmForwarder(sender)).await?;
+        channel.confirm_select(ConfirmSelectArguments::default()).await?;
+        channel
+            .exchange_declare(ExchangeDeclareArguments::new(exchange, "topic").durable(true).finish())
+            .await?;
+
+        Ok(Self {
+            connection,
+            channel,
+            exchange: exchange.to_string(),
+            confirms,
+            next_tag: 1,
+        })
+    }
+
+    // Publishes the batch and waits until the broker has confirmed every message
+    pub async fn publish(&mut self, events: &[OutboxEvent]) -> Result<(), PublishError> {
+        let mut pending = BTreeSet::new();
+
+        for event in events {
+            let body = serde_json::to_vec(&Envelope {
+                id: event.id,
+                event_type: &event.event_type,
+                version: event.event_version,
+                aggregate_type: &event.aggregate_type,
+                aggregate_id: event.aggregate_id,
+                occurred_at: event.created_at,
+                data: &event.payload.0,
+            })
+            .expect("JSON values always serialize");
+
+            let properties = BasicProperties::default()
+                .with_headers(trace_headers(&event.trace_context))
+                .with_message_id(&event.id.to_string())
+                .with_message_type(&event.event_type)
+                .with_content_type("application/json")
+                .with_persistence(true)
+                .finish();
+            let args = BasicPublishArguments::new(&self.exchange, &event.event_type);
+
+            self.channel.basic_publish(properties, body, args).await?;
+            pending.insert(self.next_tag);
+            self.next_tag += 1;
+        }
+
+        while !pending.is_empty() {
+            let confirm = tokio::time::timeout(CONFIRM_TIMEOUT, self.confirms.recv())
+                .await
+                .map_err(|_| PublishError::ConfirmTimeout)?
+                .ok_or(PublishError::ChannelClosed)?;
+            match confirm {
+                Confirm::Ack { tag, multiple: true } => pending = pending.split_off(&(tag + 1)),
+                Confirm::Ack { tag, multiple: false } => {
+                    pending.remove(&tag);
+                }
+                Confirm::Nack { tag } => return Err(PublishError::Nacked(tag)),
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.channel.is_open()
+    }
+
+    // Every batch has been confirmed by the time `publish` returns, so nothing is lost here
+    pub async fn close(self) {
+        let _ = self.channel.close().await;
+        let _ = self.connection.close().await;
+    }
+}
@@ -0,0 +1,159 @@
+use chrono::{Datelike, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+
+// GST rates are basis points like the pricing engine: 1800 = 18%
+pub const VALID_RATES_BP: [i32; 7] = [0, 25, 300, 500, 1200, 1800, 2800];
+
+pub const SUPPLY_INTRA_STATE: &str = "intra_state";
+pub const SUPPLY_INTER_STATE: &str = "inter_state";
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GstState {
+    pub code: &'static str,
+    pub name: &'static str,
+}
+
+const fn state(code: &'static str, name: &'static str) -> GstState {
+    GstState { code, name }
+}
+
+// First three pincode digits (inclusive ranges) to the GST state code.
+// Specific ranges come before the broad postal-circle ranges they sit in.
+const PINCODE_PREFIXES: &[(u16, u16, GstState)] = &[
+    (110, 110, state("07", "Delhi")),
+    (160, 160, state("04", "Chandigarh")),
+    (121, 136, state("06", "Haryana")),
+    (140, 159, state("03", "Punjab")),
+    (171, 177, state("02", "Himachal Pradesh")),
+    (194, 194, state("38", "Ladakh")),
+    (180, 193, state("01", "Jammu and Kashmir")),
+    (246, 249, state("05", "Uttarakhand")),
+    (262, 263, state("05", "Uttarakhand")),
+    (201, 285, state("09", "Uttar Pradesh")),
+    (301, 345, state("08", "Rajasthan")),
+    (396, 396, state("26", "Dadra and Nagar Haveli and Daman and Diu")),
+    (360, 396, state("24", "Gujarat")),
+    (403, 403, state("30", "Goa")),
+    (400, 445, state("27", "Maharashtra")),
+    (450, 488, state("23", "Madhya Pradesh")),
+    (490, 497, state("22", "Chhattisgarh")),
+    (500, 509, state("36", "Telangana")),
+    (515, 535, state("37", "Andhra Pradesh")),
+    (560, 591, state("29", "Karnataka")),
+    (605, 605, state("34", "Puducherry")),
+    (600, 643, state("33", "Tamil Nadu")),
+    (670, 695, state("32", "Kerala")),
+    (737, 737, state("11", "Sikkim")),
+    (744, 744, state("35", "Andaman and Nicobar Islands")),
+    (700, 743, state("19", "West Bengal")),
+    (751, 770, state("21", "Odisha")),
+    (781, 788, state("18", "Assam")),
+    (790, 792, state("12", "Arunachal Pradesh")),
+    (793, 794, state("17", "Meghalaya")),
+    (795, 795, state("14", "Manipur")),
+    (796, 796, state("15", "Mizoram")),
+    (797, 798, state("13", "Nagaland")),
+    (799, 799, state("16", "Tripura")),
+    (813, 816, state("20", "Jharkhand")),
+    (822, 835, state("20", "Jharkhand")),
+    (800, 855, state("10", "Bihar")),
+];
+
+pub fn state_for_pincode(pincode: &str) -> Option<GstState> {
+    let pincode = pincode.trim();
+    if pincode.len() != 6 || !pincode.bytes().all(|b| b.is_ascii_digit()) || pincode.starts_with('0') {
+        return None;
+    }
+    let prefix: u16 = pincode[0..3].parse().ok()?;
+    PINCODE_PREFIXES
+        .iter()
+        .find(|(from, to, _)| (*from..=*to).contains(&prefix))
+        .map(|(_, _, state)| *state)
+}
+
+pub fn state_name(code: &str) -> Option<&'static str> {
+    PINCODE_PREFIXES
+        .iter()
+        .find(|(_, _, state)| state.code == code)
+        .map(|(_, _, state)| state.name)
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct LineTax {
+    pub cgst_paise: i64,
+    pub sgst_paise: i64,
+    pub igst_paise: i64,
+}
+
+impl LineTax {
+    pub fn total(&self) -> i64 {
+        self.cgst_paise + self.sgst_paise + self.igst_paise
+    }
+}
+
+// Same half-up rounding as the pricing engine; `divisor` 2 gives half the rate
+fn rate_of(amount: i64, rate_bp: i32, divisor: i64) -> i64 {
+    let scale = 10_000 * divisor;
+    (amount * i64::from(rate_bp) + scale / 2) / scale
+}
+
+// Within a state the rate is split equally into CGST and SGST; across states it is all IGST
+pub fn compute_line_tax(taxable_paise: i64, rate_bp: i32, intra_state: bool) -> LineTax {
+    if intra_state {
+        let half = rate_of(taxable_paise, rate_bp, 2);
+        LineTax { cgst_paise: half, sgst_paise: half, igst_paise: 0 }
+    } else {
+        LineTax { cgst_paise: 0, sgst_paise: 0, igst_paise: rate_of(taxable_paise, rate_bp, 1) }
+    }
+}
+
+// Indian financial years run April to March, e.g. "2026-27"
+pub fn financial_year(at: NaiveDateTime) -> String {
+    let start = if at.month() >= 4 { at.year() } else { at.year() - 1 };
+    format!("{}-{:02}", start, (start + 1) % 100)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::*;
+
+    #[test]
+    fn maps_pincodes_to_states() {
+        assert_eq!(state_for_pincode("560001").map(|s| s.code), Some("29"));
+        assert_eq!(state_for_pincode(" 110001 ").map(|s| s.code), Some("07"));
+        // Carved out of the surrounding Maharashtra and Gujarat ranges
+        assert_eq!(state_for_pincode("403001").map(|s| s.code), Some("30"));
+        assert_eq!(state_for_pincode("396210").map(|s| s.code), Some("26"));
+        assert_eq!(state_for_pincode("400001").map(|s| s.code), Some("27"));
+        assert_eq!(state_for_pincode("012345"), None);
+        assert_eq!(state_for_pincode("56001"), None);
+        assert_eq!(state_for_pincode("5600a1"), None);
+        assert_eq!(state_for_pincode("999999"), None);
+    }
+
+    #[test]
+    fn splits_tax_by_supply_type() {
+        let intra = compute_line_tax(10_000, 1800, true);
+        assert_eq!(intra, LineTax { cgst_paise: 900, sgst_paise: 900, igst_paise: 0 });
+        let inter = compute_line_tax(10_000, 1800, false);
+        assert_eq!(inter, LineTax { cgst_paise: 0, sgst_paise: 0, igst_paise: 1800 });
+
+        // 5% of 1010 is 50.5: each 2.5% half is 25.25 and rounds down, IGST rounds half up
+        assert_eq!(compute_line_tax(1010, 500, true).total(), 50);
+        assert_eq!(compute_line_tax(1010, 500, false).total(), 51);
+        // 18% of 25 is 4.5 and rounds up; each 9% half is 2.25 and rounds down
+        assert_eq!(compute_line_tax(25, 1800, false).igst_paise, 5);
+        assert_eq!(compute_line_tax(25, 1800, true), LineTax { cgst_paise: 2, sgst_paise: 2, igst_paise: 0 });
+        assert_eq!(compute_line_tax(10_000, 0, true).total(), 0);
+    }
+
+    #[test]
+    fn financial_year_starts_in_april() {
+        let at = |y, m, d, h| NaiveDate::from_ymd_opt(y, m, d).unwrap().and_hms_opt(h, 59, 59).unwrap();
+        assert_eq!(financial_year(at(2027, 3, 31, 23)), "2026-27");
+        assert_eq!(financial_year(at(2027, 4, 1, 0)), "2027-28");
+        assert_eq!(financial_year(at(2100, 1, 15, 12)), "2099-00");
+    }
+}
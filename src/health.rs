@@ -0,0 +1,198 @@
+use std::fmt;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use std::sync::Arc;
+
+use amqprs::connection::{Connection, OpenConnectionArguments};
+use axum::{extract::State, http::StatusCode, response::Json};
+use futures_util::future::join_all;
+use serde::Serialize;
+use serde_json::json;
+use tokio::sync::Mutex;
+use tracing::warn;
+
+use crate::mailer;
+use crate::AppState;
+
+const UP: &str = "up";
+const DOWN: &str = "down";
+const NOT_CONFIGURED: &str = "not_configured";
+
+// What readiness can depend on. Which of them are critical is configured with
+// `health.critical`; a failing non-critical dependency is reported but keeps the instance ready.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dependency {
+    Postgres,
+    Redis,
+    RabbitMq,
+    Smtp,
+}
+
+impl Dependency {
+    pub const ALL: [Dependency; 4] = [Dependency::Postgres, Dependency::Redis, Dependency::RabbitMq, Dependency::Smtp];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Dependency::Postgres => "postgres",
+            Dependency::Redis => "redis",
+            Dependency::RabbitMq => "rabbitmq",
+            Dependency::Smtp => "smtp",
+        }
+    }
+}
+
+impl fmt::Display for Dependency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Dependency {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Dependency::ALL
+            .into_iter()
+            .find(|d| d.name() == s)
+            .ok_or_else(|| format!("unknown dependency {:?}, expected postgres, redis, rabbitmq or smtp", s))
+    }
+}
+
+#[derive(Serialize, Clone)]
+struct Check {
+    name: &'static str,
+    status: &'static str,
+    critical: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    latency_ms: Option<f64>,
+}
+
+// The last round of readiness checks. The lock is held while probing, so concurrent
+// requests wait for that round instead of each starting their own.
+#[derive(Clone, Default)]
+pub struct ReadinessCache(Arc<Mutex<Option<Readiness>>>);
+
+struct Readiness {
+    checked_at: Instant,
+    checks: Vec<Check>,
+}
+
+// None when the dependency isn't configured. Errors are only for the log.
+async fn probe(state: &AppState, dependency: Dependency, timeout: Duration) -> Option<Result<(), String>> {
+    let config = &state.config;
+    match dependency {
+        // Started degraded and still waiting for the database or its migrations
+        Dependency::Postgres if !state.db_ready.is_ready() => Some(Err("not connected yet".to_string())),
+        Dependency::Postgres => Some(
+            sqlx::query("SELECT 1")
+                .execute(&state.pool)
+                .await
+                .map(|_| ())
+                .map_err(|e| e.to_string()),
+        ),
+        Dependency::Redis => {
+            let url = config.redis_url.as_ref()?;
+            Some(ping_redis(url.expose()).await.map_err(|e| e.to_string()))
+        }
+        Dependency::RabbitMq => {
+            let url = config.amqp_url.as_ref()?;
+            Some(open_amqp(url.expose()).await.map_err(|e| e.to_string()))
+        }
+        Dependency::Smtp => {
+            let config = state.config.clone();
+            Some(match tokio::task::spawn_blocking(move || mailer::check_connection(&config, timeout)).await {
+                Ok(result) => result.map_err(|e| format!("{}: {}", e, e.detail())),
+                Err(e) => Err(format!("SMTP check task failed: {:?}", e)),
+            })
+        }
+    }
+}
+
+async fn ping_redis(url: &str) -> Result<(), redis::RedisError> {
+    let mut connection = redis::Client::open(url)?.get_async_connection().await?;
+    redis::cmd("PING").query_async::<_, String>(&mut connection).await?;
+    Ok(())
+}
+
+async fn open_amqp(url: &str) -> Result<(), amqprs::error::Error> {
+    let connection = Connection::open(&OpenConnectionArguments::try_from(url)?).await?;
+    connection.close().await
+}
+
+async fn run_check(state: &AppState, dependency: Dependency, timeout: Duration) -> Check {
+    let critical = state.config.health_critical.contains(&dependency);
+    let started = Instant::now();
+    let outcome = tokio::time::timeout(timeout, probe(state, dependency, timeout)).await;
+    let latency_ms = Some(started.elapsed().as_secs_f64() * 1000.0);
+
+    let status = match outcome {
+        Ok(None) => return Check { name: dependency.name(), status: NOT_CONFIGURED, critical, latency_ms: None },
+        Ok(Some(Ok(()))) => UP,
+        Ok(Some(Err(e))) => {
+            warn!(dependency = dependency.name(), critical, error = %e, "Readiness check failed");
+            DOWN
+        }
+        Err(_) => {
+            warn!(dependency = dependency.name(), critical, timeout_ms = timeout.as_millis() as u64, "Readiness check timed out");
+            DOWN
+        }
+    };
+    Check { name: dependency.name(), status, critical, latency_ms }
+}
+
+// The process is up and serving; says nothing about its dependencies, so a broken
+// database never gets the instance restarted
+#[utoipa::path(
+    get,
+    path = "/livez",
+    tag = "health",
+    security(()),
+    responses(
+        (status = 200, description = "Process is alive"),
+    )
+)]
+pub async fn livez() -> Json<serde_json::Value> {
+    Json(json!({"status": "ok"}))
+}
+
+// Whether this instance should receive traffic. Every dependency is checked concurrently
+// and reported with its latency; only critical ones decide the status code.
+#[utoipa::path(
+    get,
+    path = "/readyz",
+    tag = "health",
+    security(()),
+    responses(
+        (status = 200, description = "Every critical dependency is up; `checks` lists each dependency's status and latency"),
+        (status = 503, description = "A critical dependency is down, or the instance is shutting down"),
+    )
+)]
+pub async fn readyz(State(state): State<AppState>) -> (StatusCode, Json<serde_json::Value>) {
+    // Fail readiness first so load balancers stop routing here while requests drain
+    if state.shutdown.is_requested() {
+        return (StatusCode::SERVICE_UNAVAILABLE, Json(json!({"status": "shutting_down"})));
+    }
+
+    let checks = {
+        let mut cached = state.readiness.0.lock().await;
+        let max_age = Duration::from_millis(state.config.health_cache_ms);
+        match cached.as_ref() {
+            Some(last) if last.checked_at.elapsed() < max_age => last.checks.clone(),
+            _ => {
+                let timeout = Duration::from_millis(state.config.health_timeout_ms);
+                let checks = join_all(Dependency::ALL.map(|dependency| run_check(&state, dependency, timeout))).await;
+                *cached = Some(Readiness { checked_at: Instant::now(), checks: checks.clone() });
+                checks
+            }
+        }
+    };
+    let ready = checks.iter().all(|check| !check.critical || check.status == UP);
+
+    let (code, status) = if ready {
+        (StatusCode::OK, "ready")
+    } else {
+        (StatusCode::SERVICE_UNAVAILABLE, "not_ready")
+    };
+    (code, Json(json!({"status": status, "checks": checks})))
+}
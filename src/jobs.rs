@@ -0,0 +1,119 @@
+use sqlx::PgPool;
+use std::time::Duration;
+use tracing::{error, info, warn};
+
+use crate::db::orders::Order;
+use crate::db::outbox::OutboxEvent;
+use crate::events::{EventPublisher, PublishError};
+use crate::shutdown::Shutdown;
+
+const EXPIRY_BATCH_SIZE: i64 = 100;
+const OUTBOX_BATCH_SIZE: i64 = 100;
+
+// Periodically releases reservations of orders that were not paid in time
+pub async fn run_reservation_expiry(pool: PgPool, every: Duration, shutdown: Shutdown) {
+    let mut interval = tokio::time::interval(every);
+    loop {
+        tokio::select! {
+            _ = interval.tick() => {}
+            _ = shutdown.requested() => return,
+        }
+        match Order::expire_overdue(&pool, EXPIRY_BATCH_SIZE).await {
+            Ok(expired) if !expired.is_empty() => {
+                let ids: Vec<i32> = expired.iter().map(|order| order.id).collect();
+                info!(orders = ?ids, "Released stock for expired orders");
+                for order in expired.iter().filter(|order| order.refund_due_paise() > 0) {
+                    warn!(order_id = order.id, refund_due_paise = order.refund_due_paise(), "Expired order was part paid");
+                }
+            }
+            Ok(_) => {}
+            Err(e) => error!("Error expiring overdue orders: {:?}", e),
+        }
+    }
+}
+
+enum RelayError {
+    Db(sqlx::Error),
+    Publish(PublishError),
+}
+
+// Publishes one batch of the outbox. The rows stay locked until the broker has
+// confirmed them, so a crash in between just means they are sent again.
+async fn relay_batch(pool: &PgPool, publisher: &mut EventPublisher) -> Result<usize, RelayError> {
+    let mut tx = pool.begin().await.map_err(RelayError::Db)?;
+
+    let events = OutboxEvent::lock_unpublished(&mut tx, OUTBOX_BATCH_SIZE)
+        .await
+        .map_err(RelayError::Db)?;
+    if events.is_empty() {
+        return Ok(0);
+    }
+    let ids: Vec<i64> = events.iter().map(|e| e.id).collect();
+
+    match publisher.publish(&events).await {
+        Ok(()) => OutboxEvent::mark_published(&mut tx, &ids).await.map_err(RelayError::Db)?,
+        Err(e) => {
+            OutboxEvent::mark_failed(&mut tx, &ids, &e.to_string())
+                .await
+                .map_err(RelayError::Db)?;
+            tx.commit().await.map_err(RelayError::Db)?;
+            return Err(RelayError::Publish(e));
+        }
+    }
+
+    tx.commit().await.map_err(RelayError::Db)?;
+
+    Ok(events.len())
+}
+
+// Drains the outbox to RabbitMQ, reconnecting whenever publishing fails.
+// Delivery is at-least-once: consumers should de-duplicate on the event id.
+// Keeps polling while the servers drain, then makes one last pass once they have stopped
+// so events committed by the last requests go out too.
+pub async fn run_outbox_relay(pool: PgPool, amqp_url: String, exchange: String, every: Duration, shutdown: Shutdown) {
+    let mut interval = tokio::time::interval(every);
+    let mut publisher: Option<EventPublisher> = None;
+    loop {
+        let last_pass = tokio::select! {
+            _ = interval.tick() => false,
+            _ = shutdown.drained() => true,
+        };
+
+        if publisher.as_ref().is_none_or(|p| !p.is_open()) {
+            match EventPublisher::connect(&amqp_url, &exchange).await {
+                Ok(connected) => publisher = Some(connected),
+                Err(e) => error!("Error connecting to RabbitMQ: {}", e),
+            }
+        }
+        if let Some(active) = publisher.as_mut() {
+            if let Err(e) = drain_outbox(&pool, active).await {
+                error!("Error publishing events: {}", e);
+                publisher = None;
+            }
+        }
+
+        if last_pass {
+            if let Some(publisher) = publisher {
+                publisher.close().await;
+            }
+            info!("Outbox relay stopped");
+            return;
+        }
+    }
+}
+
+// Keeps going while full batches come back so a backlog drains quickly. Only a publish
+// error is returned, since it means the connection has to be replaced.
+async fn drain_outbox(pool: &PgPool, publisher: &mut EventPublisher) -> Result<(), PublishError> {
+    loop {
+        match relay_batch(pool, publisher).await {
+            Ok(sent) if sent as i64 == OUTBOX_BATCH_SIZE => {}
+            Ok(_) => return Ok(()),
+            Err(RelayError::Db(e)) => {
+                error!("Error reading outbox: {:?}", e);
+                return Ok(());
+            }
+            Err(RelayError::Publish(e)) => return Err(e),
+        }
+    }
+}
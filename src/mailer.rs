@@ -0,0 +1,111 @@
+use lettre::message::header::ContentType;
+use lettre::message::{Attachment, MultiPart, SinglePart};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{Message, SmtpTransport, Transport};
+use std::fmt;
+use std::time::Duration;
+use tracing::instrument;
+
+use crate::config::Config;
+
+pub struct EmailAttachment {
+    pub filename: String,
+    pub content_type: String,
+    pub bytes: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub enum MailError {
+    NotConfigured,
+    Message(String),
+    Transport(String),
+    Send(String),
+}
+
+impl MailError {
+    // Underlying lettre error, for logs only
+    pub fn detail(&self) -> &str {
+        match self {
+            MailError::NotConfigured => "smtp.user and smtp.pass (EMAIL_USER, EMAIL_PASS) must be set",
+            MailError::Message(detail) | MailError::Transport(detail) | MailError::Send(detail) => detail,
+        }
+    }
+}
+
+impl fmt::Display for MailError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MailError::NotConfigured => write!(f, "Email is not configured"),
+            MailError::Message(_) => write!(f, "Failed to create email message"),
+            MailError::Transport(_) => write!(f, "Failed to create email transport"),
+            MailError::Send(_) => write!(f, "Email send error"),
+        }
+    }
+}
+
+// Sends a plain-text email through the configured SMTP relay, optionally with attachments.
+// This is blocking; async callers that can't afford the wait should use spawn_blocking,
+// entering their span inside the closure so the SMTP span stays in the caller's trace.
+#[instrument(
+    name = "smtp send",
+    skip_all,
+    fields(otel.kind = "client", server.address = %config.smtp_host, attachments = attachments.len())
+)]
+pub fn send_email(
+    config: &Config,
+    from_name: &str,
+    to: &str,
+    subject: &str,
+    body: String,
+    attachments: Vec<EmailAttachment>,
+) -> Result<(), MailError> {
+    let (Some(smtp_user), Some(smtp_pass)) = (&config.smtp_user, &config.smtp_pass) else {
+        return Err(MailError::NotConfigured);
+    };
+
+    let from = format!("{} <{}>", from_name, smtp_user)
+        .parse()
+        .map_err(|e| MailError::Message(format!("{:?}", e)))?;
+    let to = to.parse().map_err(|e| MailError::Message(format!("{:?}", e)))?;
+    let builder = Message::builder().from(from).to(to).subject(subject);
+
+    let email_message = if attachments.is_empty() {
+        builder.body(body)
+    } else {
+        let mut parts = MultiPart::mixed().singlepart(SinglePart::plain(body));
+        for attachment in attachments {
+            let content_type = ContentType::parse(&attachment.content_type)
+                .map_err(|e| MailError::Message(format!("{:?}", e)))?;
+            parts = parts.singlepart(Attachment::new(attachment.filename).body(attachment.bytes, content_type));
+        }
+        builder.multipart(parts)
+    }
+    .map_err(|e| MailError::Message(format!("{:?}", e)))?;
+
+    let creds = Credentials::new(smtp_user.clone(), smtp_pass.expose().to_string());
+    let mailer = SmtpTransport::relay(&config.smtp_host)
+        .map_err(|e| MailError::Transport(format!("{:?}", e)))?
+        .credentials(creds)
+        .build();
+
+    mailer
+        .send(&email_message)
+        .map(|_| ())
+        .map_err(|e| MailError::Send(format!("{:?}", e)))
+}
+
+// Connects to the relay and says hello, without sending anything or logging in. The
+// timeout bounds each network operation, so a hung relay doesn't hold a blocking thread.
+pub fn check_connection(config: &Config, timeout: Duration) -> Result<(), MailError> {
+    let reachable = SmtpTransport::relay(&config.smtp_host)
+        .map_err(|e| MailError::Transport(format!("{:?}", e)))?
+        .timeout(Some(timeout))
+        .build()
+        .test_connection()
+        .map_err(|e| MailError::Send(format!("{:?}", e)))?;
+    if reachable {
+        Ok(())
+    } else {
+        Err(MailError::Send("relay did not answer NOOP".to_string()))
+    }
+}
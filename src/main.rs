@@ -1,104 +1,463 @@
 mod api;
 mod config;
 mod db;
+mod events;
+mod gst;
+mod health;
+mod jobs;
+mod mailer;
+mod payments;
+mod metrics;
 mod middleware;
-use api::auth::create_admin_users;
+mod notifications;
+mod openapi;
+mod pricing;
+mod realtime;
+mod rendering;
+mod shutdown;
+mod sms;
+mod telemetry;
+mod tls;
+mod worker;
 use config::Config;
+use db::pool::DbReady;
+use health::ReadinessCache;
+use middleware::auth::JwtKeys;
+use middleware::logging::{capture_route, log_requests};
+use middleware::rate_limit::{rate_limit, BucketStore, MemoryStore, RateLimiter, RedisStore, Store as RateLimitStore};
+use middleware::security::{security_headers, SecurityHeaders};
+use notifications::OtpDelivery;
+use openapi::ApiDoc;
+use payments::{MockProvider, PaymentProviders};
+use realtime::{LowStockNotifier, NotificationHub, NotificationPublisher, OrderStatusNotifier};
+use shutdown::Shutdown;
+use worker::{Worker, WorkerSettings};
 
 use axum::{
-    extract::State,
+    extract::{DefaultBodyLimit, FromRef, State},
     http::StatusCode,
     response::Json,
-    routing::{get, post},
+    routing::get,
     Router,
 };
+use futures_util::future::try_join_all;
+use futures_util::FutureExt;
 use serde_json::json;
-use sqlx::postgres::PgPoolOptions;
 use sqlx::PgPool;
+use std::future::IntoFuture;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
 use tokio::net::TcpListener;
-
-// Explicitly import the handler functions 
-use crate::api::auth::{login, create_admin, register, verify_otp};
+use tower_http::timeout::TimeoutLayer;
+use utoipa::OpenApi;
+use utoipa_swagger_ui::SwaggerUi;
+use tracing::{error, info, warn};
 
 #[derive(Clone)]
 pub struct AppState {
     pub pool: PgPool,
+    pub db_ready: DbReady,
+    pub config: Arc<Config>,
+    pub payment_providers: Arc<PaymentProviders>,
+    pub notification_hub: Option<NotificationHub>,
+    pub jwt_keys: Option<JwtKeys>,
+    pub rate_limiter: Option<Arc<RateLimiter>>,
+    pub readiness: ReadinessCache,
+    pub shutdown: Shutdown,
+}
+
+impl FromRef<AppState> for PgPool {
+    fn from_ref(state: &AppState) -> Self {
+        state.pool.clone()
+    }
+}
+
+// For router tests. The pool never connects, so only requests turned away before a
+// handler touches the database can be exercised.
+#[cfg(test)]
+pub fn test_state(jwt_keys: Option<JwtKeys>) -> AppState {
+    use db::pool::{connect_degraded, connect_options};
+
+    let env = |name: &str| (name == "DATABASE_URL").then(|| "postgres://localhost/erp".to_string());
+    let config = Arc::new(Config::from_sources(None, &env, &[]).unwrap());
+    let pool = connect_degraded(&config, connect_options(&config).unwrap());
+    AppState {
+        pool,
+        db_ready: DbReady::default(),
+        config,
+        payment_providers: Arc::default(),
+        notification_hub: None,
+        jwt_keys,
+        rate_limiter: None,
+        readiness: ReadinessCache::default(),
+        shutdown: Shutdown::new(Duration::ZERO),
+    }
 }
 
 // Health check handler with concrete return type
+#[utoipa::path(
+    get,
+    path = "/health",
+    tag = "health",
+    security(()),
+    responses(
+        (status = 200, description = "Database reachable"),
+        (status = 500, description = "Database unreachable"),
+    )
+)]
 pub async fn check_database_connection(State(state): State<AppState>) -> (StatusCode, Json<serde_json::Value>) {
     match sqlx::query("SELECT 1").execute(&state.pool).await {
         Ok(_) => (
             StatusCode::OK,
             Json(json!({"message": "✅ Database connection successful"})),
         ),
-        Err(e) => (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(json!({"error": format!("Postgres error: {}", e)})),
-        ),
+        Err(e) => {
+            // The driver's message can name hosts and roles; keep it in the log
+            error!("Health check query failed: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({"error": "Database unavailable"})),
+            )
+        }
     }
 }
 
-// Startup DB check function (no Axum State)
-pub async fn check_database_connection_startup(pool: &PgPool) -> Result<(), sqlx::Error> {
-    sqlx::query("SELECT 1").execute(pool).await.map(|_| ())
-}
-
 #[tokio::main]
 async fn main() -> Result<(), std::io::Error> {
-    let config = Config::from_env();
-
-    let pool = match PgPoolOptions::new()
-        .max_connections(5)
-        .connect(&config.database_url)
-        .await
-    {
-        Ok(pool) => {
-            println!("✅ Connection successful to DB: {}", config.app_name);
-            pool
+    // `axum_project worker` runs the RabbitMQ consumers instead of the HTTP server;
+    // everything after the command is configuration flags
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+    let run_worker = args.first().map(String::as_str) == Some("worker");
+    if run_worker {
+        args.remove(0);
+    }
+
+    // Logging is configured from this, so problems can only go to stderr
+    let config = match Config::load(&args) {
+        Ok(config) => Arc::new(config),
+        Err(e) => {
+            eprint!("{}", e);
+            std::process::exit(2);
         }
+    };
+    let telemetry = telemetry::init(&config);
+
+    let shutdown = Shutdown::new(Duration::from_secs(config.shutdown_grace_secs));
+    shutdown.listen_for_signals();
+
+    let connect_options = match db::pool::connect_options(&config) {
+        Ok(options) => options,
         Err(e) => {
-            eprintln!("❌ Failed to connect to DB: {:?}", e);
+            error!("❌ Invalid DATABASE_URL: {}", e);
             std::process::exit(1);
         }
     };
 
-    // Use the startup DB check function here
-    if let Err(e) = check_database_connection_startup(&pool).await {
-        eprintln!("❌ Database connection failed during startup: {:?}", e);
-        return Err(std::io::Error::new(
-            std::io::ErrorKind::Other,
-            "Database connection failed",
+    let db_ready = DbReady::default();
+    let pool = match db::pool::connect(&config, connect_options.clone(), &shutdown).await {
+        Some(pool) => {
+            info!("✅ Connection successful to DB: {}", config.app_name);
+            if let Err(e) = db::pool::migrate(&pool).await {
+                error!("❌ Failed to run migrations: {:?}", e);
+                return Err(std::io::Error::other("Migrations failed"));
+            }
+            db_ready.mark_ready();
+            pool
+        }
+        None if shutdown.is_requested() => {
+            telemetry.shutdown();
+            return Ok(());
+        }
+        // The worker has nothing to do without the database, so only the server can start degraded
+        None if config.db_start_degraded && !run_worker => {
+            warn!("Starting without the database; /readyz reports not ready until it is reachable");
+            let pool = db::pool::connect_degraded(&config, connect_options);
+            shutdown.spawn(db::pool::recover(pool.clone(), db_ready.clone(), config.clone(), shutdown.clone()));
+            pool
+        }
+        None => std::process::exit(1),
+    };
+
+    if run_worker {
+        let Some(amqp_url) = config.amqp_url.as_ref().map(|url| url.expose().to_string()) else {
+            error!("❌ AMQP_URL must be set to run the worker");
+            return Err(std::io::Error::other("AMQP_URL not set"));
+        };
+        let mut worker = Worker::new(WorkerSettings {
+            amqp_url,
+            exchange: config.amqp_exchange.clone(),
+            prefetch: config.worker_prefetch,
+            max_attempts: config.worker_max_attempts,
+            retry_delay: Duration::from_secs(config.worker_retry_delay_secs),
+        });
+        worker.register(OtpDelivery { pool: pool.clone(), config: config.clone() });
+
+        match &config.redis_url {
+            Some(redis_url) => {
+                let publisher = match NotificationPublisher::connect(redis_url.expose()).await {
+                    Ok(publisher) => publisher,
+                    Err(e) => {
+                        error!("❌ Failed to connect to Redis: {}", e);
+                        return Err(std::io::Error::other("Redis connection failed"));
+                    }
+                };
+                worker.register(OrderStatusNotifier { publisher: publisher.clone() });
+                worker.register(LowStockNotifier { pool: pool.clone(), publisher });
+            }
+            None => warn!("REDIS_URL not set, realtime notifications will not be published"),
+        }
+
+        // The worker has no API, only a scrape endpoint for its own metrics
+        let metrics_app = Router::new()
+            .route("/metrics", get(metrics::metrics_handler))
+            .with_state(pool.clone());
+        let metrics_listener = TcpListener::bind(&config.worker_metrics_addr).await?;
+        let stopping = shutdown.requested();
+        tokio::spawn(async move {
+            if let Err(e) = axum::serve(metrics_listener, metrics_app).with_graceful_shutdown(stopping).await {
+                error!("Worker metrics server failed: {}", e);
+            }
+        });
+
+        info!("🚀 Worker started, metrics at http://{}/metrics", config.worker_metrics_addr);
+        tokio::select! {
+            _ = worker.run(shutdown.clone()) => {}
+            _ = shutdown.grace_expired() => warn!("Grace period over, abandoning in-flight messages"),
+        }
+        pool.close().await;
+        info!("Shutdown complete");
+        telemetry.shutdown();
+        return Ok(());
+    }
+
+    shutdown.spawn(jobs::run_reservation_expiry(
+        pool.clone(),
+        Duration::from_secs(config.reservation_sweep_secs),
+        shutdown.clone(),
+    ));
+
+    // Without a broker, events still accumulate in the outbox and go out once one is configured
+    match &config.amqp_url {
+        Some(amqp_url) => {
+            shutdown.spawn(jobs::run_outbox_relay(
+                pool.clone(),
+                amqp_url.expose().to_string(),
+                config.amqp_exchange.clone(),
+                Duration::from_secs(config.outbox_poll_secs),
+                shutdown.clone(),
+            ));
+        }
+        None => warn!("AMQP_URL not set, domain events will stay in the outbox"),
+    }
+
+    // Every instance subscribes, so an admin gets notifications whichever instance they connect to
+    let notification_hub = config.redis_url.as_ref().map(|redis_url| {
+        let hub = NotificationHub::new();
+        shutdown.spawn(realtime::run_redis_subscriber(
+            redis_url.expose().to_string(),
+            hub.clone(),
+            shutdown.clone(),
         ));
-    } else {
-        println!("✅ Database connected successfully at startup");
+        hub
+    });
+    if notification_hub.is_none() {
+        warn!("REDIS_URL not set, /ws/notifications is disabled");
     }
 
-    let app_state = AppState { pool };
+    let mut payment_providers = PaymentProviders::default();
+    if let Some(secret) = &config.mock_payment_secret {
+        payment_providers.register(Arc::new(MockProvider::new(secret.expose())));
+    }
 
-    let app = Router::new()
-        .route("/", get(root_handler))
+    let jwt_keys = match (&config.jwt_key_set, &config.jwt_secret) {
+        (Some(path), _) => match JwtKeys::load(path) {
+            Ok(keys) => {
+                shutdown.spawn(keys.clone().watch(
+                    path.clone(),
+                    Duration::from_secs(config.jwt_key_set_reload_secs),
+                    shutdown.clone(),
+                ));
+                Some(keys)
+            }
+            Err(e) => {
+                error!("❌ {}", e);
+                return Err(std::io::Error::other("JWT key set could not be loaded"));
+            }
+        },
+        (None, Some(secret)) => Some(JwtKeys::single(secret.clone())),
+        (None, None) => None,
+    };
+
+    let rate_limiter = if config.rate_limit_enabled {
+        let store: Box<dyn BucketStore> = match (config.rate_limit_store, &config.redis_url) {
+            (RateLimitStore::Redis, Some(redis_url)) => match RedisStore::connect(redis_url.expose()).await {
+                Ok(store) => Box::new(store),
+                Err(e) => {
+                    error!("❌ Failed to connect to Redis for rate limiting: {}", e);
+                    return Err(std::io::Error::other("Redis connection failed"));
+                }
+            },
+            _ => Box::new(MemoryStore::default()),
+        };
+        let rules = config.rate_limit_rules.to_vec();
+        Some(Arc::new(RateLimiter::new(rules, store, config.rate_limit_trust_forwarded_for)))
+    } else {
+        warn!("Rate limiting is disabled");
+        None
+    };
+
+    let app_state = AppState {
+        pool: pool.clone(),
+        db_ready,
+        config: config.clone(),
+        payment_providers: Arc::new(payment_providers),
+        notification_hub,
+        jwt_keys,
+        rate_limiter,
+        readiness: ReadinessCache::default(),
+        shutdown: shutdown.clone(),
+    };
+
+    // Probes and scraping. With an admin listener they are only served there, so they
+    // never reach the public internet.
+    let ops = Router::new()
         .route("/health", get(check_database_connection))
-        .route("/login", post(login))
-        .route("/verify", post(verify_otp))
-        .route("/add_user", post(register))
-        .route("/ceate_user",post(create_admin))
-        .route("/ceate_admin_user",post(create_admin_users))
-        .with_state(app_state);
+        .route("/livez", get(health::livez))
+        .route("/readyz", get(health::readyz))
+        .route("/metrics", get(metrics::metrics_handler));
+    let mut public = Router::new()
+        .route("/", get(root_handler))
+        .merge(api::router(&app_state))
+        .merge(SwaggerUi::new("/swagger-ui").url("/openapi.json", ApiDoc::openapi()));
+    let admin = match &config.admin_addr {
+        Some(_) => Some(
+            ops.route_layer(axum::middleware::from_fn(capture_route))
+                .with_state(app_state.clone())
+                .layer(axum::middleware::from_fn(metrics::track_requests))
+                .layer(axum::middleware::from_fn(log_requests)),
+        ),
+        None => {
+            public = public.merge(ops);
+            None
+        }
+    };
 
-    let listener = TcpListener::bind("localhost:3100")
-        .await
-        .expect("Failed to bind to address");
+    let app = public
+        .route_layer(axum::middleware::from_fn_with_state(app_state.clone(), rate_limit))
+        .route_layer(axum::middleware::from_fn(capture_route))
+        .with_state(app_state)
+        .layer(DefaultBodyLimit::max(config.max_body_bytes))
+        .layer(TimeoutLayer::new(Duration::from_secs(config.request_timeout_secs)))
+        .layer(axum::middleware::from_fn_with_state(SecurityHeaders::from_config(&config), security_headers))
+        .layer(middleware::security::cors(&config))
+        .layer(axum::middleware::from_fn(metrics::track_requests))
+        .layer(axum::middleware::from_fn(log_requests));
+    // Connect info gives the rate limiter the client's address
+    let app = app.into_make_service_with_connect_info::<SocketAddr>();
 
-    println!("🚀 Server running at http://localhost:3100");
+    let mut listeners = Vec::new();
+    for addr in config.bind_addrs.iter() {
+        match TcpListener::bind(addr).await {
+            Ok(listener) => listeners.push((addr, listener)),
+            Err(e) => {
+                error!("❌ Failed to bind to {}: {}", addr, e);
+                return Err(e);
+            }
+        }
+    }
 
-    axum::serve(listener, app)
-        .await
-        .expect("Server failed");
+    let tls = match (&config.tls_cert_path, &config.tls_key_path) {
+        (Some(cert_path), Some(key_path)) => {
+            let tls = match tls::load(cert_path, key_path).await {
+                Ok(tls) => tls,
+                Err(e) => {
+                    error!("❌ Failed to load TLS certificate: {}", e);
+                    return Err(std::io::Error::other("TLS certificate could not be loaded"));
+                }
+            };
+            shutdown.spawn(tls::watch(
+                tls.clone(),
+                cert_path.clone(),
+                key_path.clone(),
+                Duration::from_secs(config.tls_reload_secs),
+                shutdown.clone(),
+            ));
+            if let Some(redirect_addr) = &config.tls_redirect_addr {
+                let redirect_listener = TcpListener::bind(redirect_addr).await?;
+                let https_addr = listeners[0].1.local_addr()?;
+                shutdown.spawn(tls::run_redirect(redirect_listener, https_addr, shutdown.clone()));
+            }
+            Some(tls)
+        }
+        _ => None,
+    };
+
+    // Stop accepting on SIGTERM and let in-flight requests finish, then let background
+    // tasks wind down (the outbox relay flushes what those requests recorded once they're done)
+    let mut servers = Vec::new();
+    for (addr, listener) in listeners {
+        let server = match &tls {
+            Some(tls) => {
+                let handle = axum_server::Handle::new();
+                let stopping = shutdown.requested();
+                let draining = handle.clone();
+                tokio::spawn(async move {
+                    stopping.await;
+                    draining.graceful_shutdown(None);
+                });
+
+                info!("🚀 Server running at https://{}", addr);
+                axum_server::from_tcp_rustls(listener.into_std()?, tls.clone())
+                    .handle(handle)
+                    .serve(app.clone())
+                    .boxed()
+            }
+            None => {
+                info!("🚀 Server running at http://{}", addr);
+                axum::serve(listener, app.clone())
+                    .with_graceful_shutdown(shutdown.requested())
+                    .into_future()
+                    .boxed()
+            }
+        };
+        servers.push(server);
+    }
+    if let (Some(admin), Some(addr)) = (admin, &config.admin_addr) {
+        let listener = match TcpListener::bind(addr).await {
+            Ok(listener) => listener,
+            Err(e) => {
+                error!("❌ Failed to bind admin listener to {}: {}", addr, e);
+                return Err(e);
+            }
+        };
+        info!("🔧 Admin endpoints at http://{}", addr);
+        servers.push(axum::serve(listener, admin).with_graceful_shutdown(shutdown.requested()).into_future().boxed());
+    }
+    let server = try_join_all(servers).map(|result| result.map(drop));
+
+    tokio::select! {
+        result = server => result.expect("Server failed"),
+        _ = shutdown.grace_expired() => warn!("Grace period over, dropping open connections"),
+    }
+    shutdown.mark_drained();
+    shutdown.wait_for_tasks().await;
+    pool.close().await;
+    info!("Shutdown complete");
 
+    telemetry.shutdown();
     Ok(())
 }
 
+#[utoipa::path(
+    get,
+    path = "/",
+    tag = "health",
+    security(()),
+    responses(
+        (status = 200, description = "Greeting"),
+    )
+)]
 async fn root_handler() -> Json<serde_json::Value> {
     Json(json!({
         "message": "Hello from Axum!"
@@ -0,0 +1,163 @@
+use std::sync::LazyLock;
+use std::time::Instant;
+
+use axum::extract::{MatchedPath, Request, State};
+use axum::http::header;
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use prometheus::{Encoder, HistogramOpts, HistogramVec, IntCounterVec, IntGauge, IntGaugeVec, Opts, Registry, TextEncoder};
+use sqlx::PgPool;
+use tracing::error;
+
+const OUTCOME_SENT: &str = "sent";
+const OUTCOME_FAILED: &str = "failed";
+
+pub const VERIFY_VERIFIED: &str = "verified";
+pub const VERIFY_EXPIRED: &str = "expired";
+pub const VERIFY_NOT_FOUND: &str = "not_found";
+pub const VERIFY_ERROR: &str = "error";
+
+struct Metrics {
+    registry: Registry,
+    http_requests: IntCounterVec,
+    http_request_duration: HistogramVec,
+    db_pool_connections: IntGaugeVec,
+    db_pool_max_connections: IntGauge,
+    otp_deliveries: IntCounterVec,
+    otp_verifications: IntCounterVec,
+    emails: IntCounterVec,
+    rate_limited: IntCounterVec,
+}
+
+impl Metrics {
+    fn new() -> Self {
+        let registry = Registry::new();
+
+        let http_requests = IntCounterVec::new(
+            Opts::new("http_requests_total", "HTTP requests by route template, method and status"),
+            &["route", "method", "status"],
+        )
+        .unwrap();
+        let http_request_duration = HistogramVec::new(
+            HistogramOpts::new("http_request_duration_seconds", "HTTP request latency by route template, method and status"),
+            &["route", "method", "status"],
+        )
+        .unwrap();
+        let db_pool_connections = IntGaugeVec::new(
+            Opts::new("db_pool_connections", "Postgres pool connections by state (idle, in_use)"),
+            &["state"],
+        )
+        .unwrap();
+        let db_pool_max_connections =
+            IntGauge::new("db_pool_max_connections", "Configured size limit of the Postgres pool").unwrap();
+        let otp_deliveries = IntCounterVec::new(
+            Opts::new("otp_deliveries_total", "OTP deliveries by channel (email, sms) and outcome (sent, failed)"),
+            &["channel", "outcome"],
+        )
+        .unwrap();
+        let otp_verifications = IntCounterVec::new(
+            Opts::new("otp_verifications_total", "OTP verification attempts by outcome"),
+            &["outcome"],
+        )
+        .unwrap();
+        let emails = IntCounterVec::new(
+            Opts::new("emails_total", "Emails by kind (otp, invoice) and outcome (sent, failed)"),
+            &["kind", "outcome"],
+        )
+        .unwrap();
+        let rate_limited = IntCounterVec::new(
+            Opts::new("rate_limited_total", "Requests rejected by a rate limit, by route and key (ip, email, mobile)"),
+            &["route", "key"],
+        )
+        .unwrap();
+
+        registry.register(Box::new(http_requests.clone())).unwrap();
+        registry.register(Box::new(http_request_duration.clone())).unwrap();
+        registry.register(Box::new(db_pool_connections.clone())).unwrap();
+        registry.register(Box::new(db_pool_max_connections.clone())).unwrap();
+        registry.register(Box::new(otp_deliveries.clone())).unwrap();
+        registry.register(Box::new(otp_verifications.clone())).unwrap();
+        registry.register(Box::new(emails.clone())).unwrap();
+        registry.register(Box::new(rate_limited.clone())).unwrap();
+
+        Self {
+            registry,
+            http_requests,
+            http_request_duration,
+            db_pool_connections,
+            db_pool_max_connections,
+            otp_deliveries,
+            otp_verifications,
+            emails,
+            rate_limited,
+        }
+    }
+}
+
+// One set per process; the server and the worker each expose their own
+static METRICS: LazyLock<Metrics> = LazyLock::new(Metrics::new);
+
+fn outcome(success: bool) -> &'static str {
+    if success { OUTCOME_SENT } else { OUTCOME_FAILED }
+}
+
+pub fn record_otp_delivery(channel: &str, success: bool) {
+    METRICS.otp_deliveries.with_label_values(&[channel, outcome(success)]).inc();
+}
+
+pub fn record_otp_verification(outcome: &str) {
+    METRICS.otp_verifications.with_label_values(&[outcome]).inc();
+}
+
+pub fn record_email(kind: &str, success: bool) {
+    METRICS.emails.with_label_values(&[kind, outcome(success)]).inc();
+}
+
+pub fn record_rate_limited(route: &str, key: &str) {
+    METRICS.rate_limited.with_label_values(&[route, key]).inc();
+}
+
+// Counts and times every request. Routes are labelled by template, never by raw path,
+// so ids in URLs don't blow up the number of series.
+pub async fn track_requests(req: Request, next: Next) -> Response {
+    let method = req.method().clone();
+    let started = Instant::now();
+    let response = next.run(req).await;
+
+    let route = response.extensions().get::<MatchedPath>().map_or("unmatched", MatchedPath::as_str);
+    let status = response.status().as_u16().to_string();
+    let labels = [route, method.as_str(), status.as_str()];
+    METRICS.http_requests.with_label_values(&labels).inc();
+    METRICS
+        .http_request_duration
+        .with_label_values(&labels)
+        .observe(started.elapsed().as_secs_f64());
+
+    response
+}
+
+#[utoipa::path(
+    get,
+    path = "/metrics",
+    tag = "health",
+    security(()),
+    responses(
+        (status = 200, description = "Prometheus text exposition format", content_type = "text/plain")
+    )
+)]
+pub async fn metrics_handler(State(pool): State<PgPool>) -> impl IntoResponse {
+    let size = i64::from(pool.size());
+    let idle = i64::try_from(pool.num_idle()).unwrap_or(i64::MAX);
+    METRICS.db_pool_connections.with_label_values(&["idle"]).set(idle);
+    METRICS.db_pool_connections.with_label_values(&["in_use"]).set(size - idle);
+    METRICS
+        .db_pool_max_connections
+        .set(i64::from(pool.options().get_max_connections()));
+
+    let mut buffer = Vec::new();
+    let encoder = TextEncoder::new();
+    if let Err(e) = encoder.encode(&METRICS.registry.gather(), &mut buffer) {
+        error!(error = %e, "Error encoding metrics");
+    }
+    ([(header::CONTENT_TYPE, encoder.format_type().to_string())], buffer)
+}
@@ -1,8 +1,279 @@
-use axum::{http::StatusCode, middleware::Next, response::Response, body::Body,};
-use axum::extract::Request;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime};
 
+use async_trait::async_trait;
+use axum::{http::StatusCode, middleware::Next, response::Response};
+use axum::extract::{FromRequestParts, Request, State};
+use axum::http::{header, request::Parts, HeaderMap};
+use axum::response::{IntoResponse, Json};
+use jsonwebtoken::errors::ErrorKind;
+use jsonwebtoken::{decode, decode_header, Algorithm, DecodingKey, Validation};
+#[cfg(test)]
+use jsonwebtoken::{encode, EncodingKey, Header};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tracing::{info, warn};
 
-pub async fn jwt_auth(req: Request<Body>, next: Next)-> Result<Response, StatusCode> {
-    // Validate JWT here
-    Ok(next.run(req).await)
+use crate::config::Secret;
+use crate::shutdown::Shutdown;
+use crate::AppState;
+
+// `kid` given to a key configured on its own with JWT_SECRET
+const SINGLE_KEY_ID: &str = "default";
+
+// Claims carried by an admin's access token
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Claims {
+    pub sub: String,
+    pub admin_id: i32,
+    pub exp: u64,
+}
+
+// A key set file, e.g.
+//
+//   active = "2026-10"
+//   [keys]
+//   "2026-10" = "..."
+//   "2026-07" = "..."
+//
+// To rotate, add a key, make it active, and drop the old one once its tokens have expired.
+#[derive(Deserialize)]
+struct KeySetFile {
+    active: String,
+    keys: HashMap<String, Secret>,
+}
+
+struct KeySet {
+    active: String,
+    keys: HashMap<String, Secret>,
+}
+
+#[derive(Debug)]
+pub enum KeySetError {
+    Read(String),
+    Parse(String),
+    UnknownActive(String),
+}
+
+impl fmt::Display for KeySetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeySetError::Read(detail) => write!(f, "Cannot read JWT key set: {}", detail),
+            KeySetError::Parse(detail) => write!(f, "Invalid JWT key set: {}", detail),
+            KeySetError::UnknownActive(kid) => write!(f, "Active JWT key {} is not in the key set", kid),
+        }
+    }
+}
+
+// HS256 keys by `kid`. Tokens are signed with the active key and verified with whichever
+// key their header names, so tokens signed before a rotation stay valid while their key
+// is still listed. Shared by every handler; `watch` swaps in a new set when the file changes.
+#[derive(Clone)]
+pub struct JwtKeys(Arc<RwLock<KeySet>>);
+
+impl JwtKeys {
+    pub fn single(secret: Secret) -> Self {
+        let keys = HashMap::from([(SINGLE_KEY_ID.to_string(), secret)]);
+        Self(Arc::new(RwLock::new(KeySet { active: SINGLE_KEY_ID.to_string(), keys })))
+    }
+
+    pub fn load(path: &str) -> Result<Self, KeySetError> {
+        Ok(Self(Arc::new(RwLock::new(read_key_set(path)?))))
+    }
+
+    // Re-reads the key set whenever the file changes. A bad file is logged and the
+    // current keys are kept, so a botched rotation never locks everyone out.
+    pub async fn watch(self, path: String, every: Duration, shutdown: Shutdown) {
+        let mut seen = modified_at(&path);
+        let mut interval = tokio::time::interval(every);
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = shutdown.requested() => return,
+            }
+            let modified = modified_at(&path);
+            if modified.is_none() || modified == seen {
+                continue;
+            }
+            seen = modified;
+            match read_key_set(&path) {
+                Ok(set) => {
+                    let mut kids: Vec<_> = set.keys.keys().cloned().collect();
+                    kids.sort();
+                    info!(active = %set.active, kids = ?kids, "Reloaded JWT key set");
+                    *self.0.write().unwrap() = set;
+                }
+                Err(e) => warn!(error = %e, "Keeping current JWT keys"),
+            }
+        }
+    }
+
+    // Checks the signature and expiry. A token without `kid` is checked against the active key.
+    pub fn decode(&self, token: &str) -> Result<Claims, jsonwebtoken::errors::Error> {
+        let kid = decode_header(token)?.kid;
+        let set = self.0.read().unwrap();
+        let secret = set
+            .keys
+            .get(kid.as_deref().unwrap_or(&set.active))
+            .ok_or(ErrorKind::InvalidToken)?;
+        let validation = Validation::new(Algorithm::HS256);
+        decode::<Claims>(token, &DecodingKey::from_secret(secret.expose().as_bytes()), &validation)
+            .map(|data| data.claims)
+    }
+
+    // Access tokens are issued by the identity service that shares the key set
+    #[cfg(test)]
+    pub fn sign(&self, claims: &Claims) -> Result<String, jsonwebtoken::errors::Error> {
+        let set = self.0.read().unwrap();
+        let header = Header { kid: Some(set.active.clone()), ..Header::new(Algorithm::HS256) };
+        let secret = &set.keys[&set.active];
+        encode(&header, claims, &EncodingKey::from_secret(secret.expose().as_bytes()))
+    }
+}
+
+fn modified_at(path: &str) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+fn read_key_set(path: &str) -> Result<KeySet, KeySetError> {
+    let contents = std::fs::read_to_string(path).map_err(|e| KeySetError::Read(format!("{}: {}", path, e)))?;
+    let file: KeySetFile = toml::from_str(&contents).map_err(|e| KeySetError::Parse(e.message().to_string()))?;
+    if !file.keys.contains_key(&file.active) {
+        return Err(KeySetError::UnknownActive(file.active));
+    }
+    Ok(KeySet { active: file.active, keys: file.keys })
+}
+
+// Who made a request, for the access log. Handlers that authenticate the caller attach it
+// to their response.
+#[derive(Clone, Debug)]
+pub struct Principal(pub String);
+
+pub fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+}
+
+fn auth_error(status: StatusCode, message: &str) -> Response {
+    (status, Json(json!({ "status": "error", "message": message }))).into_response()
+}
+
+// Checks the bearer token against the current key set before the handler runs and
+// hands its claims on in the request extensions
+pub async fn jwt_auth(State(state): State<AppState>, mut req: Request, next: Next) -> Response {
+    let Some(keys) = &state.jwt_keys else {
+        return auth_error(StatusCode::SERVICE_UNAVAILABLE, "Authentication is not configured");
+    };
+    let Some(token) = bearer_token(req.headers()) else {
+        return auth_error(StatusCode::UNAUTHORIZED, "Missing token");
+    };
+    let claims = match keys.decode(token) {
+        Ok(claims) => claims,
+        Err(e) => {
+            warn!(error = %e, "Rejected access token");
+            return auth_error(StatusCode::UNAUTHORIZED, "Invalid token");
+        }
+    };
+
+    let principal = Principal(claims.sub.clone());
+    req.extensions_mut().insert(claims);
+    let mut response = next.run(req).await;
+    response.extensions_mut().insert(principal);
+    response
+}
+
+// The admin whose access token `jwt_auth` verified. Handlers act on this admin's data
+// only, never on an admin id taken from the request body or query.
+#[derive(Clone, Copy, Debug)]
+pub struct AuthAdmin {
+    pub admin_id: i32,
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for AuthAdmin {
+    type Rejection = Response;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        parts
+            .extensions
+            .get::<Claims>()
+            .map(|claims| AuthAdmin { admin_id: claims.admin_id })
+            .ok_or_else(|| auth_error(StatusCode::UNAUTHORIZED, "Missing token"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::body::Body;
+    use axum::http::{header, Request, StatusCode};
+    use axum::routing::get;
+    use axum::{middleware, Router};
+    use tower::ServiceExt;
+
+    use super::{jwt_auth, AuthAdmin, Claims, JwtKeys};
+    use crate::config::Secret;
+    use crate::test_state;
+
+    fn protected(keys: Option<JwtKeys>) -> Router {
+        let state = test_state(keys);
+        Router::new()
+            .route("/", get(|admin: AuthAdmin| async move { admin.admin_id.to_string() }))
+            .route_layer(middleware::from_fn_with_state(state.clone(), jwt_auth))
+            .with_state(state)
+    }
+
+    fn request(token: Option<&str>) -> Request<Body> {
+        let mut request = Request::get("/");
+        if let Some(token) = token {
+            request = request.header(header::AUTHORIZATION, format!("Bearer {}", token));
+        }
+        request.body(Body::empty()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn only_tokens_signed_with_a_known_key_get_through() {
+        let keys = JwtKeys::single(Secret::new("test-secret"));
+        let claims = Claims { sub: "admin@example.com".to_string(), admin_id: 7, exp: u64::MAX / 2 };
+        let token = keys.sign(&claims).unwrap();
+        let forged = JwtKeys::single(Secret::new("other-secret")).sign(&claims).unwrap();
+        let app = protected(Some(keys));
+
+        let response = app.clone().oneshot(request(Some(&token))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), 64).await.unwrap();
+        assert_eq!(&body[..], b"7");
+
+        let response = app.clone().oneshot(request(None)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        let response = app.oneshot(request(Some(&forged))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let response = protected(None).oneshot(request(Some(&token))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    #[test]
+    fn tokens_survive_rotation_while_their_key_is_listed() {
+        let path = std::env::temp_dir().join(format!("jwt-keys-{}.toml", std::process::id()));
+        let path = path.to_str().unwrap();
+        std::fs::write(path, "active = \"old\"\n[keys]\nold = \"first-secret\"\n").unwrap();
+        let keys = JwtKeys::load(path).unwrap();
+        let claims = Claims { sub: "admin@example.com".to_string(), admin_id: 7, exp: u64::MAX / 2 };
+        let old_token = keys.sign(&claims).unwrap();
+
+        std::fs::write(path, "active = \"new\"\n[keys]\nold = \"first-secret\"\nnew = \"second-secret\"\n").unwrap();
+        let rotated = JwtKeys::load(path).unwrap();
+        let new_token = rotated.sign(&claims).unwrap();
+        assert_eq!(rotated.decode(&old_token).unwrap().admin_id, 7);
+        assert_eq!(rotated.decode(&new_token).unwrap().admin_id, 7);
+
+        std::fs::write(path, "active = \"new\"\n[keys]\nnew = \"second-secret\"\n").unwrap();
+        let retired = JwtKeys::load(path).unwrap();
+        assert!(retired.decode(&old_token).is_err());
+        std::fs::remove_file(path).unwrap();
+    }
 }
@@ -0,0 +1,190 @@
+use std::borrow::Cow;
+use std::io::{self, Write};
+use std::sync::OnceLock;
+use std::time::Instant;
+
+use axum::extract::{MatchedPath, Request};
+use axum::http::{HeaderName, HeaderValue};
+use axum::middleware::Next;
+use axum::response::Response;
+use rand::Rng;
+use regex::Regex;
+use tracing::field::Empty;
+use tracing::{error, info, info_span, Instrument, Subscriber};
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::{EnvFilter, Layer};
+
+use crate::middleware::auth::Principal;
+use crate::telemetry;
+
+pub static REQUEST_ID_HEADER: HeaderName = HeaderName::from_static("x-request-id");
+const MAX_REQUEST_ID_LEN: usize = 128;
+
+// JSON lines on stdout, filtered by RUST_LOG (default `info`). Installed by `telemetry::init`.
+pub fn layer<S>() -> impl Layer<S>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    tracing_subscriber::fmt::layer()
+        .json()
+        .with_current_span(true)
+        .with_span_list(false)
+        .with_writer(RedactingStdout)
+        .with_filter(EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info")))
+}
+
+// Personal data that must never reach the logs, whichever message or error it turns up in
+fn redactions() -> &'static [(Regex, &'static str)] {
+    static REDACTIONS: OnceLock<Vec<(Regex, &'static str)>> = OnceLock::new();
+    REDACTIONS.get_or_init(|| {
+        vec![
+            // Fields that hold secrets outright
+            (
+                Regex::new(r#""(otp|token|password|secret)":\s*("(?:[^"\\]|\\.)*"|\d+)"#).unwrap(),
+                r#""$1":"[redacted]""#,
+            ),
+            // a.person@example.com -> a***@example.com
+            (
+                Regex::new(r"([A-Za-z0-9._%+-])[A-Za-z0-9._%+-]*@([A-Za-z0-9-]+(?:\.[A-Za-z0-9-]+)+)").unwrap(),
+                "$1***@$2",
+            ),
+            // Only string values of mobile or phone fields, so amounts and ids that happen to
+            // be ten digits stay readable: "mobile":"9876543210" -> "mobile":"******3210"
+            (
+                Regex::new(r#"(?i)\b((?:mobile|phone)\w*\\?"?\s*[:=]\s*\\?")\+?(?:91)?[6-9]\d{5}(\d{4})\b"#).unwrap(),
+                "${1}******$2",
+            ),
+            // Anywhere else a number needs the country code: +91 9876543210 -> ******3210
+            (Regex::new(r"\+91[ -]?[6-9]\d{5}(\d{4})\b").unwrap(), "******$1"),
+        ]
+    })
+}
+
+pub fn redact(line: &str) -> Cow<'_, str> {
+    let mut line = Cow::Borrowed(line);
+    for (pattern, replacement) in redactions() {
+        if let Cow::Owned(redacted) = pattern.replace_all(&line, *replacement) {
+            line = Cow::Owned(redacted);
+        }
+    }
+    line
+}
+
+struct RedactingStdout;
+
+impl<'a> MakeWriter<'a> for RedactingStdout {
+    type Writer = RedactingWriter;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        RedactingWriter(Vec::new())
+    }
+}
+
+// Buffers one formatted event and scrubs it on the way to stdout
+struct RedactingWriter(Vec<u8>);
+
+impl Write for RedactingWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Drop for RedactingWriter {
+    fn drop(&mut self) {
+        let line = String::from_utf8_lossy(&self.0);
+        let _ = io::stdout().lock().write_all(redact(&line).as_bytes());
+    }
+}
+
+// Keeps a caller's id if it looks sane so a request can be followed across services
+fn request_id(req: &Request) -> HeaderValue {
+    req.headers()
+        .get(&REQUEST_ID_HEADER)
+        .filter(|id| !id.is_empty() && id.len() <= MAX_REQUEST_ID_LEN)
+        .filter(|id| id.as_bytes().iter().all(u8::is_ascii_graphic))
+        .cloned()
+        .unwrap_or_else(|| {
+            let id = hex::encode(rand::thread_rng().gen::<[u8; 16]>());
+            HeaderValue::from_str(&id).expect("hex is a valid header value")
+        })
+}
+
+// Outermost layer: tags everything logged while handling the request with its id and
+// writes one access log line. Bodies and query strings are never logged. The span is also
+// the request's server span when traces are exported, continuing the caller's trace if any.
+pub async fn log_requests(mut req: Request, next: Next) -> Response {
+    let request_id = request_id(&req);
+    req.headers_mut().insert(REQUEST_ID_HEADER.clone(), request_id.clone());
+
+    let span = info_span!(
+        "request",
+        request_id = request_id.to_str().unwrap_or_default(),
+        method = %req.method(),
+        otel.name = Empty,
+        otel.kind = "server",
+        http.route = Empty,
+        http.response.status_code = Empty,
+    );
+    telemetry::set_parent_from_headers(&span, req.headers());
+    let method = req.method().clone();
+    let started = Instant::now();
+    let mut response = next.run(req).instrument(span.clone()).await;
+    let latency_ms = started.elapsed().as_secs_f64() * 1000.0;
+
+    let route = response.extensions().get::<MatchedPath>().map_or("unmatched", MatchedPath::as_str);
+    let principal = response.extensions().get::<Principal>().map_or("anonymous", |p| p.0.as_str());
+    let status = response.status().as_u16();
+    span.record("otel.name", format!("{} {}", method, route));
+    span.record("http.route", route);
+    span.record("http.response.status_code", status);
+    span.in_scope(|| {
+        if response.status().is_server_error() {
+            error!(route, status, latency_ms, principal, "request failed");
+        } else {
+            info!(route, status, latency_ms, principal, "request completed");
+        }
+    });
+
+    response.headers_mut().insert(REQUEST_ID_HEADER.clone(), request_id);
+    response
+}
+
+// Route layer: the matched template is only known after routing, so it is handed back
+// to `log_requests` on the response
+pub async fn capture_route(req: Request, next: Next) -> Response {
+    let matched = req.extensions().get::<MatchedPath>().cloned();
+    let mut response = next.run(req).await;
+    if let Some(matched) = matched {
+        response.extensions_mut().insert(matched);
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::redact;
+
+    #[test]
+    fn redacts_personal_data() {
+        let line = r#"{"message":"Email to ravi.kumar@example.co.in failed","mobile":"+919876543210","otp":482913,"token":"eyJ.x.y","order_id":42}"#;
+        assert_eq!(
+            redact(line),
+            r#"{"message":"Email to r***@example.co.in failed","mobile":"******3210","otp":"[redacted]","token":"[redacted]","order_id":42}"#
+        );
+    }
+
+    #[test]
+    fn leaves_other_numbers_alone() {
+        let line = r#"{"message":"Paid 9876543210 paise, SMS to +91 9123456789","amount_paise":9876543210,"mobile":9876543210,"error":"Admin_Users { mobile: \"+919876543210\" }"}"#;
+        assert_eq!(
+            redact(line),
+            r#"{"message":"Paid 9876543210 paise, SMS to ******6789","amount_paise":9876543210,"mobile":9876543210,"error":"Admin_Users { mobile: \"******3210\" }"}"#
+        );
+    }
+}
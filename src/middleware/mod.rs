@@ -1,2 +1,4 @@
 pub mod auth;
 pub mod logging;
+pub mod rate_limit;
+pub mod security;
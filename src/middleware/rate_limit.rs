@@ -0,0 +1,370 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::net::SocketAddr;
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use axum::body::{to_bytes, Body};
+use axum::extract::{ConnectInfo, MatchedPath, Request, State};
+use axum::http::{HeaderMap, HeaderValue, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Json, Response};
+use redis::aio::ConnectionManager;
+use serde_json::json;
+use tracing::warn;
+
+use crate::api::{legacy, V1_PREFIX};
+use crate::metrics;
+use crate::AppState;
+
+// Bodies are only read for rules keyed by a field, and those endpoints take small JSON
+const MAX_BODY_BYTES: usize = 64 * 1024;
+// The in-memory store forgets buckets that have refilled once it holds this many
+const MEMORY_PRUNE_AT: usize = 10_000;
+
+// What a bucket is per: the client address, a field of the JSON body, or one bucket for
+// every client so a spread of addresses can't get around the per-IP limit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LimitKey {
+    Ip,
+    Email,
+    Mobile,
+    Global,
+}
+
+impl LimitKey {
+    fn name(self) -> &'static str {
+        match self {
+            LimitKey::Ip => "ip",
+            LimitKey::Email => "email",
+            LimitKey::Mobile => "mobile",
+            LimitKey::Global => "global",
+        }
+    }
+}
+
+// A token bucket for one route, e.g. `/login:email=3/10m`: three requests per email in a
+// burst, refilling at three per ten minutes. Routes are v1 paths without the prefix and
+// also cover their legacy alias.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rule {
+    pub route: String,
+    pub key: LimitKey,
+    pub capacity: u32,
+    pub period: Duration,
+}
+
+impl FromStr for Rule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid rate limit {:?}, expected e.g. /login:ip=10/1m", s);
+        let (target, limit) = s.rsplit_once('=').ok_or_else(invalid)?;
+        let (route, key) = target.rsplit_once(':').ok_or_else(invalid)?;
+        let (capacity, period) = limit.split_once('/').ok_or_else(invalid)?;
+
+        let key = match key {
+            "ip" => LimitKey::Ip,
+            "email" => LimitKey::Email,
+            "mobile" => LimitKey::Mobile,
+            "global" => LimitKey::Global,
+            _ => return Err(format!("unknown rate limit key {:?}, expected ip, email, mobile or global", key)),
+        };
+        let capacity: u32 = capacity.parse().map_err(|_| invalid())?;
+        let period = parse_period(period).ok_or_else(invalid)?;
+        if !route.starts_with('/') || capacity == 0 || period.is_zero() {
+            return Err(invalid());
+        }
+        Ok(Rule { route: route.to_string(), key, capacity, period })
+    }
+}
+
+fn parse_period(s: &str) -> Option<Duration> {
+    let (count, unit) = s.split_at(s.find(|c: char| !c.is_ascii_digit())?);
+    let count: u64 = count.parse().ok()?;
+    let secs = match unit {
+        "s" => count,
+        "m" => count * 60,
+        "h" => count * 3600,
+        _ => return None,
+    };
+    Some(Duration::from_secs(secs))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Store {
+    #[default]
+    Memory,
+    Redis,
+}
+
+impl FromStr for Store {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "memory" => Ok(Store::Memory),
+            "redis" => Ok(Store::Redis),
+            _ => Err(format!("unknown rate limit store {:?}, expected memory or redis", s)),
+        }
+    }
+}
+
+// Outcome of taking one token
+pub struct Decision {
+    pub allowed: bool,
+    // Until the next token, when not allowed
+    pub retry_after: Duration,
+}
+
+#[derive(Debug)]
+pub struct StoreError(String);
+
+impl fmt::Display for StoreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "rate limit store error: {}", self.0)
+    }
+}
+
+#[async_trait]
+pub trait BucketStore: Send + Sync {
+    async fn take(&self, bucket: &str, rule: &Rule) -> Result<Decision, StoreError>;
+}
+
+// Refills `tokens` for the time since `updated`, then takes one if there is one
+fn take_token(tokens: f64, elapsed: Duration, rule: &Rule) -> (f64, Decision) {
+    let capacity = f64::from(rule.capacity);
+    let per_sec = capacity / rule.period.as_secs_f64();
+    let tokens = (tokens + elapsed.as_secs_f64() * per_sec).min(capacity);
+    if tokens >= 1.0 {
+        (tokens - 1.0, Decision { allowed: true, retry_after: Duration::ZERO })
+    } else {
+        let retry_after = Duration::from_secs_f64((1.0 - tokens) / per_sec);
+        (tokens, Decision { allowed: false, retry_after })
+    }
+}
+
+// Buckets in this process only; fine for a single instance
+#[derive(Default)]
+pub struct MemoryStore {
+    buckets: Mutex<HashMap<String, MemoryBucket>>,
+}
+
+struct MemoryBucket {
+    tokens: f64,
+    updated: Instant,
+    period: Duration,
+}
+
+#[async_trait]
+impl BucketStore for MemoryStore {
+    async fn take(&self, bucket: &str, rule: &Rule) -> Result<Decision, StoreError> {
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock().unwrap();
+        if buckets.len() >= MEMORY_PRUNE_AT {
+            // A bucket untouched for a whole period is full again, same as a missing one
+            buckets.retain(|_, kept| now.duration_since(kept.updated) < kept.period);
+        }
+
+        let (tokens, elapsed) = match buckets.get(bucket) {
+            Some(kept) => (kept.tokens, now.duration_since(kept.updated)),
+            None => (f64::from(rule.capacity), Duration::ZERO),
+        };
+        let (tokens, decision) = take_token(tokens, elapsed, rule);
+        buckets.insert(bucket.to_string(), MemoryBucket { tokens, updated: now, period: rule.period });
+        Ok(decision)
+    }
+}
+
+// Same bucket as `take_token`, kept in a Redis hash so every instance shares it. Uses the
+// Redis clock so instances with skewed clocks agree; the key expires once it would be full.
+const TAKE_SCRIPT: &str = r#"
+local capacity = tonumber(ARGV[1])
+local period_ms = tonumber(ARGV[2])
+local time = redis.call('TIME')
+local now = time[1] * 1000 + math.floor(time[2] / 1000)
+local state = redis.call('HMGET', KEYS[1], 'tokens', 'updated')
+local tokens = tonumber(state[1]) or capacity
+local updated = tonumber(state[2]) or now
+local per_ms = capacity / period_ms
+tokens = math.min(capacity, tokens + math.max(0, now - updated) * per_ms)
+local allowed = 0
+local retry_ms = 0
+if tokens >= 1 then
+  tokens = tokens - 1
+  allowed = 1
+else
+  retry_ms = math.ceil((1 - tokens) / per_ms)
+end
+redis.call('HSET', KEYS[1], 'tokens', tostring(tokens), 'updated', now)
+redis.call('PEXPIRE', KEYS[1], period_ms)
+return {allowed, retry_ms}
+"#;
+
+pub struct RedisStore {
+    connection: ConnectionManager,
+    script: redis::Script,
+}
+
+impl RedisStore {
+    pub async fn connect(redis_url: &str) -> Result<Self, redis::RedisError> {
+        let client = redis::Client::open(redis_url)?;
+        Ok(Self { connection: ConnectionManager::new(client).await?, script: redis::Script::new(TAKE_SCRIPT) })
+    }
+}
+
+#[async_trait]
+impl BucketStore for RedisStore {
+    async fn take(&self, bucket: &str, rule: &Rule) -> Result<Decision, StoreError> {
+        let mut connection = self.connection.clone();
+        let (allowed, retry_ms): (i64, u64) = self
+            .script
+            .key(format!("ratelimit:{}", bucket))
+            .arg(rule.capacity)
+            .arg(rule.period.as_millis() as u64)
+            .invoke_async(&mut connection)
+            .await
+            .map_err(|e| StoreError(e.to_string()))?;
+        Ok(Decision { allowed: allowed == 1, retry_after: Duration::from_millis(retry_ms) })
+    }
+}
+
+pub struct RateLimiter {
+    rules: Vec<Rule>,
+    store: Box<dyn BucketStore>,
+    // Only behind a proxy that appends the client address to X-Forwarded-For
+    trust_forwarded_for: bool,
+}
+
+impl RateLimiter {
+    pub fn new(rules: Vec<Rule>, store: Box<dyn BucketStore>, trust_forwarded_for: bool) -> Self {
+        Self { rules, store, trust_forwarded_for }
+    }
+
+    fn client_ip(&self, req: &Request) -> Option<String> {
+        if self.trust_forwarded_for {
+            // The last entry is the one our proxy added; anything before it is client-supplied
+            let forwarded = forwarded_for(req.headers());
+            if forwarded.is_some() {
+                return forwarded;
+            }
+        }
+        req.extensions().get::<ConnectInfo<SocketAddr>>().map(|info| info.0.ip().to_string())
+    }
+}
+
+fn forwarded_for(headers: &HeaderMap) -> Option<String> {
+    let value = headers.get("x-forwarded-for")?.to_str().ok()?;
+    let last = value.rsplit(',').next()?.trim();
+    (!last.is_empty()).then(|| last.to_string())
+}
+
+// A route's v1 path without the prefix, so the legacy alias shares the v1 route's buckets
+fn route_of(matched: &str) -> String {
+    let v1 = if matched.starts_with(V1_PREFIX) { matched.to_string() } else { legacy::successor(matched) };
+    v1[V1_PREFIX.len()..].to_string()
+}
+
+// Field values are normalised so `A@x.com ` and `a@x.com` share a bucket
+fn body_field(body: &serde_json::Value, key: LimitKey) -> Option<String> {
+    let value = body.get(key.name())?.as_str()?.trim().to_lowercase();
+    (!value.is_empty()).then_some(value)
+}
+
+fn too_many_requests(retry_after: Duration) -> Response {
+    let mut response = (
+        StatusCode::TOO_MANY_REQUESTS,
+        Json(json!({
+            "status": "error",
+            "message": "Too many requests, try again later"
+        })),
+    )
+        .into_response();
+    let secs = retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0);
+    response.headers_mut().insert("retry-after", HeaderValue::from(secs.max(1)));
+    response
+}
+
+// Applies every rule for the matched route. A request without the keyed value (no client
+// address, or no such field in the body) isn't limited by that rule; if the store is
+// unreachable the request goes through rather than taking the endpoint down with it.
+pub async fn rate_limit(State(state): State<AppState>, req: Request, next: Next) -> Response {
+    let Some(limiter) = state.rate_limiter.as_deref() else {
+        return next.run(req).await;
+    };
+    let Some(route) = req.extensions().get::<MatchedPath>().map(|path| route_of(path.as_str())) else {
+        return next.run(req).await;
+    };
+    let rules: Vec<&Rule> = limiter.rules.iter().filter(|rule| rule.route == route).collect();
+    if rules.is_empty() {
+        return next.run(req).await;
+    }
+
+    let ip = limiter.client_ip(&req);
+    let mut req = req;
+    let mut body = None;
+    if rules.iter().any(|rule| matches!(rule.key, LimitKey::Email | LimitKey::Mobile)) {
+        let (parts, raw) = req.into_parts();
+        let bytes = match to_bytes(raw, MAX_BODY_BYTES).await {
+            Ok(bytes) => bytes,
+            Err(_) => return StatusCode::PAYLOAD_TOO_LARGE.into_response(),
+        };
+        body = serde_json::from_slice::<serde_json::Value>(&bytes).ok();
+        req = Request::from_parts(parts, Body::from(bytes));
+    }
+
+    for rule in rules {
+        let value = match rule.key {
+            LimitKey::Ip => ip.clone(),
+            LimitKey::Global => Some("all".to_string()),
+            key => body.as_ref().and_then(|body| body_field(body, key)),
+        };
+        let Some(value) = value else {
+            continue;
+        };
+
+        let bucket = format!("{}:{}:{}", rule.route, rule.key.name(), value);
+        match limiter.store.take(&bucket, rule).await {
+            Ok(decision) if !decision.allowed => {
+                warn!(route = %rule.route, key = rule.key.name(), "Rate limit exceeded");
+                metrics::record_rate_limited(&rule.route, rule.key.name());
+                return too_many_requests(decision.retry_after);
+            }
+            Ok(_) => {}
+            Err(e) => warn!(route = %rule.route, "{}, not limiting", e),
+        }
+    }
+
+    next.run(req).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn bucket_empties_and_refills() {
+        let rule: Rule = "/login:email=2/1s".parse().unwrap();
+        assert_eq!(rule.route, "/login");
+        assert_eq!(rule.key, LimitKey::Email);
+        assert!("/orders/:id:ip=5/1m".parse::<Rule>().is_ok());
+        assert!("/login:cookie=5/1m".parse::<Rule>().is_err());
+        assert_eq!("/verify:global=60/1m".parse::<Rule>().unwrap().key, LimitKey::Global);
+
+        let store = MemoryStore::default();
+        assert!(store.take("a", &rule).await.unwrap().allowed);
+        assert!(store.take("a", &rule).await.unwrap().allowed);
+        let denied = store.take("a", &rule).await.unwrap();
+        assert!(!denied.allowed);
+        assert!(denied.retry_after <= Duration::from_millis(500));
+        assert!(store.take("b", &rule).await.unwrap().allowed);
+
+        tokio::time::sleep(Duration::from_millis(600)).await;
+        assert!(store.take("a", &rule).await.unwrap().allowed);
+
+        assert_eq!(route_of("/api/v1/login"), "/login");
+        assert_eq!(route_of("/add_user"), "/users");
+    }
+}
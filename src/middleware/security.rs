@@ -0,0 +1,72 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::extract::{Request, State};
+use axum::http::{header, HeaderName, HeaderValue};
+use axum::middleware::Next;
+use axum::response::Response;
+use tower_http::cors::{AllowOrigin, Any, CorsLayer};
+use tracing::warn;
+
+use crate::config::Config;
+
+// Without configured origins no CORS headers are sent, so browsers only allow same-origin calls
+pub fn cors(config: &Config) -> CorsLayer {
+    let Some(origins) = &config.cors_allowed_origins else {
+        return CorsLayer::new();
+    };
+
+    let allow_origin = if origins.iter().any(|origin| origin == "*") {
+        AllowOrigin::from(Any)
+    } else {
+        let origins: Vec<HeaderValue> = origins
+            .iter()
+            .filter_map(|origin| match HeaderValue::from_str(origin) {
+                Ok(value) => Some(value),
+                Err(_) => {
+                    warn!(origin = %origin, "Ignoring CORS origin that isn't a valid header value");
+                    None
+                }
+            })
+            .collect();
+        AllowOrigin::list(origins)
+    };
+
+    CorsLayer::new()
+        .allow_origin(allow_origin)
+        .allow_methods(config.cors_allowed_methods.to_vec())
+        .allow_headers(config.cors_allowed_headers.to_vec())
+        .allow_credentials(config.cors_allow_credentials)
+        .max_age(Duration::from_secs(config.cors_max_age_secs))
+}
+
+// Headers added to every response unless the handler already set them
+pub struct SecurityHeaders(Vec<(HeaderName, HeaderValue)>);
+
+impl SecurityHeaders {
+    pub fn from_config(config: &Config) -> Arc<Self> {
+        let mut headers = vec![
+            (header::X_CONTENT_TYPE_OPTIONS, HeaderValue::from_static("nosniff")),
+            (
+                header::X_FRAME_OPTIONS,
+                HeaderValue::from_str(&config.security_frame_options).expect("validated in config"),
+            ),
+        ];
+        // Browsers ignore HSTS over plain HTTP, so it's harmless until TLS is in front
+        if config.security_hsts_max_age_secs > 0 {
+            let hsts = format!("max-age={}; includeSubDomains", config.security_hsts_max_age_secs);
+            headers.push((header::STRICT_TRANSPORT_SECURITY, HeaderValue::from_str(&hsts).expect("ASCII")));
+        }
+        Arc::new(Self(headers))
+    }
+}
+
+pub async fn security_headers(State(headers): State<Arc<SecurityHeaders>>, req: Request, next: Next) -> Response {
+    let mut response = next.run(req).await;
+    for (name, value) in &headers.0 {
+        if !response.headers().contains_key(name) {
+            response.headers_mut().insert(name.clone(), value.clone());
+        }
+    }
+    response
+}
@@ -0,0 +1,82 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use chrono::{Duration, Utc};
+use sqlx::PgPool;
+use tracing::{info, warn, Span};
+
+use crate::config::Config;
+use crate::db::users::Otp;
+use crate::events::{OtpIssued, ReceivedEvent};
+use crate::mailer::{send_email, MailError};
+use crate::metrics;
+use crate::sms::{send_sms, SmsError};
+use crate::worker::{Handler, HandlerError};
+
+// Emails (and texts, when Twilio is configured) a freshly issued OTP. The code is read
+// from the database rather than carried in the event so it never sits on the broker.
+pub async fn deliver_otp(pool: &PgPool, config: &Arc<Config>, issued: &OtpIssued) -> Result<(), HandlerError> {
+    let otp = Otp::fetch_otp(pool, issued.otp_id)
+        .await
+        .map_err(|e| HandlerError::Retry(format!("DB error: {:?}", e)))?;
+
+    // Already verified or cleaned up
+    let Some(otp) = otp else {
+        return Ok(());
+    };
+    // Same window verify_otp accepts; there is no point delivering a code after it
+    if Utc::now().naive_utc() - otp.created_at > Duration::minutes(config.otp_validity_minutes) {
+        info!(otp_id = otp.id, "Skipping delivery of expired OTP");
+        return Ok(());
+    }
+    let Some(code) = otp.otp.first().copied() else {
+        return Err(HandlerError::Fatal(format!("OTP {} has no code", otp.id)));
+    };
+
+    let to = issued.email.clone();
+    let body = format!("Your OTP is: {}", code);
+    let span = Span::current();
+    let mail_config = config.clone();
+    let sent = tokio::task::spawn_blocking(move || {
+        span.in_scope(|| send_email(&mail_config, "OTP Service", &to, "Your OTP Code", body, Vec::new()))
+    })
+    .await
+    .map_err(|e| HandlerError::Retry(format!("email task failed: {:?}", e)))?;
+    metrics::record_otp_delivery("email", sent.is_ok());
+    metrics::record_email("otp", sent.is_ok());
+    sent.map_err(|e| {
+        warn!(detail = e.detail(), "Error sending email");
+        match e {
+            // Retrying won't help until SMTP credentials are configured
+            MailError::NotConfigured => HandlerError::Fatal(e.to_string()),
+            _ => HandlerError::Retry(e.to_string()),
+        }
+    })?;
+
+    // SMS is best effort: retrying would send the email again
+    match send_sms(config, &issued.mobile, &format!("Your OTP is: {}", code)).await {
+        Ok(()) => metrics::record_otp_delivery("sms", true),
+        Err(SmsError::NotConfigured) => {}
+        Err(e) => {
+            metrics::record_otp_delivery("sms", false);
+            warn!(detail = e.detail(), "Error sending SMS");
+        }
+    }
+
+    Ok(())
+}
+
+pub struct OtpDelivery {
+    pub pool: PgPool,
+    pub config: Arc<Config>,
+}
+
+#[async_trait]
+impl Handler for OtpDelivery {
+    type Event = OtpIssued;
+    const QUEUE: &'static str = "notifications.otp";
+
+    async fn handle(&self, event: ReceivedEvent<OtpIssued>) -> Result<(), HandlerError> {
+        deliver_otp(&self.pool, &self.config, &event.data).await
+    }
+}
@@ -0,0 +1,207 @@
+use utoipa::openapi::security::{HttpAuthScheme, HttpBuilder, SecurityScheme};
+use utoipa::{Modify, OpenApi, ToSchema};
+
+use crate::api;
+
+// Every endpoint answers with this envelope, errors included, and with HTTP 200 unless
+// noted otherwise. Successful responses carry the resource under its own key, e.g. `order`.
+#[allow(dead_code)]
+#[derive(ToSchema)]
+pub struct ApiResponse {
+    #[schema(example = "success")]
+    pub status: String,
+    pub message: Option<String>,
+}
+
+// Admin access tokens, verified by `middleware::auth::jwt_auth`
+struct BearerToken;
+
+impl Modify for BearerToken {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "bearer_token",
+            SecurityScheme::Http(HttpBuilder::new().scheme(HttpAuthScheme::Bearer).bearer_format("JWT").build()),
+        );
+    }
+}
+
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "X-ERP API"),
+    modifiers(&BearerToken),
+    security(("bearer_token" = [])),
+    paths(
+        crate::root_handler,
+        crate::check_database_connection,
+        crate::health::livez,
+        crate::health::readyz,
+        crate::metrics::metrics_handler,
+        api::auth::login,
+        api::auth::verify_otp,
+        api::auth::register,
+        api::auth::create_admin,
+        api::auth::create_admin_users,
+        api::products::create_product,
+        api::products::list_products,
+        api::inventory::create_warehouse,
+        api::inventory::list_warehouses,
+        api::inventory::current_stock,
+        api::inventory::stock_ledger,
+        api::inventory::receive_stock,
+        api::inventory::issue_stock,
+        api::inventory::adjust_stock,
+        api::inventory::transfer_stock,
+        api::inventory::set_threshold,
+        api::inventory::low_stock,
+        api::pricing::create_price_list,
+        api::pricing::add_price_list_item,
+        api::pricing::create_discount,
+        api::pricing::create_coupon,
+        api::orders::quote_order,
+        api::orders::place_order,
+        api::orders::get_order,
+        api::orders::cancel_order,
+        api::orders::deliver_order,
+        api::invoices::issue_invoice,
+        api::invoices::get_invoice,
+        api::invoices::email_invoice,
+        api::payments::record_payment,
+        api::payments::list_payments,
+        api::payments::record_refund,
+        api::payments::payment_webhook,
+        api::payments::reconciliation,
+        api::returns::request_return,
+        api::returns::get_return,
+        api::returns::approve_return,
+        api::returns::reject_return,
+        api::returns::get_credit_note,
+        api::purchasing::create_supplier,
+        api::purchasing::list_suppliers,
+        api::purchasing::create_purchase_order,
+        api::purchasing::get_purchase_order,
+        api::purchasing::cancel_purchase_order,
+        api::purchasing::receive_goods,
+        api::purchasing::record_supplier_invoice,
+        api::purchasing::match_purchase_order,
+        api::realtime::notifications_socket,
+    ),
+    components(schemas(
+        ApiResponse,
+        api::auth::OTPRequest,
+        api::auth::OtpVerify,
+        api::auth::Admin,
+        api::auth::Admin_Users,
+        crate::db::products::NewProduct,
+        api::inventory::NewWarehouse,
+        api::inventory::StockChange,
+        api::inventory::Transfer,
+        api::inventory::Threshold,
+        api::pricing::NewPriceList,
+        api::pricing::NewPriceListItem,
+        crate::db::pricing::NewDiscount,
+        crate::db::pricing::NewCoupon,
+        api::orders::QuoteRequest,
+        crate::db::orders::NewOrder,
+        crate::db::orders::NewOrderLine,
+        api::payments::ManualPayment,
+        crate::db::returns::NewReturn,
+        crate::db::returns::NewReturnLine,
+        api::returns::ReturnDecision,
+        crate::db::purchasing::NewSupplier,
+        crate::db::purchasing::NewPurchaseOrder,
+        crate::db::purchasing::NewPurchaseOrderLine,
+        crate::db::purchasing::NewGoodsReceipt,
+        crate::db::purchasing::NewReceiptLine,
+        crate::db::purchasing::NewSupplierInvoice,
+        crate::db::purchasing::NewSupplierInvoiceLine,
+    )),
+    tags(
+        (name = "health", description = "Liveness and readiness probes, database checks and metrics"),
+        (name = "auth", description = "Registration and OTP login"),
+        (name = "products", description = "Product catalogue"),
+        (name = "inventory", description = "Warehouses and the stock ledger"),
+        (name = "pricing", description = "Price lists, discounts and coupons"),
+        (name = "orders", description = "Quotes and orders"),
+        (name = "invoices", description = "GST tax invoices"),
+        (name = "payments", description = "Payments, refunds and provider webhooks"),
+        (name = "returns", description = "Customer returns and credit notes"),
+        (name = "purchasing", description = "Suppliers, purchase orders and three-way match"),
+        (name = "realtime", description = "Admin notifications over WebSocket"),
+    )
+)]
+pub struct ApiDoc;
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use regex::Regex;
+    use utoipa::openapi::PathItemType;
+    use utoipa::OpenApi;
+
+    use super::ApiDoc;
+    use crate::api::V1_PREFIX;
+
+    fn method_name(method: &PathItemType) -> &'static str {
+        match method {
+            PathItemType::Get => "get",
+            PathItemType::Post => "post",
+            PathItemType::Put => "put",
+            PathItemType::Delete => "delete",
+            PathItemType::Options => "options",
+            PathItemType::Head => "head",
+            PathItemType::Patch => "patch",
+            PathItemType::Trace => "trace",
+            PathItemType::Connect => "connect",
+        }
+    }
+
+    // axum cannot list the routes of a Router, so they are read from the source that builds it
+    fn routes_in(source: &str, prefix: &str) -> BTreeSet<(String, String)> {
+        let route = Regex::new(r#"\.route\("([^"]+)",\s*(.+)\)"#).unwrap();
+        let method = Regex::new(r"\b(get|post|put|patch|delete)\(").unwrap();
+        let param = Regex::new(r":(\w+)").unwrap();
+
+        let mut operations = BTreeSet::new();
+        for captures in route.captures_iter(source) {
+            let path = format!("{}{}", prefix, param.replace_all(&captures[1], "{$1}"));
+            for m in method.captures_iter(&captures[2]) {
+                operations.insert((m[1].to_string(), path.clone()));
+            }
+        }
+        operations
+    }
+
+    // Unversioned routes plus v1; the deprecated flat aliases are deliberately left out of the spec
+    fn router_operations() -> BTreeSet<(String, String)> {
+        let mut operations = routes_in(include_str!("main.rs"), "");
+        operations.extend(routes_in(include_str!("api/v1.rs"), V1_PREFIX));
+        operations
+    }
+
+    fn documented_operations() -> BTreeSet<(String, String)> {
+        ApiDoc::openapi()
+            .paths
+            .paths
+            .iter()
+            .flat_map(|(path, item)| {
+                item.operations
+                    .keys()
+                    .map(move |method| (method_name(method).to_string(), path.clone()))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn spec_matches_router() {
+        let routed = router_operations();
+        let documented = documented_operations();
+        assert!(!routed.is_empty(), "no routes found in main.rs");
+
+        let undocumented: Vec<_> = routed.difference(&documented).collect();
+        let unrouted: Vec<_> = documented.difference(&routed).collect();
+        assert!(undocumented.is_empty(), "routes missing from the OpenAPI spec: {:?}", undocumented);
+        assert!(unrouted.is_empty(), "documented operations with no route: {:?}", unrouted);
+    }
+}
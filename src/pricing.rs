@@ -0,0 +1,329 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::db::pricing::{ApplicablePrice, Coupon, Discount};
+use crate::db::products::Product;
+
+pub const KIND_PERCENT: &str = "percent";
+pub const KIND_FIXED: &str = "fixed";
+
+// Percent values are basis points: 10000 = 100%
+const BASIS_POINTS: i64 = 10_000;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AppliedDiscount {
+    pub source: String,
+    pub id: i32,
+    pub name: String,
+    pub kind: String,
+    pub value: i64,
+    pub amount_paise: i64,
+}
+
+// Auditable breakdown stored with each order line
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LinePricing {
+    pub product_id: i32,
+    pub quantity: i32,
+    pub list_unit_price_paise: i64,
+    pub unit_price_paise: i64,
+    pub price_list_id: Option<i32>,
+    pub gross_paise: i64,
+    pub discount: Option<AppliedDiscount>,
+    pub coupon: Option<AppliedDiscount>,
+    pub net_paise: i64,
+}
+
+impl LinePricing {
+    pub fn discount_paise(&self) -> i64 {
+        self.discount.as_ref().map_or(0, |d| d.amount_paise) + self.coupon.as_ref().map_or(0, |c| c.amount_paise)
+    }
+}
+
+#[derive(Debug)]
+pub enum PricingError {
+    LineOverflow(i32),
+    TotalOverflow,
+}
+
+impl fmt::Display for PricingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PricingError::LineOverflow(product_id) => write!(f, "Line total for product {} is too large", product_id),
+            PricingError::TotalOverflow => write!(f, "Order total is too large"),
+        }
+    }
+}
+
+pub fn is_valid_kind(kind: &str) -> bool {
+    kind == KIND_PERCENT || kind == KIND_FIXED
+}
+
+// Rounds half up so 0.5 paise goes to the customer consistently. Never more than `amount`
+// for up to 100%, so only the intermediate product needs the wider type.
+fn percent_of(amount: i64, basis_points: i64) -> i64 {
+    let scaled = i128::from(amount) * i128::from(basis_points) + i128::from(BASIS_POINTS / 2);
+    (scaled / i128::from(BASIS_POINTS)) as i64
+}
+
+fn amount_off(kind: &str, value: i64, base: i64) -> i64 {
+    let amount = if kind == KIND_PERCENT { percent_of(base, value.min(BASIS_POINTS)) } else { value };
+    amount.clamp(0, base)
+}
+
+// Customer-specific lists win over general ones, then the largest quantity break
+// the line qualifies for, then the cheapest price.
+fn unit_price(product: &Product, quantity: i32, prices: &[ApplicablePrice]) -> (i64, Option<i32>) {
+    prices
+        .iter()
+        .filter(|p| p.product_id == product.id && p.min_quantity <= quantity)
+        .max_by_key(|p| (p.customer_specific, p.min_quantity, -p.unit_price_paise))
+        .map_or((product.unit_price_paise, None), |p| (p.unit_price_paise, Some(p.price_list_id)))
+}
+
+// Discounts don't stack: the line gets the single one worth the most
+fn best_discount(product_id: i32, quantity: i32, gross: i64, discounts: &[Discount]) -> Option<AppliedDiscount> {
+    discounts
+        .iter()
+        .filter(|d| d.product_id.is_none_or(|id| id == product_id) && d.min_quantity <= quantity)
+        .map(|d| AppliedDiscount {
+            source: "discount".to_string(),
+            id: d.id,
+            name: d.name.clone(),
+            kind: d.kind.clone(),
+            value: d.value,
+            amount_paise: amount_off(&d.kind, d.value, gross),
+        })
+        .filter(|d| d.amount_paise > 0)
+        .max_by_key(|d| d.amount_paise)
+}
+
+pub fn price_lines(
+    lines: &[(Product, i32)],
+    prices: &[ApplicablePrice],
+    discounts: &[Discount],
+    coupon: Option<&Coupon>,
+) -> Result<Vec<LinePricing>, PricingError> {
+    let mut priced: Vec<LinePricing> = lines
+        .iter()
+        .map(|(product, quantity)| {
+            let (unit_price_paise, price_list_id) = unit_price(product, *quantity, prices);
+            let gross_paise = unit_price_paise
+                .checked_mul(i64::from(*quantity))
+                .ok_or(PricingError::LineOverflow(product.id))?;
+            let discount = best_discount(product.id, *quantity, gross_paise, discounts);
+            let net_paise = gross_paise - discount.as_ref().map_or(0, |d| d.amount_paise);
+            Ok(LinePricing {
+                product_id: product.id,
+                quantity: *quantity,
+                list_unit_price_paise: product.unit_price_paise,
+                unit_price_paise,
+                price_list_id,
+                gross_paise,
+                discount,
+                coupon: None,
+                net_paise,
+            })
+        })
+        .collect::<Result<_, _>>()?;
+
+    // Every later sum over the order is bounded by this one
+    priced
+        .iter()
+        .try_fold(0i64, |total, line| total.checked_add(line.gross_paise))
+        .ok_or(PricingError::TotalOverflow)?;
+
+    if let Some(coupon) = coupon {
+        apply_coupon(&mut priced, coupon);
+    }
+
+    Ok(priced)
+}
+
+// Coupons apply to the order as a whole; a fixed amount is split across lines in proportion
+// to their net value. The rounding remainder goes on the last line, and whatever that line
+// can't absorb on earlier ones.
+fn apply_coupon(lines: &mut [LinePricing], coupon: &Coupon) {
+    let order_net: i64 = lines.iter().map(|l| l.net_paise).sum();
+    if order_net == 0 {
+        return;
+    }
+    let total_off = amount_off(&coupon.kind, coupon.value, order_net);
+
+    // Rounded down, so no share exceeds its line and together they never exceed the total
+    let mut shares: Vec<i64> = lines
+        .iter()
+        .map(|l| (i128::from(total_off) * i128::from(l.net_paise) / i128::from(order_net)) as i64)
+        .collect();
+    let mut remaining = total_off - shares.iter().sum::<i64>();
+    for (share, line) in shares.iter_mut().zip(lines.iter()).rev() {
+        let extra = remaining.min(line.net_paise - *share);
+        *share += extra;
+        remaining -= extra;
+    }
+
+    for (line, share) in lines.iter_mut().zip(shares) {
+        line.net_paise -= share;
+        line.coupon = Some(AppliedDiscount {
+            source: "coupon".to_string(),
+            id: coupon.id,
+            name: coupon.code.clone(),
+            kind: coupon.kind.clone(),
+            value: coupon.value,
+            amount_paise: share,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDateTime;
+
+    use super::*;
+
+    fn product(id: i32, unit_price_paise: i64) -> Product {
+        Product {
+            id,
+            admin_id: 1,
+            sku: format!("SKU{}", id),
+            name: format!("Product {}", id),
+            unit_price_paise,
+            stock_on_hand: 100,
+            stock_reserved: 0,
+            created_at: NaiveDateTime::default(),
+            hsn_code: None,
+            gst_rate_bp: 1800,
+        }
+    }
+
+    fn discount(id: i32, product_id: Option<i32>, kind: &str, value: i64, min_quantity: i32) -> Discount {
+        Discount {
+            id,
+            admin_id: 1,
+            name: format!("Discount {}", id),
+            product_id,
+            kind: kind.to_string(),
+            value,
+            min_quantity,
+            valid_from: None,
+            valid_to: None,
+            active: true,
+            created_at: NaiveDateTime::default(),
+        }
+    }
+
+    fn coupon(kind: &str, value: i64) -> Coupon {
+        Coupon {
+            id: 7,
+            admin_id: 1,
+            code: "SAVE".to_string(),
+            kind: kind.to_string(),
+            value,
+            max_uses: None,
+            used_count: 0,
+            valid_from: None,
+            valid_to: None,
+            active: true,
+            created_at: NaiveDateTime::default(),
+        }
+    }
+
+    fn coupon_amounts(lines: &[LinePricing]) -> Vec<i64> {
+        lines.iter().map(|l| l.coupon.as_ref().map_or(0, |c| c.amount_paise)).collect()
+    }
+
+    #[test]
+    fn price_lines_uses_best_price_list_and_discount() {
+        let price = |price_list_id, min_quantity, unit_price_paise, customer_specific| ApplicablePrice {
+            price_list_id,
+            product_id: 1,
+            min_quantity,
+            unit_price_paise,
+            customer_specific,
+        };
+        let prices = [price(1, 1, 900, false), price(2, 10, 800, false), price(3, 1, 950, true)];
+        let discounts = [discount(1, Some(1), KIND_PERCENT, 1000, 1)];
+
+        let priced = price_lines(&[(product(1, 1000), 10), (product(2, 500), 2)], &prices, &discounts, None).unwrap();
+
+        // The customer's own list wins even though the general quantity break is cheaper
+        assert_eq!(priced[0].unit_price_paise, 950);
+        assert_eq!(priced[0].price_list_id, Some(3));
+        assert_eq!(priced[0].gross_paise, 9500);
+        assert_eq!(priced[0].discount_paise(), 950);
+        assert_eq!(priced[0].net_paise, 8550);
+
+        assert_eq!(priced[1].unit_price_paise, 500);
+        assert_eq!(priced[1].price_list_id, None);
+        assert!(priced[1].discount.is_none());
+        assert_eq!(priced[1].net_paise, 1000);
+    }
+
+    #[test]
+    fn price_lines_rejects_totals_that_overflow() {
+        let huge = product(1, i64::MAX / 2);
+        let result = price_lines(&[(huge.clone(), 3)], &[], &[], None);
+        assert!(matches!(result, Err(PricingError::LineOverflow(1))));
+
+        let result = price_lines(&[(huge.clone(), 2), (product(2, 500), 1)], &[], &[], None);
+        assert!(matches!(result, Err(PricingError::TotalOverflow)));
+
+        // Percentages of very large amounts are computed without overflowing
+        let priced = price_lines(&[(huge, 1)], &[], &[], Some(&coupon(KIND_PERCENT, 5000))).unwrap();
+        assert_eq!(priced[0].net_paise, i64::MAX / 4);
+    }
+
+    #[test]
+    fn best_discount_picks_largest_that_applies() {
+        let discounts = [
+            discount(1, None, KIND_PERCENT, 500, 1),
+            discount(2, Some(1), KIND_FIXED, 300, 1),
+            discount(3, Some(1), KIND_PERCENT, 5000, 5),
+            discount(4, Some(2), KIND_FIXED, 900, 1),
+        ];
+
+        let best = best_discount(1, 2, 2000, &discounts).unwrap();
+        assert_eq!((best.id, best.amount_paise), (2, 300));
+        let best = best_discount(1, 5, 5000, &discounts).unwrap();
+        assert_eq!((best.id, best.amount_paise), (3, 2500));
+        // A fixed amount can't take a line below zero
+        let best = best_discount(2, 1, 400, &discounts).unwrap();
+        assert_eq!((best.id, best.amount_paise), (4, 400));
+        assert!(best_discount(1, 1, 0, &discounts).is_none());
+    }
+
+    #[test]
+    fn apply_coupon_splits_fixed_amount() {
+        let line = |product_id, net_paise| LinePricing {
+            product_id,
+            quantity: 1,
+            list_unit_price_paise: net_paise,
+            unit_price_paise: net_paise,
+            price_list_id: None,
+            gross_paise: net_paise,
+            discount: None,
+            coupon: None,
+            net_paise,
+        };
+
+        let mut lines = vec![line(1, 3000), line(2, 1000)];
+        apply_coupon(&mut lines, &coupon(KIND_FIXED, 1000));
+        assert_eq!(coupon_amounts(&lines), [750, 250]);
+        assert_eq!(lines[0].net_paise, 2250);
+
+        // Each one-paise line rounds to nothing; the last can only take one of the two
+        let mut lines = vec![line(1, 1), line(2, 1), line(3, 1)];
+        apply_coupon(&mut lines, &coupon(KIND_FIXED, 2));
+        assert_eq!(coupon_amounts(&lines), [0, 1, 1]);
+        assert_eq!(lines.iter().map(|l| l.net_paise).sum::<i64>(), 1);
+
+        let mut lines = vec![line(1, 333), line(2, 333), line(3, 334)];
+        apply_coupon(&mut lines, &coupon(KIND_PERCENT, 1000));
+        assert_eq!(coupon_amounts(&lines).iter().sum::<i64>(), 100);
+
+        let mut lines = vec![line(1, 100)];
+        apply_coupon(&mut lines, &coupon(KIND_FIXED, 500));
+        assert_eq!(lines[0].net_paise, 0);
+    }
+}
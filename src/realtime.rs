@@ -0,0 +1,203 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use futures_util::StreamExt;
+use redis::aio::ConnectionManager;
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use tokio::sync::broadcast;
+use tracing::{error, info, warn};
+
+use crate::db::inventory::{LowStock, Warehouse};
+use crate::events::{OrderStatusChanged, ReceivedEvent, StockMoved};
+use crate::shutdown::Shutdown;
+use crate::worker::{Handler, HandlerError};
+
+// Each admin has its own channel, e.g. `notifications:admin:42`
+const CHANNEL_PREFIX: &str = "notifications:admin:";
+const HUB_CAPACITY: usize = 256;
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+fn channel_for(admin_id: i32) -> String {
+    format!("{}{}", CHANNEL_PREFIX, admin_id)
+}
+
+// What an admin's WebSocket receives, as JSON tagged with `type`
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Notification {
+    OrderStatus {
+        order_id: i32,
+        status: String,
+    },
+    LowStock {
+        product_id: i32,
+        sku: String,
+        warehouse_id: i32,
+        warehouse_code: String,
+        available: i64,
+        min_quantity: i32,
+    },
+}
+
+// Publishes notifications to Redis so every server instance can pass them on
+#[derive(Clone)]
+pub struct NotificationPublisher {
+    connection: ConnectionManager,
+}
+
+impl NotificationPublisher {
+    pub async fn connect(redis_url: &str) -> Result<Self, redis::RedisError> {
+        let client = redis::Client::open(redis_url)?;
+        Ok(Self { connection: ConnectionManager::new(client).await? })
+    }
+
+    pub async fn publish(&self, admin_id: i32, notification: &Notification) -> Result<(), redis::RedisError> {
+        let payload = serde_json::to_string(notification).expect("notifications always serialize");
+        let mut connection = self.connection.clone();
+        let _receivers: i64 = connection.publish(channel_for(admin_id), payload).await?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct AdminMessage {
+    pub admin_id: i32,
+    pub payload: Arc<str>,
+}
+
+// Per-instance fan-out: one Redis subscription feeds every WebSocket connected here,
+// and each connection keeps only the messages for its own admin.
+#[derive(Clone)]
+pub struct NotificationHub {
+    sender: broadcast::Sender<AdminMessage>,
+}
+
+impl NotificationHub {
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(HUB_CAPACITY);
+        Self { sender }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<AdminMessage> {
+        self.sender.subscribe()
+    }
+}
+
+// Forwards every admin channel from Redis into the hub, resubscribing whenever Redis goes away.
+// Messages published while disconnected are lost; pub/sub has no replay.
+pub async fn run_redis_subscriber(redis_url: String, hub: NotificationHub, shutdown: Shutdown) {
+    loop {
+        tokio::select! {
+            result = subscribe(&redis_url, &hub) => {
+                if let Err(e) = result {
+                    error!("Redis subscriber error: {}", e);
+                }
+            }
+            _ = shutdown.requested() => return,
+        }
+        tokio::select! {
+            _ = tokio::time::sleep(RECONNECT_DELAY) => {}
+            _ = shutdown.requested() => return,
+        }
+    }
+}
+
+async fn subscribe(redis_url: &str, hub: &NotificationHub) -> Result<(), redis::RedisError> {
+    let client = redis::Client::open(redis_url)?;
+    let mut pubsub = client.get_async_connection().await?.into_pubsub();
+    pubsub.psubscribe(format!("{}*", CHANNEL_PREFIX)).await?;
+    info!("Subscribed to Redis notifications");
+
+    let mut messages = pubsub.on_message();
+    while let Some(message) = messages.next().await {
+        let admin_id = message
+            .get_channel_name()
+            .strip_prefix(CHANNEL_PREFIX)
+            .and_then(|id| id.parse().ok());
+        let (Some(admin_id), Ok(payload)) = (admin_id, message.get_payload::<String>()) else {
+            continue;
+        };
+        // No receivers just means nobody is connected to this instance
+        let _ = hub.sender.send(AdminMessage { admin_id, payload: payload.into() });
+    }
+
+    warn!("Redis subscription closed");
+    Ok(())
+}
+
+pub struct OrderStatusNotifier {
+    pub publisher: NotificationPublisher,
+}
+
+#[async_trait]
+impl Handler for OrderStatusNotifier {
+    type Event = OrderStatusChanged;
+    const QUEUE: &'static str = "realtime.order_status";
+
+    async fn handle(&self, event: ReceivedEvent<OrderStatusChanged>) -> Result<(), HandlerError> {
+        let notification = Notification::OrderStatus {
+            order_id: event.data.order_id,
+            status: event.data.status,
+        };
+        self.publisher
+            .publish(event.data.admin_id, &notification)
+            .await
+            .map_err(|e| HandlerError::Retry(format!("Redis error: {}", e)))
+    }
+}
+
+// Alerts when a movement takes available stock below the warehouse threshold. Stock is read
+// after the fact, so a later movement may already be included; only the crossing is reported
+// so an admin is not alerted again for every sale while stock stays low.
+pub struct LowStockNotifier {
+    pub pool: PgPool,
+    pub publisher: NotificationPublisher,
+}
+
+#[async_trait]
+impl Handler for LowStockNotifier {
+    type Event = StockMoved;
+    const QUEUE: &'static str = "realtime.low_stock";
+
+    async fn handle(&self, event: ReceivedEvent<StockMoved>) -> Result<(), HandlerError> {
+        let moved = event.data;
+        let available_delta = i64::from(moved.on_hand_delta) - i64::from(moved.reserved_delta);
+        if available_delta >= 0 {
+            return Ok(());
+        }
+
+        let db_error = |e: sqlx::Error| HandlerError::Retry(format!("DB error: {:?}", e));
+        let Some(stock) = LowStock::fetch_threshold(&self.pool, moved.product_id, moved.warehouse_id)
+            .await
+            .map_err(db_error)?
+        else {
+            return Ok(());
+        };
+        let min_quantity = i64::from(stock.min_quantity);
+        if stock.available >= min_quantity || stock.available - available_delta < min_quantity {
+            return Ok(());
+        }
+
+        let Some(warehouse) = Warehouse::fetch_warehouse(&self.pool, moved.warehouse_id)
+            .await
+            .map_err(db_error)?
+        else {
+            return Ok(());
+        };
+        let notification = Notification::LowStock {
+            product_id: stock.product_id,
+            sku: stock.sku,
+            warehouse_id: stock.warehouse_id,
+            warehouse_code: stock.warehouse_code,
+            available: stock.available,
+            min_quantity: stock.min_quantity,
+        };
+        self.publisher
+            .publish(warehouse.admin_id, &notification)
+            .await
+            .map_err(|e| HandlerError::Retry(format!("Redis error: {}", e)))
+    }
+}
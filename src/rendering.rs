@@ -0,0 +1,301 @@
+use askama::Template;
+use printpdf::{BuiltinFont, IndirectFontRef, Mm, PdfDocument, PdfLayerReference};
+
+use crate::db::invoices::Invoice;
+use crate::gst;
+
+const PAGE_WIDTH: f32 = 210.0;
+const PAGE_HEIGHT: f32 = 297.0;
+const MARGIN: f32 = 15.0;
+const LINE_HEIGHT: f32 = 6.0;
+
+// Amounts are kept in paise everywhere; only documents show rupees
+pub fn format_paise(paise: i64) -> String {
+    let sign = if paise < 0 { "-" } else { "" };
+    let paise = paise.abs();
+    format!("{}{}.{:02}", sign, paise / 100, paise % 100)
+}
+
+fn format_rate(rate_bp: i32) -> String {
+    if rate_bp % 100 == 0 {
+        format!("{}%", rate_bp / 100)
+    } else {
+        format!("{}.{:02}%", rate_bp / 100, rate_bp % 100)
+    }
+}
+
+fn state_label(code: &str) -> String {
+    match gst::state_name(code) {
+        Some(name) => format!("{} - {}", code, name),
+        None => code.to_string(),
+    }
+}
+
+pub struct InvoiceLineView {
+    pub name: String,
+    pub sku: String,
+    pub hsn_code: String,
+    pub quantity: i32,
+    pub unit_price: String,
+    pub discount: String,
+    pub taxable: String,
+    pub gst_rate: String,
+    pub cgst: String,
+    pub sgst: String,
+    pub igst: String,
+}
+
+#[derive(Template)]
+#[template(path = "invoice.html")]
+struct InvoiceHtml<'a> {
+    app_name: &'a str,
+    invoice: &'a Invoice,
+    issued_on: String,
+    supply_label: &'static str,
+    intra_state: bool,
+    origin_state: String,
+    place_of_supply: String,
+    lines: Vec<InvoiceLineView>,
+    taxable: String,
+    cgst: String,
+    sgst: String,
+    igst: String,
+    total: String,
+}
+
+fn line_views(invoice: &Invoice) -> Vec<InvoiceLineView> {
+    invoice
+        .lines
+        .iter()
+        .map(|line| InvoiceLineView {
+            name: line.name.clone(),
+            sku: line.sku.clone(),
+            hsn_code: line.hsn_code.clone().unwrap_or_default(),
+            quantity: line.quantity,
+            unit_price: format_paise(line.unit_price_paise),
+            discount: format_paise(line.discount_paise),
+            taxable: format_paise(line.taxable_paise),
+            gst_rate: format_rate(line.gst_rate_bp),
+            cgst: format_paise(line.cgst_paise),
+            sgst: format_paise(line.sgst_paise),
+            igst: format_paise(line.igst_paise),
+        })
+        .collect()
+}
+
+fn supply_label(invoice: &Invoice) -> &'static str {
+    if invoice.supply_type == gst::SUPPLY_INTRA_STATE {
+        "Intra-state (CGST + SGST)"
+    } else {
+        "Inter-state (IGST)"
+    }
+}
+
+pub fn render_invoice_html(invoice: &Invoice, app_name: &str) -> Result<String, askama::Error> {
+    InvoiceHtml {
+        app_name,
+        invoice,
+        issued_on: invoice.issued_at.format("%d-%m-%Y").to_string(),
+        supply_label: supply_label(invoice),
+        intra_state: invoice.supply_type == gst::SUPPLY_INTRA_STATE,
+        origin_state: state_label(&invoice.origin_state_code),
+        place_of_supply: state_label(&invoice.place_of_supply),
+        lines: line_views(invoice),
+        taxable: format_paise(invoice.taxable_paise),
+        cgst: format_paise(invoice.cgst_paise),
+        sgst: format_paise(invoice.sgst_paise),
+        igst: format_paise(invoice.igst_paise),
+        total: format_paise(invoice.total_paise),
+    }
+    .render()
+}
+
+// Writes rows top-down and starts a new A4 page when the current one is full
+struct PdfWriter {
+    doc: printpdf::PdfDocumentReference,
+    layer: PdfLayerReference,
+    regular: IndirectFontRef,
+    bold: IndirectFontRef,
+    y: f32,
+}
+
+impl PdfWriter {
+    fn text(&self, text: &str, size: f32, x: f32, bold: bool) {
+        let font = if bold { &self.bold } else { &self.regular };
+        self.layer.use_text(text, size, Mm(x), Mm(self.y), font);
+    }
+
+    fn next_line(&mut self) {
+        self.y -= LINE_HEIGHT;
+        if self.y < MARGIN {
+            let (page, layer) = self.doc.add_page(Mm(PAGE_WIDTH), Mm(PAGE_HEIGHT), "Layer 1");
+            self.layer = self.doc.get_page(page).get_layer(layer);
+            self.y = PAGE_HEIGHT - MARGIN;
+        }
+    }
+
+    fn row(&mut self, columns: &[(f32, &str)], bold: bool) {
+        for (x, text) in columns {
+            self.text(text, 8.0, *x, bold);
+        }
+        self.next_line();
+    }
+}
+
+// Uses the PDF built-in Helvetica so no font files or external services are needed.
+// Built-in fonts only cover Latin-1, hence "INR" rather than the rupee sign.
+pub fn render_invoice_pdf(invoice: &Invoice, app_name: &str) -> Result<Vec<u8>, printpdf::Error> {
+    let title = format!("Tax Invoice {}", invoice.invoice_number);
+    let (doc, page, layer) = PdfDocument::new(&title, Mm(PAGE_WIDTH), Mm(PAGE_HEIGHT), "Layer 1");
+    let regular = doc.add_builtin_font(BuiltinFont::Helvetica)?;
+    let bold = doc.add_builtin_font(BuiltinFont::HelveticaBold)?;
+    let layer = doc.get_page(page).get_layer(layer);
+
+    let mut pdf = PdfWriter { doc, layer, regular, bold, y: PAGE_HEIGHT - MARGIN };
+
+    pdf.text(&format!("{} - Tax Invoice", app_name), 16.0, MARGIN, true);
+    pdf.next_line();
+    pdf.next_line();
+    for line in [
+        format!("Invoice No: {}", invoice.invoice_number),
+        format!("Date: {}", invoice.issued_at.format("%d-%m-%Y")),
+        format!("Supply: {}", supply_label(invoice)),
+        format!(
+            "Seller: {}, PIN {} ({}){}",
+            invoice.seller_name,
+            invoice.seller_pincode,
+            state_label(&invoice.origin_state_code),
+            invoice.seller_gstin.as_deref().map(|gstin| format!(", GSTIN {}", gstin)).unwrap_or_default()
+        ),
+        format!(
+            "Buyer: {}, PIN {} (Place of supply: {})",
+            invoice.buyer_email,
+            invoice.buyer_pincode,
+            state_label(&invoice.place_of_supply)
+        ),
+    ] {
+        pdf.text(&line, 10.0, MARGIN, false);
+        pdf.next_line();
+    }
+    pdf.next_line();
+
+    let columns = [MARGIN, 22.0, 72.0, 88.0, 100.0, 118.0, 136.0, 156.0, 170.0, 182.0, 194.0];
+    let headers = ["#", "Item", "HSN", "Qty", "Rate", "Disc.", "Taxable", "GST", "CGST", "SGST", "IGST"];
+    let header_row: Vec<(f32, &str)> = columns.iter().copied().zip(headers).collect();
+    pdf.row(&header_row, true);
+
+    for (i, line) in line_views(invoice).iter().enumerate() {
+        let index = (i + 1).to_string();
+        let quantity = line.quantity.to_string();
+        let item: String = line.name.chars().take(28).collect();
+        let cells = [
+            index.as_str(),
+            item.as_str(),
+            line.hsn_code.as_str(),
+            quantity.as_str(),
+            line.unit_price.as_str(),
+            line.discount.as_str(),
+            line.taxable.as_str(),
+            line.gst_rate.as_str(),
+            line.cgst.as_str(),
+            line.sgst.as_str(),
+            line.igst.as_str(),
+        ];
+        let row: Vec<(f32, &str)> = columns.iter().copied().zip(cells).collect();
+        pdf.row(&row, false);
+    }
+    pdf.next_line();
+
+    // Only the taxes that apply to this supply are listed
+    let taxes = if invoice.supply_type == gst::SUPPLY_INTRA_STATE {
+        vec![("CGST", invoice.cgst_paise), ("SGST", invoice.sgst_paise)]
+    } else {
+        vec![("IGST", invoice.igst_paise)]
+    };
+    let totals = std::iter::once(("Taxable value", invoice.taxable_paise))
+        .chain(taxes)
+        .chain(std::iter::once(("Invoice total (INR)", invoice.total_paise)));
+    for (label, paise) in totals {
+        let amount = format_paise(paise);
+        pdf.row(&[(136.0, label), (182.0, amount.as_str())], label.starts_with("Invoice"));
+    }
+
+    pdf.doc.save_to_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+    use sqlx::types::Json;
+
+    use super::{format_paise, format_rate, render_invoice_html};
+    use crate::db::invoices::{Invoice, InvoiceLine};
+    use crate::gst;
+
+    fn invoice(supply_type: &str, place_of_supply: &str) -> Invoice {
+        let intra = supply_type == gst::SUPPLY_INTRA_STATE;
+        Invoice {
+            id: 1,
+            admin_id: 1,
+            order_id: 1,
+            invoice_number: "INV/2627/000001".to_string(),
+            financial_year: "2026-27".to_string(),
+            sequence_number: 1,
+            seller_name: "Acme Traders".to_string(),
+            seller_pincode: "560001".to_string(),
+            seller_gstin: Some("29ABCDE1234F1Z5".to_string()),
+            origin_state_code: "29".to_string(),
+            buyer_email: "buyer@example.com".to_string(),
+            buyer_pincode: "560002".to_string(),
+            place_of_supply: place_of_supply.to_string(),
+            supply_type: supply_type.to_string(),
+            taxable_paise: 10000,
+            cgst_paise: if intra { 900 } else { 0 },
+            sgst_paise: if intra { 900 } else { 0 },
+            igst_paise: if intra { 0 } else { 1800 },
+            total_paise: 11800,
+            lines: Json(vec![InvoiceLine {
+                product_id: 1,
+                sku: "SKU-1".to_string(),
+                name: "Widget".to_string(),
+                hsn_code: Some("8471".to_string()),
+                quantity: 2,
+                unit_price_paise: 5000,
+                discount_paise: 0,
+                taxable_paise: 10000,
+                gst_rate_bp: 1800,
+                cgst_paise: if intra { 900 } else { 0 },
+                sgst_paise: if intra { 900 } else { 0 },
+                igst_paise: if intra { 0 } else { 1800 },
+            }]),
+            issued_at: NaiveDate::from_ymd_opt(2026, 10, 19).unwrap().and_hms_opt(10, 0, 0).unwrap(),
+        }
+    }
+
+    #[test]
+    fn formats_paise_and_rates() {
+        assert_eq!(format_paise(123456), "1234.56");
+        assert_eq!(format_paise(5), "0.05");
+        assert_eq!(format_paise(-250), "-2.50");
+        assert_eq!(format_rate(1800), "18%");
+        assert_eq!(format_rate(25), "0.25%");
+        assert_eq!(format_rate(1250), "12.50%");
+    }
+
+    #[test]
+    fn html_lists_the_taxes_for_the_supply_type() {
+        let intra = render_invoice_html(&invoice(gst::SUPPLY_INTRA_STATE, "29"), "X-ERP").unwrap();
+        assert!(intra.contains("INV/2627/000001"));
+        assert!(intra.contains("GSTIN 29ABCDE1234F1Z5"));
+        assert!(intra.contains("<th>CGST</th><td class=\"num\">9.00</td>"));
+        assert!(intra.contains("<th>SGST</th><td class=\"num\">9.00</td>"));
+        assert!(!intra.contains("<th>IGST</th>"));
+
+        let inter = render_invoice_html(&invoice(gst::SUPPLY_INTER_STATE, "27"), "X-ERP").unwrap();
+        assert!(inter.contains("INV/2627/000001"));
+        assert!(inter.contains("GSTIN 29ABCDE1234F1Z5"));
+        assert!(inter.contains("<th>IGST</th><td class=\"num\">18.00</td>"));
+        assert!(!inter.contains("<th>CGST</th>"));
+        assert!(!inter.contains("<th>SGST</th>"));
+    }
+}
@@ -0,0 +1,127 @@
+use std::future::Future;
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+
+use tokio::time::Instant;
+use tokio_util::sync::CancellationToken;
+use tokio_util::task::TaskTracker;
+use tracing::{info, warn};
+
+// Shared by everything that has to stop cleanly on SIGTERM/SIGINT: the HTTP server stops
+// accepting connections, background loops finish their current pass, and tracked tasks
+// (e.g. an OTP email being sent) are waited for. Everything together gets one grace
+// period from the signal, after which the process exits anyway.
+#[derive(Clone)]
+pub struct Shutdown {
+    token: CancellationToken,
+    // Cancelled once the HTTP servers have finished draining
+    drained: CancellationToken,
+    tasks: TaskTracker,
+    grace: Duration,
+    deadline: Arc<OnceLock<Instant>>,
+}
+
+impl Shutdown {
+    pub fn new(grace: Duration) -> Self {
+        Self {
+            token: CancellationToken::new(),
+            drained: CancellationToken::new(),
+            tasks: TaskTracker::new(),
+            grace,
+            deadline: Arc::new(OnceLock::new()),
+        }
+    }
+
+    // Triggers shutdown on the first SIGTERM or SIGINT
+    pub fn listen_for_signals(&self) {
+        let shutdown = self.clone();
+        tokio::spawn(async move {
+            signal().await;
+            shutdown.trigger();
+        });
+    }
+
+    pub fn trigger(&self) {
+        if self.deadline.set(Instant::now() + self.grace).is_ok() {
+            info!(grace_secs = self.grace.as_secs(), "Shutting down");
+        }
+        self.token.cancel();
+    }
+
+    pub fn is_requested(&self) -> bool {
+        self.token.is_cancelled()
+    }
+
+    // Resolves once shutdown has been triggered
+    pub fn requested(&self) -> impl Future<Output = ()> + Send + 'static {
+        let token = self.token.clone();
+        async move { token.cancelled().await }
+    }
+
+    // Called once the servers have stopped, so no request can record anything more
+    pub fn mark_drained(&self) {
+        self.drained.cancel();
+    }
+
+    // Resolves once the servers have finished their in-flight requests
+    pub fn drained(&self) -> impl Future<Output = ()> + Send + 'static {
+        let drained = self.drained.clone();
+        async move { drained.cancelled().await }
+    }
+
+    // Resolves once the grace period after the signal has run out
+    pub fn grace_expired(&self) -> impl Future<Output = ()> + Send + 'static {
+        let shutdown = self.clone();
+        async move {
+            shutdown.token.cancelled().await;
+            let deadline = *shutdown.deadline.get_or_init(|| Instant::now() + shutdown.grace);
+            tokio::time::sleep_until(deadline).await;
+        }
+    }
+
+    // Spawns work that must not be cut off mid-way by a deploy
+    pub fn spawn<F>(&self, task: F)
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        self.tasks.spawn(task);
+    }
+
+    // Waits for every tracked task, up to the end of the grace period
+    pub async fn wait_for_tasks(&self) {
+        self.tasks.close();
+        tokio::select! {
+            _ = self.tasks.wait() => {}
+            _ = self.grace_expired() => warn!(tasks = self.tasks.len(), "Grace period over, abandoning tasks"),
+        }
+    }
+}
+
+async fn signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            warn!(error = %e, "Cannot listen for SIGINT");
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut sigterm) => {
+                sigterm.recv().await;
+            }
+            Err(e) => {
+                warn!(error = %e, "Cannot listen for SIGTERM");
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+    }
+}
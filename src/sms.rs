@@ -0,0 +1,76 @@
+use reqwest::Client;
+use std::fmt;
+use tracing::field::Empty;
+use tracing::{instrument, Span};
+
+use crate::config::Config;
+
+const TWILIO_HOST: &str = "api.twilio.com";
+
+#[derive(Debug)]
+pub enum SmsError {
+    NotConfigured,
+    Request(String),
+    Rejected(String),
+}
+
+impl SmsError {
+    // Underlying HTTP error or provider response, for logs only
+    pub fn detail(&self) -> &str {
+        match self {
+            SmsError::NotConfigured => "twilio.sid, twilio.auth_token and twilio.phone_number must be set",
+            SmsError::Request(detail) | SmsError::Rejected(detail) => detail,
+        }
+    }
+}
+
+impl fmt::Display for SmsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SmsError::NotConfigured => write!(f, "SMS is not configured"),
+            SmsError::Request(_) => write!(f, "Failed to reach SMS provider"),
+            SmsError::Rejected(_) => write!(f, "SMS provider rejected the message"),
+        }
+    }
+}
+
+// Sends a text message through Twilio's REST API. The client span is recorded here, but
+// trace context stays inside our own services and isn't sent to a third party.
+#[instrument(
+    name = "POST",
+    skip_all,
+    fields(
+        otel.kind = "client",
+        http.request.method = "POST",
+        server.address = TWILIO_HOST,
+        http.response.status_code = Empty,
+    )
+)]
+pub async fn send_sms(config: &Config, to: &str, body: &str) -> Result<(), SmsError> {
+    let (Some(twilio_sid), Some(twilio_token), Some(twilio_from)) =
+        (&config.twilio_sid, &config.twilio_auth_token, &config.twilio_phone_number)
+    else {
+        return Err(SmsError::NotConfigured);
+    };
+
+    let params = [("To", to), ("From", twilio_from.as_str()), ("Body", body)];
+
+    let res = Client::new()
+        .post(format!("https://{}/2010-04-01/Accounts/{}/Messages.json", TWILIO_HOST, twilio_sid))
+        .basic_auth(twilio_sid, Some(twilio_token.expose()))
+        .form(&params)
+        .send()
+        .await
+        .map_err(|e| SmsError::Request(format!("{:?}", e)))?;
+
+    Span::current().record("http.response.status_code", res.status().as_u16());
+    if !res.status().is_success() {
+        let text = res
+            .text()
+            .await
+            .unwrap_or_else(|e| format!("Failed to read response text: {:?}", e));
+        return Err(SmsError::Rejected(text));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,268 @@
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime};
+
+use axum::http::HeaderMap;
+use opentelemetry::propagation::Extractor;
+use opentelemetry::trace::{Span as _, SpanKind, Tracer as _, TracerProvider as _};
+use opentelemetry::{global, KeyValue};
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::trace::{Tracer, TracerProvider};
+use opentelemetry_sdk::{runtime, Resource};
+use tracing::field::{Field, Visit};
+use tracing::{error, info, Event, Level, Span, Subscriber};
+use tracing_opentelemetry::{OpenTelemetrySpanExt, OtelData, PreSampledTracer};
+use tracing_subscriber::filter::{LevelFilter, Targets};
+use tracing_subscriber::layer::{Context as LayerContext, SubscriberExt};
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::Layer;
+
+use crate::config::Config;
+use crate::middleware::logging;
+
+// sqlx reports every statement it runs as an event on this target once the statement finishes
+const SQLX_QUERY_TARGET: &str = "sqlx::query";
+
+// Keeps the tracer provider alive; `shutdown` flushes spans still waiting in the batch
+pub struct Telemetry {
+    provider: Option<TracerProvider>,
+}
+
+impl Telemetry {
+    pub fn shutdown(self) {
+        if let Some(provider) = self.provider {
+            if let Err(e) = provider.shutdown() {
+                error!(error = %e, "Error flushing traces");
+            }
+        }
+    }
+}
+
+// Installs the global subscriber: JSON logs on stdout always, and OTLP trace export when
+// OTEL_EXPORTER_OTLP_ENDPOINT is set. W3C trace context is propagated either way, so a
+// caller's `traceparent` still reaches RabbitMQ consumers when this instance exports nothing.
+pub fn init(config: &Config) -> Telemetry {
+    global::set_text_map_propagator(TraceContextPropagator::new());
+
+    let exporter = config.otel_endpoint.as_deref().map(|endpoint| {
+        opentelemetry_otlp::SpanExporter::builder()
+            .with_http()
+            .with_endpoint(format!("{}/v1/traces", endpoint.trim_end_matches('/')))
+            .build()
+    });
+    let (provider, exporter_error) = match exporter {
+        Some(Ok(exporter)) => {
+            let provider = TracerProvider::builder()
+                .with_batch_exporter(exporter, runtime::Tokio)
+                .with_resource(Resource::new([KeyValue::new("service.name", config.otel_service_name.clone())]))
+                .build();
+            (Some(provider), None)
+        }
+        Some(Err(e)) => (None, Some(e)),
+        None => (None, None),
+    };
+
+    tracing_subscriber::registry()
+        .with(logging::layer())
+        .with(provider.as_ref().map(trace_layers))
+        .init();
+
+    match (&provider, exporter_error) {
+        (Some(_), _) => info!(endpoint = config.otel_endpoint.as_deref(), "Exporting traces over OTLP"),
+        (None, Some(e)) => error!(error = %e, "Failed to create OTLP exporter, traces will not be exported"),
+        (None, None) => {}
+    }
+
+    Telemetry { provider }
+}
+
+// Request and other spans, plus a client span for every statement sqlx runs
+fn trace_layers<S>(provider: &TracerProvider) -> impl Layer<S>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    let spans = tracing_opentelemetry::layer()
+        .with_tracer(provider.tracer(env!("CARGO_PKG_NAME")))
+        .with_filter(LevelFilter::INFO);
+    let queries = QuerySpans { tracer: provider.tracer("sqlx") }
+        // Spans must pass the filter too, or the layer can't see which one a query ran in
+        .with_filter(Targets::new().with_target(SQLX_QUERY_TARGET, Level::DEBUG).with_default(Level::INFO));
+    spans.and_then(queries)
+}
+
+// Turns the statement events sqlx emits into client spans under whichever span ran the query.
+// The event arrives when the statement has finished, so the span is back-dated by its duration.
+struct QuerySpans {
+    tracer: Tracer,
+}
+
+#[derive(Default)]
+struct QueryFields {
+    summary: String,
+    statement: String,
+    rows_affected: u64,
+    rows_returned: u64,
+    elapsed_secs: f64,
+}
+
+impl Visit for QueryFields {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        match field.name() {
+            "summary" => self.summary = value.to_string(),
+            "db.statement" => self.statement = value.trim().to_string(),
+            _ => {}
+        }
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        match field.name() {
+            "rows_affected" => self.rows_affected = value,
+            "rows_returned" => self.rows_returned = value,
+            _ => {}
+        }
+    }
+
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        if field.name() == "elapsed_secs" {
+            self.elapsed_secs = value;
+        }
+    }
+
+    fn record_debug(&mut self, _field: &Field, _value: &dyn std::fmt::Debug) {}
+}
+
+impl<S> Layer<S> for QuerySpans
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_event(&self, event: &Event<'_>, ctx: LayerContext<'_, S>) {
+        if event.metadata().target() != SQLX_QUERY_TARGET {
+            return;
+        }
+        let mut fields = QueryFields::default();
+        event.record(&mut fields);
+
+        // Queries run outside any span (startup checks, background jobs) start their own trace
+        let parent = ctx
+            .event_span(event)
+            .and_then(|span| {
+                let mut extensions = span.extensions_mut();
+                extensions.get_mut::<OtelData>().map(|data| self.tracer.sampled_context(data))
+            })
+            .unwrap_or_default();
+
+        let finished = SystemTime::now();
+        let started = finished
+            .checked_sub(Duration::from_secs_f64(fields.elapsed_secs.max(0.0)))
+            .unwrap_or(finished);
+        // Only the SQL text is recorded; bind parameters never reach sqlx's log events
+        let statement = if fields.statement.is_empty() { fields.summary.clone() } else { fields.statement };
+        let mut span = self
+            .tracer
+            .span_builder(fields.summary)
+            .with_kind(SpanKind::Client)
+            .with_start_time(started)
+            .with_attributes([
+                KeyValue::new("db.system", "postgresql"),
+                KeyValue::new("db.statement", statement),
+                KeyValue::new("db.rows_affected", fields.rows_affected as i64),
+                KeyValue::new("db.rows_returned", fields.rows_returned as i64),
+            ])
+            .start_with_context(&self.tracer, &parent);
+        span.end_with_timestamp(finished);
+    }
+}
+
+struct HeaderExtractor<'a>(&'a HeaderMap);
+
+impl Extractor for HeaderExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|value| value.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(|name| name.as_str()).collect()
+    }
+}
+
+// Continues the caller's trace when the request carries `traceparent`
+pub fn set_parent_from_headers(span: &Span, headers: &HeaderMap) {
+    let parent = global::get_text_map_propagator(|propagator| propagator.extract(&HeaderExtractor(headers)));
+    span.set_parent(parent);
+}
+
+// Continues a trace from context carried outside HTTP, e.g. in AMQP headers
+pub fn set_parent_from_map(span: &Span, carrier: &HashMap<String, String>) {
+    let parent = global::get_text_map_propagator(|propagator| propagator.extract(carrier));
+    span.set_parent(parent);
+}
+
+// `traceparent`/`tracestate` for the current span, to hand on to our own next hop (outbox, AMQP).
+// Empty when tracing is off or there is no span.
+pub fn current_context() -> HashMap<String, String> {
+    let mut carrier = HashMap::new();
+    let context = Span::current().context();
+    global::get_text_map_propagator(|propagator| propagator.inject_context(&context, &mut carrier));
+    carrier
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use amqprs::BasicProperties;
+    use axum::body::{to_bytes, Body};
+    use axum::http::Request;
+    use axum::middleware::from_fn;
+    use axum::routing::get;
+    use axum::{Json, Router};
+    use opentelemetry::trace::SpanKind;
+    use opentelemetry_sdk::testing::trace::InMemorySpanExporter;
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::{events, worker};
+
+    // What a handler does when it records an event: query, then hand the trace context on
+    async fn record_event() -> Json<HashMap<String, String>> {
+        tracing::debug!(
+            target: SQLX_QUERY_TARGET,
+            summary = "SELECT * FROM orders …",
+            db.statement = "SELECT * FROM orders WHERE id = $1",
+            rows_affected = 0u64,
+            rows_returned = 1u64,
+            elapsed_secs = 0.002,
+        );
+        let mut properties = BasicProperties::default();
+        properties.with_headers(events::trace_headers(&current_context()));
+        Json(worker::trace_context(Some(&properties)))
+    }
+
+    #[tokio::test]
+    async fn request_and_query_spans_are_exported_and_context_reaches_amqp() {
+        global::set_text_map_propagator(TraceContextPropagator::new());
+        let exporter = InMemorySpanExporter::default();
+        let provider = TracerProvider::builder().with_simple_exporter(exporter.clone()).build();
+        let _subscriber = tracing_subscriber::registry().with(trace_layers(&provider)).set_default();
+
+        let app = Router::new()
+            .route("/orders/:id", get(record_event))
+            .route_layer(from_fn(logging::capture_route))
+            .layer(from_fn(logging::log_requests));
+        let response = app.oneshot(Request::get("/orders/7").body(Body::empty()).unwrap()).await.unwrap();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let amqp_headers: HashMap<String, String> = serde_json::from_slice(&body).unwrap();
+
+        let spans = exporter.get_finished_spans().unwrap();
+        let request = spans.iter().find(|span| span.name == "GET /orders/:id").expect("request span");
+        assert_eq!(request.span_kind, SpanKind::Server);
+        let query = spans.iter().find(|span| span.name == "SELECT * FROM orders …").expect("query span");
+        assert_eq!(query.span_kind, SpanKind::Client);
+        assert_eq!(query.span_context.trace_id(), request.span_context.trace_id());
+        assert_eq!(query.parent_span_id, request.span_context.span_id());
+
+        let traceparent = format!("00-{}-{}-01", request.span_context.trace_id(), request.span_context.span_id());
+        assert_eq!(amqp_headers.get("traceparent"), Some(&traceparent));
+    }
+}
@@ -0,0 +1,69 @@
+use std::net::SocketAddr;
+use std::time::{Duration, SystemTime};
+
+use axum::extract::{Host, State};
+use axum::http::{StatusCode, Uri};
+use axum::response::{IntoResponse, Redirect, Response};
+use axum::Router;
+use axum_server::tls_rustls::RustlsConfig;
+use tokio::net::TcpListener;
+use tracing::{error, info, warn};
+
+use crate::shutdown::Shutdown;
+
+fn modified_at(path: &str) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|meta| meta.modified()).ok()
+}
+
+// PEM certificate chain and private key
+pub async fn load(cert_path: &str, key_path: &str) -> std::io::Result<RustlsConfig> {
+    // Lettre's rustls also links ring; whichever installs first wins, and either is fine
+    let _ = rustls::crypto::ring::default_provider().install_default();
+    RustlsConfig::from_pem_file(cert_path, key_path).await
+}
+
+// Re-reads the certificate and key once either file changes, e.g. after a renewal. New
+// connections get the new certificate; a pair that doesn't load keeps the current one.
+pub async fn watch(tls: RustlsConfig, cert_path: String, key_path: String, every: Duration, shutdown: Shutdown) {
+    let mut seen = (modified_at(&cert_path), modified_at(&key_path));
+    let mut interval = tokio::time::interval(every);
+    loop {
+        tokio::select! {
+            _ = interval.tick() => {}
+            _ = shutdown.requested() => return,
+        }
+        let modified = (modified_at(&cert_path), modified_at(&key_path));
+        if modified.0.is_none() || modified.1.is_none() || modified == seen {
+            continue;
+        }
+        seen = modified;
+        match tls.reload_from_pem_file(&cert_path, &key_path).await {
+            Ok(()) => info!(cert = %cert_path, "Reloaded TLS certificate"),
+            Err(e) => warn!(error = %e, "Keeping current TLS certificate"),
+        }
+    }
+}
+
+// Sends plain HTTP requests to the same host and path over HTTPS
+async fn to_https(State(https_port): State<u16>, Host(host): Host, uri: Uri) -> Response {
+    // Host may carry the plain listener's port, or be an IPv6 literal in brackets
+    let hostname = match host.rsplit_once(':') {
+        Some((name, port)) if !name.is_empty() && port.chars().all(|c| c.is_ascii_digit()) => name,
+        _ => host.as_str(),
+    };
+    let authority = if https_port == 443 { hostname.to_string() } else { format!("{}:{}", hostname, https_port) };
+    let path = uri.path_and_query().map_or("/", |path| path.as_str());
+    match format!("https://{}{}", authority, path).parse::<Uri>() {
+        Ok(target) => Redirect::permanent(&target.to_string()).into_response(),
+        Err(_) => StatusCode::BAD_REQUEST.into_response(),
+    }
+}
+
+pub async fn run_redirect(listener: TcpListener, https_addr: SocketAddr, shutdown: Shutdown) {
+    let addr = listener.local_addr().map(|addr| addr.to_string()).unwrap_or_default();
+    let app = Router::new().fallback(to_https).with_state(https_addr.port());
+    info!("↪️ Redirecting http://{} to HTTPS", addr);
+    if let Err(e) = axum::serve(listener, app).with_graceful_shutdown(shutdown.requested()).await {
+        error!("HTTPS redirect listener failed: {}", e);
+    }
+}
@@ -0,0 +1,373 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+use amqprs::channel::{
+    BasicAckArguments, BasicConsumeArguments, BasicPublishArguments, BasicQosArguments, Channel,
+    ExchangeDeclareArguments, QueueBindArguments, QueueDeclareArguments,
+};
+use amqprs::connection::{Connection, OpenConnectionArguments};
+use amqprs::{BasicProperties, FieldTable, FieldValue};
+use async_trait::async_trait;
+use serde::de::DeserializeOwned;
+use tokio::task::JoinSet;
+use tracing::{error, info, info_span, warn, Instrument};
+
+use crate::events::{DomainEvent, ReceivedEvent};
+use crate::shutdown::Shutdown;
+use crate::telemetry;
+
+const ATTEMPTS_HEADER: &str = "x-attempts";
+const ERROR_HEADER: &str = "x-last-error";
+const TRACEPARENT_HEADER: &str = "traceparent";
+const TRACESTATE_HEADER: &str = "tracestate";
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+#[derive(Debug)]
+pub enum HandlerError {
+    // Worth trying again later, e.g. the mail relay is down
+    Retry(String),
+    // Will never succeed; goes straight to the dead-letter queue
+    Fatal(String),
+}
+
+impl fmt::Display for HandlerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HandlerError::Retry(reason) => write!(f, "retryable: {}", reason),
+            HandlerError::Fatal(reason) => write!(f, "fatal: {}", reason),
+        }
+    }
+}
+
+// Handles one event type from its own durable queue. The queue is bound to the
+// events exchange with the event type as routing key.
+#[async_trait]
+pub trait Handler: Send + Sync + 'static {
+    type Event: DomainEvent + DeserializeOwned + Send;
+    const QUEUE: &'static str;
+
+    async fn handle(&self, event: ReceivedEvent<Self::Event>) -> Result<(), HandlerError>;
+}
+
+// Object-safe view of a handler so handlers for different event types can share a registry
+#[async_trait]
+trait Dispatch: Send + Sync {
+    fn queue(&self) -> &'static str;
+    fn routing_key(&self) -> &'static str;
+    async fn dispatch(&self, body: &[u8]) -> Result<(), HandlerError>;
+}
+
+struct Typed<H>(H);
+
+#[async_trait]
+impl<H: Handler> Dispatch for Typed<H> {
+    fn queue(&self) -> &'static str {
+        H::QUEUE
+    }
+
+    fn routing_key(&self) -> &'static str {
+        <H::Event as DomainEvent>::TYPE
+    }
+
+    async fn dispatch(&self, body: &[u8]) -> Result<(), HandlerError> {
+        let event: ReceivedEvent<H::Event> = serde_json::from_slice(body)
+            .map_err(|e| HandlerError::Fatal(format!("malformed event: {}", e)))?;
+        if event.event_type != self.routing_key() {
+            return Err(HandlerError::Fatal(format!("unexpected event type {}", event.event_type)));
+        }
+
+        let id = event.id;
+        self.0.handle(event).await.map_err(|e| match e {
+            HandlerError::Retry(reason) => HandlerError::Retry(format!("event {}: {}", id, reason)),
+            HandlerError::Fatal(reason) => HandlerError::Fatal(format!("event {}: {}", id, reason)),
+        })
+    }
+}
+
+pub struct WorkerSettings {
+    pub amqp_url: String,
+    pub exchange: String,
+    pub prefetch: u16,
+    pub max_attempts: u32,
+    pub retry_delay: Duration,
+}
+
+// Consumer runtime. For every handler queue `q` it declares:
+//   q        bound to the events exchange, rejected messages dead-letter to q.dead
+//   q.retry  holds failed messages for `retry_delay`, then dead-letters them back to q
+//   q.dead   messages that failed `max_attempts` times or can never succeed
+pub struct Worker {
+    settings: WorkerSettings,
+    handlers: Vec<Arc<dyn Dispatch>>,
+}
+
+fn field_name(name: &str) -> amqprs::FieldName {
+    name.try_into().expect("header names are short")
+}
+
+fn attempts(properties: Option<&BasicProperties>) -> u32 {
+    let value = properties
+        .and_then(|p| p.headers())
+        .and_then(|headers| headers.get(&field_name(ATTEMPTS_HEADER)));
+    match value {
+        Some(FieldValue::I(n)) => u32::try_from(*n).unwrap_or(0),
+        Some(FieldValue::l(n)) => u32::try_from(*n).unwrap_or(0),
+        _ => 0,
+    }
+}
+
+// Where a handled message goes next with the reason recorded on the copy: nowhere once
+// handled, back through `q.retry` while retries are left, otherwise to `q.dead`
+fn route(queue: &str, attempt: u32, max_attempts: u32, outcome: &Result<(), HandlerError>) -> Option<(String, String)> {
+    match outcome {
+        Ok(()) => None,
+        Err(HandlerError::Retry(reason)) if attempt < max_attempts => Some((format!("{}.retry", queue), reason.clone())),
+        Err(e) => Some((format!("{}.dead", queue), e.to_string())),
+    }
+}
+
+// The publisher's trace context, copied from the message headers
+pub fn trace_context(properties: Option<&BasicProperties>) -> HashMap<String, String> {
+    let Some(headers) = properties.and_then(|p| p.headers()) else {
+        return HashMap::new();
+    };
+    [TRACEPARENT_HEADER, TRACESTATE_HEADER]
+        .into_iter()
+        .filter_map(|name| match headers.get(&field_name(name)) {
+            Some(FieldValue::S(value)) => Some((name.to_string(), value.to_string())),
+            _ => None,
+        })
+        .collect()
+}
+
+impl Worker {
+    pub fn new(settings: WorkerSettings) -> Self {
+        Self { settings, handlers: Vec::new() }
+    }
+
+    pub fn register<H: Handler>(&mut self, handler: H) {
+        self.handlers.push(Arc::new(Typed(handler)));
+    }
+
+    // Runs until shutdown, reconnecting whenever the broker goes away. On shutdown each
+    // consumer finishes the message it is handling; prefetched messages that were never
+    // started are returned to the queue when the connection closes.
+    pub async fn run(self, shutdown: Shutdown) {
+        let worker = Arc::new(self);
+        loop {
+            match worker.consume_all(&shutdown).await {
+                Ok(()) if shutdown.is_requested() => {
+                    info!("Worker stopped");
+                    return;
+                }
+                Ok(()) => warn!("RabbitMQ consumers stopped, reconnecting"),
+                Err(e) => error!("RabbitMQ worker error: {}", e),
+            }
+            tokio::select! {
+                _ = tokio::time::sleep(RECONNECT_DELAY) => {}
+                _ = shutdown.requested() => return,
+            }
+        }
+    }
+
+    async fn consume_all(self: &Arc<Self>, shutdown: &Shutdown) -> Result<(), amqprs::error::Error> {
+        let connection = Connection::open(&OpenConnectionArguments::try_from(self.settings.amqp_url.as_str())?).await?;
+
+        let mut consumers = JoinSet::new();
+        for handler in &self.handlers {
+            let channel = connection.open_channel(None).await?;
+            self.declare(&channel, handler.as_ref()).await?;
+            channel
+                .basic_qos(BasicQosArguments::new(0, self.settings.prefetch, false))
+                .await?;
+            let (_, messages) = channel
+                .basic_consume_rx(BasicConsumeArguments::new(handler.queue(), "").manual_ack(true).finish())
+                .await?;
+            info!(event_type = handler.routing_key(), queue = handler.queue(), "Consuming");
+
+            let worker = Arc::clone(self);
+            let handler = Arc::clone(handler);
+            let stopping = shutdown.requested();
+            consumers.spawn(async move {
+                let mut messages = messages;
+                tokio::pin!(stopping);
+                loop {
+                    let message = tokio::select! {
+                        message = messages.recv() => message,
+                        _ = &mut stopping => None,
+                    };
+                    let Some(message) = message else {
+                        break;
+                    };
+                    let (Some(deliver), Some(content)) = (message.deliver, message.content) else {
+                        continue;
+                    };
+                    let processed = worker
+                        .process(&channel, handler.as_ref(), deliver.delivery_tag(), message.basic_properties, content)
+                        .await;
+                    if processed.is_err() {
+                        break;
+                    }
+                }
+                channel
+            });
+        }
+
+        // A consumer only ends when its channel or the connection is gone, when it can't move a
+        // failed message on, or on shutdown, in which case the others finish their current message
+        consumers.join_next().await;
+        if shutdown.is_requested() {
+            while consumers.join_next().await.is_some() {}
+        } else {
+            consumers.abort_all();
+        }
+        let _ = connection.close().await;
+
+        Ok(())
+    }
+
+    async fn declare(&self, channel: &Channel, handler: &dyn Dispatch) -> Result<(), amqprs::error::Error> {
+        let queue = handler.queue();
+        let retry_queue = format!("{}.retry", queue);
+        let dead_queue = format!("{}.dead", queue);
+
+        channel
+            .exchange_declare(ExchangeDeclareArguments::new(&self.settings.exchange, "topic").durable(true).finish())
+            .await?;
+
+        channel
+            .queue_declare(QueueDeclareArguments::durable_client_named(&dead_queue))
+            .await?;
+
+        let mut retry_args = FieldTable::new();
+        retry_args.insert(field_name("x-message-ttl"), FieldValue::I(self.settings.retry_delay.as_millis() as i32));
+        retry_args.insert(field_name("x-dead-letter-exchange"), "".into());
+        retry_args.insert(field_name("x-dead-letter-routing-key"), queue.into());
+        channel
+            .queue_declare(QueueDeclareArguments::durable_client_named(&retry_queue).arguments(retry_args).finish())
+            .await?;
+
+        let mut queue_args = FieldTable::new();
+        queue_args.insert(field_name("x-dead-letter-exchange"), "".into());
+        queue_args.insert(field_name("x-dead-letter-routing-key"), dead_queue.as_str().into());
+        channel
+            .queue_declare(QueueDeclareArguments::durable_client_named(queue).arguments(queue_args).finish())
+            .await?;
+        channel
+            .queue_bind(QueueBindArguments::new(queue, &self.settings.exchange, handler.routing_key()))
+            .await?;
+
+        Ok(())
+    }
+
+    async fn process(
+        &self,
+        channel: &Channel,
+        handler: &dyn Dispatch,
+        delivery_tag: u64,
+        properties: Option<BasicProperties>,
+        content: Vec<u8>,
+    ) -> Result<(), amqprs::error::Error> {
+        let attempt = attempts(properties.as_ref()) + 1;
+
+        let span = info_span!(
+            "consume",
+            otel.name = format!("{} process", handler.queue()),
+            otel.kind = "consumer",
+            messaging.system = "rabbitmq",
+            messaging.destination.name = handler.queue(),
+            attempt,
+        );
+        telemetry::set_parent_from_map(&span, &trace_context(properties.as_ref()));
+
+        let outcome = handler.dispatch(&content).instrument(span).await;
+        let target = route(handler.queue(), attempt, self.settings.max_attempts, &outcome);
+        match &target {
+            Some((routing_key, reason)) if routing_key.ends_with(".retry") => {
+                warn!(queue = handler.queue(), attempt, reason = %reason, "Handler failed, retrying");
+            }
+            Some((_, reason)) => {
+                error!(queue = handler.queue(), attempt, error = %reason, "Handler failed, dead-lettering");
+            }
+            None => {}
+        }
+
+        // The copy is published before the original is acked, so a message can be handled
+        // twice but never dropped. If that fails the original is left unacked and the consumer
+        // stops: the broker requeues it once the connection closes, and `run` waits
+        // RECONNECT_DELAY before consuming again instead of redelivering it straight away.
+        if let Some((routing_key, reason)) = target {
+            let mut headers = properties.as_ref().and_then(|p| p.headers()).cloned().unwrap_or_default();
+            headers.insert(field_name(ATTEMPTS_HEADER), FieldValue::I(attempt as i32));
+            headers.insert(field_name(ERROR_HEADER), reason.as_str().into());
+
+            let mut copy = properties.unwrap_or_default();
+            copy.with_headers(headers).with_persistence(true);
+
+            if let Err(e) = channel
+                .basic_publish(copy, content, BasicPublishArguments::new("", &routing_key))
+                .await
+            {
+                error!("Error moving message to {}, reconnecting: {}", routing_key, e);
+                return Err(e);
+            }
+        }
+
+        if let Err(e) = channel.basic_ack(BasicAckArguments::new(delivery_tag, false)).await {
+            error!("Error acking message on {}: {}", handler.queue(), e);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use amqprs::{BasicProperties, FieldTable, FieldValue};
+
+    use super::{attempts, field_name, route, HandlerError, ATTEMPTS_HEADER};
+
+    fn with_attempts(value: FieldValue) -> BasicProperties {
+        let mut headers = FieldTable::new();
+        headers.insert(field_name(ATTEMPTS_HEADER), value);
+        let mut properties = BasicProperties::default();
+        properties.with_headers(headers);
+        properties
+    }
+
+    #[test]
+    fn attempts_are_read_from_int_and_long_headers() {
+        assert_eq!(attempts(None), 0);
+        assert_eq!(attempts(Some(&BasicProperties::default())), 0);
+        assert_eq!(attempts(Some(&with_attempts(FieldValue::I(2)))), 2);
+        assert_eq!(attempts(Some(&with_attempts(FieldValue::l(3)))), 3);
+        assert_eq!(attempts(Some(&with_attempts(FieldValue::I(-1)))), 0);
+        assert_eq!(attempts(Some(&with_attempts("4".into()))), 0);
+    }
+
+    #[test]
+    fn handled_messages_go_nowhere() {
+        assert_eq!(route("otp", 1, 5, &Ok(())), None);
+    }
+
+    #[test]
+    fn retryable_failures_retry_until_attempts_run_out() {
+        let error = Err(HandlerError::Retry("smtp down".to_string()));
+        assert_eq!(route("otp", 1, 5, &error), Some(("otp.retry".to_string(), "smtp down".to_string())));
+        assert_eq!(route("otp", 4, 5, &error), Some(("otp.retry".to_string(), "smtp down".to_string())));
+        assert_eq!(
+            route("otp", 5, 5, &error),
+            Some(("otp.dead".to_string(), "retryable: smtp down".to_string()))
+        );
+    }
+
+    #[test]
+    fn fatal_failures_are_dead_lettered_at_once() {
+        let error = Err(HandlerError::Fatal("malformed event".to_string()));
+        assert_eq!(
+            route("otp", 1, 5, &error),
+            Some(("otp.dead".to_string(), "fatal: malformed event".to_string()))
+        );
+    }
+}